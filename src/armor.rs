@@ -0,0 +1,109 @@
+/// Wraps binary data, like an encrypted export, in text that can be piped and copy-pasted.
+const BEGIN_LINE: &'static str = "-----BEGIN ROOSTER ENCRYPTED EXPORT-----";
+const END_LINE: &'static str = "-----END ROOSTER ENCRYPTED EXPORT-----";
+
+/// How many base64 characters per line, like PEM files.
+const LINE_LEN: usize = 64;
+
+const BASE64_ALPHABET: &'static [u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes bytes in base64 (RFC 4648), with padding.
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(BASE64_ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+/// Decodes base64, ignoring whitespace. Returns `None` on characters outside of the alphabet.
+pub fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len() / 4 * 3);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in s.chars().filter(|c| !c.is_whitespace()) {
+        if c == '=' {
+            break;
+        }
+        let value = BASE64_ALPHABET.iter().position(|&a| a as char == c)? as u32;
+        buffer = buffer << 6 | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+pub fn armor(bytes: &[u8]) -> String {
+    let encoded = encode_base64(bytes);
+    let mut armored = format!("{}\n", BEGIN_LINE);
+    for line in encoded.as_bytes().chunks(LINE_LEN) {
+        // Base64 is ASCII, so any split is valid UTF-8.
+        armored.push_str(std::str::from_utf8(line).unwrap());
+        armored.push('\n');
+    }
+    armored.push_str(END_LINE);
+    armored.push('\n');
+    armored
+}
+
+pub fn is_armored(input: &[u8]) -> bool {
+    input.starts_with(BEGIN_LINE.as_bytes())
+}
+
+/// Reverses `armor`. Returns `None` if the input is not armored or was damaged.
+pub fn dearmor(input: &[u8]) -> Option<Vec<u8>> {
+    let text = std::str::from_utf8(input).ok()?.trim();
+    let body = text.strip_prefix(BEGIN_LINE)?.strip_suffix(END_LINE)?;
+    decode_base64(body)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{armor, dearmor, decode_base64, encode_base64, is_armored};
+
+    #[test]
+    fn test_encode_base64() {
+        // Test vectors from RFC 4648
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64("Zg==").unwrap(), b"f");
+        assert_eq!(decode_base64("Zm8=").unwrap(), b"fo");
+        assert_eq!(decode_base64("Zm9v\nYmFy").unwrap(), b"foobar");
+        assert_eq!(decode_base64("Zm9v!"), None);
+    }
+
+    #[test]
+    fn test_armor() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let armored = armor(&bytes);
+        assert!(is_armored(armored.as_bytes()));
+        assert!(armored
+            .lines()
+            .all(|line| line.len() <= 64 || line.starts_with("-----")));
+        assert_eq!(dearmor(armored.as_bytes()).unwrap(), bytes);
+
+        assert!(!is_armored(&bytes));
+        assert_eq!(dearmor(&bytes), None);
+    }
+}
@@ -1,105 +1,386 @@
+use crate::commands::CommandError;
+use crate::list;
 use crate::password;
 use rclio::{CliInputOutput, OutputType};
 use rtoolbox::safe_string::SafeString;
+use serde::{Deserialize, Serialize};
 
 use std::ops::Deref;
 
 // On Windows and Mac, we'll use the native solutions provided by the OS libraries
 #[cfg(any(windows, target_os = "macos"))]
-pub fn copy_to_clipboard(s: &SafeString) -> Result<(), ()> {
+pub fn copy_to_clipboard(s: &SafeString) -> Result<&'static str, ()> {
     use clipboard::ClipboardContext;
     use clipboard::ClipboardProvider;
 
     let mut context: ClipboardContext = ClipboardProvider::new().map_err(|_| ())?;
     context.set_contents(s.deref().to_owned()).map_err(|_| ())?;
-    Ok(())
+    Ok("native API")
 }
 
 // On UNIX, the most stable way to copy to the clipboard is using one of the existing
-// and battle tested tools: xsel and xclip.
+// and battle tested tools: wl-copy, xsel and xclip. When one fails, like wl-copy can on
+// some XWayland setups, we try the next one.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn copy_to_clipboard(s: &SafeString) -> Result<&'static str, ()> {
+    use crate::quale::which;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    // The password goes to the tool's stdin. Command line arguments, including those of a
+    // shell running a pipeline, can be read by any user on the machine, e.g. with `ps`.
+    fn copy_with(backend: &'static str, password: &SafeString) -> Result<&'static str, ()> {
+        let program = which(backend).ok_or(())?;
+        let args: &[&str] = match backend {
+            "xsel" => &["-ib"],
+            "xclip" => &["-selection", "clipboard"],
+            _ => &[],
+        };
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|_| ())?;
+
+        let written = match child.stdin.take() {
+            // Dropping stdin closes it, so the tool knows the password is complete.
+            Some(mut stdin) => stdin.write_all(password.deref().as_bytes()).is_ok(),
+            None => false,
+        };
+        if child.wait().map_err(|_| ())?.success() && written {
+            Ok(backend)
+        } else {
+            Err(())
+        }
+    }
+
+    clipboard_backends()
+        .into_iter()
+        .find_map(|backend| copy_with(backend, s).ok())
+        .ok_or(())
+}
+
+/// The clipboard backends to try, in order.
+#[cfg(any(windows, target_os = "macos"))]
+pub fn clipboard_backends() -> Vec<&'static str> {
+    vec!["native API"]
+}
+
+/// The clipboard tools that are installed, in the order `copy_to_clipboard` tries them.
 #[cfg(all(unix, not(target_os = "macos")))]
-pub fn copy_to_clipboard(s: &SafeString) -> Result<(), ()> {
+pub fn clipboard_backends() -> Vec<&'static str> {
     use crate::quale::which;
-    use crate::shell_escape;
     use std::env;
-    use std::process::Command;
 
-    let password = SafeString::from_string(shell_escape::escape(s.deref().into()).into());
+    let wayland = match env::var_os("XDG_SESSION_TYPE") {
+        Some(s) => s == "wayland",
+        None => false,
+    };
+    let installed = ["wl-copy", "xsel", "xclip"]
+        .iter()
+        .cloned()
+        .filter(|backend| which(backend).is_some())
+        .collect();
+    order_clipboard_backends(installed, wayland)
+}
 
-    fn wayland_clipboards(password: &SafeString) -> Result<(), ()> {
-        match which("wl-copy") {
-            Some(wl_copy) => {
-                let shell = format!(
-                    "printf '%s' {} | {} 2> /dev/null",
-                    password.deref(),
-                    wl_copy.to_string_lossy()
-                );
-                if Command::new("sh")
-                    .args(&["-c", shell.as_str()])
-                    .status()
-                    .map_err(|_| ())?
-                    .success()
-                {
-                    Ok(())
-                } else {
-                    Err(())
-                }
+/// Puts the backend that matches the session first. Outside of Wayland, wl-copy is only a
+/// last resort.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn order_clipboard_backends(installed: Vec<&'static str>, wayland: bool) -> Vec<&'static str> {
+    let (wayland_backends, x11_backends): (Vec<_>, Vec<_>) = installed
+        .into_iter()
+        .partition(|&backend| backend == "wl-copy");
+    if wayland {
+        wayland_backends.into_iter().chain(x11_backends).collect()
+    } else {
+        x11_backends.into_iter().chain(wayland_backends).collect()
+    }
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+pub fn paste_from_clipboard() -> Result<SafeString, ()> {
+    use clipboard::ClipboardContext;
+    use clipboard::ClipboardProvider;
+
+    let mut context: ClipboardContext = ClipboardProvider::new().map_err(|_| ())?;
+    context
+        .get_contents()
+        .map(SafeString::from_string)
+        .map_err(|_| ())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn paste_from_clipboard() -> Result<SafeString, ()> {
+    use crate::quale::which;
+    use std::env;
+    use std::process::{Command, Stdio};
+
+    fn paste_with(program: &str, args: &[&str]) -> Result<SafeString, ()> {
+        let program = which(program).ok_or(())?;
+        let output = Command::new(program)
+            .args(args)
+            .stderr(Stdio::null())
+            .output()
+            .map_err(|_| ())?;
+        if !output.status.success() {
+            return Err(());
+        }
+        String::from_utf8(output.stdout)
+            .map(SafeString::from_string)
+            .map_err(|_| ())
+    }
+
+    if let Some(s) = env::var_os("XDG_SESSION_TYPE") {
+        if s == "wayland" {
+            if let Ok(contents) = paste_with("wl-paste", &["--no-newline"]) {
+                return Ok(contents);
             }
-            None => Err(()),
         }
     }
 
-    fn x11_clipboards(password: &SafeString) -> Result<(), ()> {
-        match which("xsel") {
-            Some(xsel) => {
-                let shell = format!(
-                    "printf '%s' {} | {} -ib 2> /dev/null",
-                    password.deref(),
-                    xsel.to_string_lossy()
+    paste_with("xsel", &["-ob"])
+        .or_else(|_| paste_with("xclip", &["-o", "-selection", "clipboard"]))
+}
+
+/// When and how to restore the clipboard after copying a password, from `--clear-after`.
+#[derive(Clone, Copy)]
+pub struct ClearAfter {
+    pub seconds: u64,
+    /// Let the user know once the clipboard is restored, from `--notify`.
+    pub notify: bool,
+}
+
+impl ClearAfter {
+    pub fn from_matches(matches: &clap::ArgMatches) -> Option<ClearAfter> {
+        matches
+            .get_one::<u64>("clear-after")
+            .map(|&seconds| ClearAfter {
+                seconds,
+                notify: matches.get_flag("notify"),
+            })
+    }
+}
+
+/// What the background process needs to know to restore the clipboard.
+#[derive(Serialize, Deserialize)]
+struct ClipboardRestore {
+    copied: SafeString,
+    previous: Option<SafeString>,
+    #[serde(default)]
+    notify: bool,
+}
+
+/// Copies to the clipboard and, if `clear_after` is set, restores the previous clipboard
+/// contents after that many seconds. Returns the clipboard backend that was used.
+///
+/// The restore happens in a detached background process (`rooster clear-clipboard`), so
+/// Rooster can exit right away. The secret is handed over through a pipe, never through
+/// the command line. If something else was copied in the meantime, it is left alone.
+///
+/// Platform limitations:
+/// - X11: works with xsel or xclip. Clipboard managers may keep their own copy.
+/// - Wayland: needs wl-paste to read the clipboard. Some compositors only allow reading
+///   the clipboard from the focused window, in which case the clipboard is cleared rather
+///   than restored.
+/// - macOS and Windows: uses the native clipboard APIs, which work from the background.
+///   Clipboard history (Windows+V) and sync features are outside of Rooster's control.
+///
+/// Programs that embed Rooster as a library, test harnesses included, don't know the
+/// `clear-clipboard` subcommand, so they must point `ROOSTER_CLIPBOARD_HELPER` at a Rooster
+/// executable. Without it, `clear_after` can't be honoured, so nothing is copied and this
+/// returns an error.
+pub fn copy_to_clipboard_with_timeout(
+    s: &SafeString,
+    clear_after: Option<ClearAfter>,
+) -> Result<&'static str, ()> {
+    // Find the helper first, a secret that can't be cleared later isn't copied at all.
+    let helper = match clear_after {
+        Some(_) => Some(clipboard_helper().ok_or(())?),
+        None => None,
+    };
+    let previous = match clear_after {
+        Some(_) => paste_from_clipboard().ok(),
+        None => None,
+    };
+
+    let backend = copy_to_clipboard(s)?;
+
+    if let (Some(clear_after), Some(helper)) = (clear_after, helper) {
+        spawn_clipboard_restore(&helper, s, previous, clear_after)?;
+    }
+
+    Ok(backend)
+}
+
+/// Says which clipboard backend was used, and which ones failed before it, for `--verbose`.
+pub fn report_clipboard_backend(copied: &Result<&'static str, ()>, io: &mut impl CliInputOutput) {
+    let backends = clipboard_backends();
+    match copied {
+        Ok(backend) => {
+            let failed: Vec<&str> = backends
+                .iter()
+                .cloned()
+                .take_while(|b| b != backend)
+                .collect();
+            if failed.is_empty() {
+                io.info(format!("Copied with {}.", backend), OutputType::Error);
+            } else {
+                io.info(
+                    format!(
+                        "Copied with {}, after {} failed.",
+                        backend,
+                        failed.join(", ")
+                    ),
+                    OutputType::Error,
+                );
+            }
+            if COPIES_VIA_STDIN {
+                io.info(
+                    format!(
+                        "{} got the secret on its stdin, so it never showed up in the process list.",
+                        backend
+                    ),
+                    OutputType::Error,
                 );
-                if Command::new("sh")
-                    .args(&["-c", shell.as_str()])
-                    .status()
-                    .map_err(|_| ())?
-                    .success()
-                {
-                    Ok(())
-                } else {
-                    Err(())
-                }
             }
-            None => match which("xclip") {
-                Some(xclip) => {
-                    let shell = format!(
-                        "printf '%s' {} | {} -selection clipboard 2> /dev/null",
-                        password.deref(),
-                        xclip.to_string_lossy()
-                    );
-                    if Command::new("sh")
-                        .args(&["-c", shell.as_str()])
-                        .status()
-                        .map_err(|_| ())?
-                        .success()
-                    {
-                        Ok(())
-                    } else {
-                        Err(())
-                    }
-                }
-                None => Err(()),
-            },
         }
+        Err(_) if backends.is_empty() => io.info(
+            format!(
+                "No clipboard backend is installed (Rooster supports {}).",
+                CLIPBOARD_BACKENDS
+            ),
+            OutputType::Error,
+        ),
+        Err(_) => io.info(
+            format!(
+                "No clipboard backend worked (tried {}).",
+                backends.join(", ")
+            ),
+            OutputType::Error,
+        ),
     }
+}
 
-    match env::var_os("XDG_SESSION_TYPE") {
-        Some(s) if s == "wayland" => {
-            let s = wayland_clipboards(&password);
-            match s {
-                Ok(_) => Ok(()),
-                Err(_) => x11_clipboards(&password),
-            }
+#[cfg(any(windows, target_os = "macos"))]
+const CLIPBOARD_BACKENDS: &'static str = "the native API";
+
+#[cfg(all(unix, not(target_os = "macos")))]
+const CLIPBOARD_BACKENDS: &'static str = "wl-copy, xsel and xclip";
+
+/// Whether `copy_to_clipboard` runs an external tool, which then reads the secret on stdin.
+#[cfg(any(windows, target_os = "macos"))]
+const COPIES_VIA_STDIN: bool = false;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+const COPIES_VIA_STDIN: bool = true;
+
+/// Environment variable with the Rooster executable that restores the clipboard, for programs
+/// that embed Rooster.
+pub const CLIPBOARD_HELPER_ENV_VAR: &'static str = "ROOSTER_CLIPBOARD_HELPER";
+
+/// The executable to run `clear-clipboard` with. The running program is only used if it is
+/// Rooster itself, anything else would be started again with arguments it doesn't expect.
+fn clipboard_helper() -> Option<std::path::PathBuf> {
+    if let Some(helper) = std::env::var_os(CLIPBOARD_HELPER_ENV_VAR) {
+        return Some(std::path::PathBuf::from(helper));
+    }
+    let exe = std::env::current_exe().ok()?;
+    if exe.file_stem().map_or(false, |stem| stem == "rooster") {
+        Some(exe)
+    } else {
+        None
+    }
+}
+
+fn spawn_clipboard_restore(
+    helper: &std::path::Path,
+    copied: &SafeString,
+    previous: Option<SafeString>,
+    clear_after: ClearAfter,
+) -> Result<(), ()> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let restore = ClipboardRestore {
+        copied: copied.clone(),
+        previous,
+        notify: clear_after.notify,
+    };
+    let restore_json = SafeString::from_string(serde_json::to_string(&restore).map_err(|_| ())?);
+
+    let mut child = Command::new(helper)
+        .args(&["clear-clipboard", clear_after.seconds.to_string().as_str()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        // The terminal bell is rung on stderr, so keep it attached to the terminal.
+        .stderr(if clear_after.notify {
+            Stdio::inherit()
+        } else {
+            Stdio::null()
+        })
+        .spawn()
+        .map_err(|_| ())?;
+
+    let mut stdin = child.stdin.take().ok_or(())?;
+    stdin
+        .write_all(restore_json.as_bytes())
+        .and_then(|_| stdin.write_all(b"\n"))
+        .map_err(|_| ())
+}
+
+/// Runs in the background process spawned by `copy_to_clipboard_with_timeout`.
+pub fn restore_clipboard_after(
+    timeout_seconds: u64,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let restore_json = SafeString::from_string(io.read_line().map_err(|_| CommandError::Io)?);
+    let restore: ClipboardRestore =
+        serde_json::from_str(restore_json.deref()).map_err(|_| CommandError::InvalidInput)?;
+
+    std::thread::sleep(std::time::Duration::from_secs(timeout_seconds));
+
+    if let Ok(current) = paste_from_clipboard() {
+        if current != restore.copied {
+            // The user copied something else in the meantime, don't clobber it.
+            return Ok(());
         }
-        _ => x11_clipboards(&password),
+    }
+
+    let previous = restore
+        .previous
+        .unwrap_or_else(|| SafeString::from_string(String::new()));
+    copy_to_clipboard(&previous).map_err(|_| CommandError::Io)?;
+
+    if restore.notify {
+        notify_clipboard_restored(io);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "notifications")]
+fn notify_clipboard_restored(io: &mut impl CliInputOutput) {
+    let shown = notify_rust::Notification::new()
+        .summary("Rooster")
+        .body("Your password is no longer in the clipboard.")
+        .show();
+    if shown.is_err() {
+        io.write("\x07", OutputType::Error);
+    }
+}
+
+#[cfg(not(feature = "notifications"))]
+fn notify_clipboard_restored(io: &mut impl CliInputOutput) {
+    io.write("\x07", OutputType::Error);
+}
+
+pub fn clear_after_message(clear_after: Option<ClearAfter>) -> String {
+    match clear_after {
+        Some(clear_after) => format!(", cleared in {} seconds", clear_after.seconds),
+        None => String::new(),
     }
 }
 
@@ -113,15 +394,37 @@ pub fn paste_keys() -> &'static str {
     "Ctrl+V"
 }
 
+/// Passwords protected with `rooster protect` are shown even without `--show`.
+pub fn must_show(
+    show: bool,
+    password: &password::v2::Password,
+    io: &mut impl CliInputOutput,
+) -> bool {
+    if password.no_clipboard && !show {
+        io.warning(
+            format!(
+                "The password for {} is protected, so I never copy it to your clipboard. \
+                 Make sure nobody is looking at your screen.",
+                password.name
+            ),
+            OutputType::Error,
+        );
+        return true;
+    }
+    show
+}
+
 pub fn confirm_password_retrieved(
     show: bool,
+    clear_after: Option<ClearAfter>,
+    verbose: bool,
     password: &password::v2::Password,
     io: &mut impl CliInputOutput,
 ) {
     if show {
         io.success(
             format!("Alright! Here is your password for {}:", password.name),
-            OutputType::Standard,
+            OutputType::Error,
         );
         io.success(
             format!("Username: {}", password.username),
@@ -131,32 +434,94 @@ pub fn confirm_password_retrieved(
             format!("Password: {}", password.password.deref()),
             OutputType::Standard,
         );
+        if !password.notes.is_empty() {
+            io.success(
+                format!("Note: {}", password.notes.deref()),
+                OutputType::Standard,
+            );
+        }
+        let backup_codes = password.unused_backup_codes();
+        if !backup_codes.is_empty() {
+            let backup_codes: Vec<&str> = backup_codes.iter().map(|c| c.deref().as_str()).collect();
+            io.success(
+                format!("Backup codes: {}", backup_codes.join(" ")),
+                OutputType::Standard,
+            );
+        }
+        io.info(
+            format!("Created: {}", list::format_timestamp(password.created_at)),
+            OutputType::Standard,
+        );
+        io.info(
+            format!("Updated: {}", list::format_timestamp(password.updated_at)),
+            OutputType::Standard,
+        );
     } else {
-        if copy_to_clipboard(&password.password).is_err() {
+        let copied = copy_to_clipboard_with_timeout(&password.password, clear_after);
+        if verbose {
+            report_clipboard_backend(&copied, io);
+        }
+        if copied.is_err() {
             io.success(
                 format!(
                     "Hmm, I tried to copy your new password to your clipboard, but \
                      something went wrong. You can see it with `rooster get '{}' --show`",
                     password.name
                 ),
-                OutputType::Standard,
+                OutputType::Error,
             );
         } else {
             io.success(
                 format!("Alright! Here is your password for {}:", password.name),
-                OutputType::Standard,
+                OutputType::Error,
             );
             io.success(
                 format!("Username: {}", password.username),
-                OutputType::Standard,
+                OutputType::Error,
             );
             io.success(
                 format!(
-                    "Password: ******** (copied to clipboard, paste with {})",
-                    paste_keys()
+                    "Password: ******** (copied to clipboard, paste with {}{})",
+                    paste_keys(),
+                    clear_after_message(clear_after)
                 ),
-                OutputType::Standard,
+                OutputType::Error,
             );
+            if !password.notes.is_empty() {
+                io.info(
+                    "This password has a note, see it with --show.",
+                    OutputType::Error,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn test_order_clipboard_backends() {
+        use super::order_clipboard_backends;
+
+        assert_eq!(
+            order_clipboard_backends(vec!["wl-copy", "xsel", "xclip"], true),
+            vec!["wl-copy", "xsel", "xclip"]
+        );
+        assert_eq!(
+            order_clipboard_backends(vec!["wl-copy", "xsel", "xclip"], false),
+            vec!["xsel", "xclip", "wl-copy"]
+        );
+        assert_eq!(order_clipboard_backends(vec!["xclip"], true), vec!["xclip"]);
+    }
+
+    #[test]
+    fn test_clipboard_helper_is_not_the_test_harness() {
+        use super::{clipboard_helper, CLIPBOARD_HELPER_ENV_VAR};
+
+        // The test binary is not Rooster, so it must not be started as `clear-clipboard`.
+        if std::env::var_os(CLIPBOARD_HELPER_ENV_VAR).is_none() {
+            assert_eq!(clipboard_helper(), None);
         }
     }
 }
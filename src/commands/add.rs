@@ -1,44 +1,75 @@
-use crate::clip::{copy_to_clipboard, paste_keys};
+use crate::clip::{clear_after_message, copy_to_clipboard_with_timeout, paste_keys, ClearAfter};
+use crate::commands::CommandError;
+use crate::otp;
 use crate::password;
 use rclio::CliInputOutput;
 use rclio::OutputType;
+use rtoolbox::safe_string::SafeString;
 use std::ops::Deref;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
+    if matches.get_flag("batch") {
+        return add_batch(matches, store, io);
+    }
+
     let app_name = matches.get_one::<String>("app").unwrap();
     let username = matches.get_one::<String>("username").unwrap();
+    let clear_after = ClearAfter::from_matches(matches);
 
     if store.has_password(app_name.deref()) {
         io.error(
             "Woops, there is already an app with that name.",
             OutputType::Error,
         );
-        return Err(1);
+        return Err(CommandError::Duplicate);
+    }
+
+    let otp_secret = matches
+        .get_one::<String>("otp")
+        .map(|secret| SafeString::from_string(secret.clone()));
+    if let Some(ref secret) = otp_secret {
+        if !otp::is_valid_secret(secret) {
+            io.error(
+                "Woops, this TOTP secret is not valid base32.",
+                OutputType::Error,
+            );
+            return Err(CommandError::InvalidInput);
+        }
     }
 
     match io.prompt_password(format!("What password do you want for \"{}\"? ", app_name)) {
         Ok(password_as_string) => {
             let password_as_string_clipboard = password_as_string.clone();
-            let password =
+            let mut password =
                 password::v2::Password::new(app_name.clone(), username, password_as_string);
+            password.otp_secret = otp_secret;
+            apply_options(&mut password, matches);
             match store.add_password(password) {
                 Ok(_) => {
+                    if matches.get_flag("dry-run") {
+                        io.info(
+                            format!("I would have added a password for {}.", app_name),
+                            OutputType::Error,
+                        );
+                        return Ok(());
+                    }
+
                     if matches.get_flag("show") {
+                        io.success("Alright! Here is your password:", OutputType::Error);
                         io.success(
-                            format!(
-                                "Alright! Here is your password: {}",
-                                password_as_string_clipboard.deref()
-                            ),
+                            format!("{}", password_as_string_clipboard.deref()),
                             OutputType::Standard,
                         );
                         return Ok(());
                     }
 
-                    if copy_to_clipboard(&password_as_string_clipboard).is_err() {
+                    if copy_to_clipboard_with_timeout(&password_as_string_clipboard, clear_after)
+                        .is_err()
+                    {
                         io.success(
                             format!(
                                 "Hmm, I tried to copy your new password to your clipboard, \
@@ -46,16 +77,17 @@ pub fn callback_exec(
                                  can see it with `rooster get {} --show`",
                                 app_name
                             ),
-                            OutputType::Standard,
+                            OutputType::Error,
                         );
                     } else {
                         io.success(
                             format!(
                                 "Alright! I've saved your new password. You can paste it \
-                                 anywhere with {}.",
-                                paste_keys()
+                                 anywhere with {}{}.",
+                                paste_keys(),
+                                clear_after_message(clear_after)
                             ),
-                            OutputType::Standard,
+                            OutputType::Error,
                         );
                     }
                 }
@@ -64,7 +96,7 @@ pub fn callback_exec(
                         format!("Woops, I couldn't add the password (reason: {:?}).", err),
                         OutputType::Error,
                     );
-                    return Err(1);
+                    return Err(CommandError::Store);
                 }
             }
             Ok(())
@@ -74,7 +106,121 @@ pub fn callback_exec(
                 format!("\nI couldn't read the app's password (reason: {:?}).", err),
                 OutputType::Error,
             );
-            Err(1)
+            Err(CommandError::Io)
         }
     }
 }
+
+/// The `--tag`, `--expire-days` and `--note` options, shared by all passwords of a batch.
+fn apply_options(password: &mut password::v2::Password, matches: &clap::ArgMatches) {
+    if let Some(tags) = matches.get_many::<String>("tag") {
+        password.tags = tags.cloned().collect();
+    }
+    password.expires_after_days = matches.get_one::<u32>("expire-days").copied();
+    if let Some(note) = matches.get_one::<String>("note") {
+        password.notes = SafeString::from_string(note.clone());
+    }
+}
+
+/// Splits an `app<TAB>username<TAB>password` line. The password is everything after the
+/// second tab, so it may contain tabs itself.
+fn parse_batch_line(line: &str) -> Result<(String, String, SafeString), String> {
+    let mut fields = line.trim_end_matches(&['\r', '\n'][..]).splitn(3, '\t');
+    match (fields.next(), fields.next(), fields.next()) {
+        (Some(app), Some(username), Some(password)) if !app.is_empty() => Ok((
+            app.to_owned(),
+            username.to_owned(),
+            SafeString::from_string(password.to_owned()),
+        )),
+        (Some(""), _, _) => Err("the app name is empty".to_owned()),
+        _ => Err("expected \"app<TAB>username<TAB>password\"".to_owned()),
+    }
+}
+
+/// Adds one password per line of stdin until the end of the input, skipping empty lines.
+/// Failed lines are reported and skipped, the others are saved, and the command then exits
+/// with 1.
+fn add_batch(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let mut added = 0;
+    let mut failed = 0;
+    let mut line_number = 0;
+
+    while let Ok(line) = io.read_line() {
+        // The line holds a password, so it gets scrubbed too.
+        let line = SafeString::from_string(line);
+        line_number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = parse_batch_line(line.deref()).and_then(|(app_name, username, password)| {
+            let mut password = password::v2::Password::new(app_name.clone(), username, password);
+            apply_options(&mut password, matches);
+            store.add_password(password).map_err(|err| match err {
+                password::PasswordError::AppExistsError => {
+                    format!("there is already an app named {}", app_name)
+                }
+                password::PasswordError::EmptyPasswordError => "the password is empty".to_owned(),
+                err => format!("I couldn't add the password (reason: {:?})", err),
+            })
+        });
+
+        match result {
+            Ok(()) => added += 1,
+            Err(message) => {
+                failed += 1;
+                io.error(
+                    format!("Woops, line {}: {}.", line_number, message),
+                    OutputType::Error,
+                );
+            }
+        }
+    }
+
+    if matches.get_flag("dry-run") {
+        io.info(
+            format!("I would have added {} password(s).", added),
+            OutputType::Error,
+        );
+    } else {
+        io.success(
+            format!("Done! I've added {} password(s).", added),
+            OutputType::Error,
+        );
+    }
+
+    if failed > 0 {
+        io.error(
+            format!("{} line(s) could not be added.", failed),
+            OutputType::Error,
+        );
+        // The passwords from the other lines are still saved.
+        return Err(CommandError::Partial);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_batch_line;
+    use std::ops::Deref;
+
+    #[test]
+    fn test_parse_batch_line() {
+        let (app, username, password) = parse_batch_line("Gmail\tme@gmail.com\ta\tb c\n").unwrap();
+        assert_eq!(app, "Gmail");
+        assert_eq!(username, "me@gmail.com");
+        assert_eq!(password.deref(), "a\tb c");
+
+        // Empty usernames are allowed, like with `rooster add`
+        assert!(parse_batch_line("Gmail\t\txxxx").is_ok());
+
+        assert!(parse_batch_line("Gmail me@gmail.com xxxx").is_err());
+        assert!(parse_batch_line("Gmail\tme@gmail.com").is_err());
+        assert!(parse_batch_line("\tme@gmail.com\txxxx").is_err());
+    }
+}
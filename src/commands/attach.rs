@@ -0,0 +1,286 @@
+use crate::commands::CommandError;
+use crate::ffi;
+use crate::list;
+use crate::password;
+use crate::password::v2::{MAX_ATTACHMENT_BYTES, MAX_TOTAL_ATTACHMENT_BYTES};
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use rtoolbox::safe_vec::SafeVec;
+use std::fs::File;
+use std::io::{Read, Result as IoResult, Write};
+use std::ops::Deref;
+use std::path::Path;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let subcommand_name = matches.subcommand_name().unwrap();
+    let subcommand_matches = matches.subcommand_matches(subcommand_name).unwrap();
+    let query = subcommand_matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which password's attachments would you like?",
+        io,
+    )
+    .ok_or(CommandError::NotFound)?
+    .clone();
+
+    if subcommand_name == "add" {
+        add_attachment(subcommand_matches, &password, store, io)
+    } else if subcommand_name == "get" {
+        get_attachment(subcommand_matches, &password, io)
+    } else if subcommand_name == "list" {
+        list_attachments(&password, io)
+    } else if subcommand_name == "remove" {
+        remove_attachment(subcommand_matches, &password, store, io)
+    } else {
+        unimplemented!("Invalid attach subcommand")
+    }
+}
+
+/// Reads at most one byte more than allowed, so that huge files are never loaded whole. The
+/// buffer is big enough from the start, so no copy is left behind by a reallocation.
+fn read_attachment_file(path: &Path) -> IoResult<SafeVec> {
+    let mut bytes = SafeVec::new(Vec::with_capacity(MAX_ATTACHMENT_BYTES + 1));
+    File::open(path)?
+        .take(MAX_ATTACHMENT_BYTES as u64 + 1)
+        .read_to_end(bytes.inner_mut())?;
+    Ok(bytes)
+}
+
+/// Creates the output file, readable by its owner only. We never overwrite a file.
+fn write_attachment_file(path: &Path, content: &SafeVec) -> IoResult<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(content.deref())?;
+    file.sync_all()
+}
+
+fn save_attachments(
+    password: &password::v2::Password,
+    attachments: Vec<(String, SafeVec)>,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    store
+        .change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                attachments: attachments.clone(),
+                updated_at: ffi::time(),
+                ..old_password
+            }
+        })
+        .map(|_| ())
+        .map_err(|err| {
+            io.error(
+                format!(
+                    "Woops, I couldn't save the attachments (reason: {:?}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            CommandError::Store
+        })
+}
+
+fn add_attachment(
+    matches: &clap::ArgMatches,
+    password: &password::v2::Password,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let path = Path::new(matches.get_one::<String>("file").unwrap());
+    let name = match matches.get_one::<String>("name") {
+        Some(name) => name.clone(),
+        None => match path.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => {
+                io.error(
+                    format!(
+                        "Woops, {} is not a file name. Use --name to pick one.",
+                        path.to_string_lossy()
+                    ),
+                    OutputType::Error,
+                );
+                return Err(CommandError::InvalidInput);
+            }
+        },
+    };
+
+    if password.get_attachment(&name).is_some() {
+        io.error(
+            format!(
+                "Woops, {} already has an attachment named \"{}\". Remove it first with `rooster attach remove '{}' '{}'`.",
+                password.name, name, password.name, name
+            ),
+            OutputType::Error,
+        );
+        return Err(CommandError::Duplicate);
+    }
+
+    let content = read_attachment_file(path).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I couldn't read {} (reason: {:?}).",
+                path.to_string_lossy(),
+                err
+            ),
+            OutputType::Error,
+        );
+        CommandError::Io
+    })?;
+    if content.len() > MAX_ATTACHMENT_BYTES {
+        io.error(
+            format!(
+                "Woops, {} is too large. Attachments can be at most {} KiB.",
+                path.to_string_lossy(),
+                MAX_ATTACHMENT_BYTES / 1024
+            ),
+            OutputType::Error,
+        );
+        return Err(CommandError::InvalidInput);
+    }
+    if store.attachments_len() + content.len() > MAX_TOTAL_ATTACHMENT_BYTES {
+        io.error(
+            format!(
+                "Woops, your password file can hold at most {} KiB of attachments, and this one doesn't fit.",
+                MAX_TOTAL_ATTACHMENT_BYTES / 1024
+            ),
+            OutputType::Error,
+        );
+        return Err(CommandError::InvalidInput);
+    }
+
+    let len = content.len();
+    let mut attachments = password.attachments.clone();
+    attachments.push((name.clone(), content));
+    save_attachments(password, attachments, store, io)?;
+
+    io.success(
+        format!(
+            "Done! I've attached {} ({} bytes) to {}.",
+            name, len, password.name
+        ),
+        OutputType::Error,
+    );
+    Ok(())
+}
+
+fn get_attachment(
+    matches: &clap::ArgMatches,
+    password: &password::v2::Password,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let name = matches.get_one::<String>("name").unwrap();
+    let output = Path::new(matches.get_one::<String>("output").unwrap());
+
+    let content = match password.get_attachment(name) {
+        Some(content) => content,
+        None => {
+            io.error(
+                format!(
+                    "Woops, {} has no attachment named \"{}\". See them with `rooster attach list '{}'`.",
+                    password.name, name, password.name
+                ),
+                OutputType::Error,
+            );
+            return Err(CommandError::NotFound);
+        }
+    };
+
+    if matches.get_flag("dry-run") {
+        io.info(
+            format!(
+                "I would have saved {} to {}.",
+                name,
+                output.to_string_lossy()
+            ),
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    write_attachment_file(output, content).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I couldn't write {} (reason: {:?}).",
+                output.to_string_lossy(),
+                err
+            ),
+            OutputType::Error,
+        );
+        CommandError::Io
+    })?;
+
+    io.success(
+        format!("Done! I've saved {} to {}.", name, output.to_string_lossy()),
+        OutputType::Error,
+    );
+    Ok(())
+}
+
+fn list_attachments(
+    password: &password::v2::Password,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    if password.attachments.is_empty() {
+        io.info(
+            format!("{} has no attachments.", password.name),
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    for (name, content) in password.attachments.iter() {
+        io.info(
+            format!("{} ({} bytes)", name, content.len()),
+            OutputType::Standard,
+        );
+    }
+    Ok(())
+}
+
+fn remove_attachment(
+    matches: &clap::ArgMatches,
+    password: &password::v2::Password,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let name = matches.get_one::<String>("name").unwrap();
+
+    if password.get_attachment(name).is_none() {
+        io.error(
+            format!(
+                "Woops, {} has no attachment named \"{}\".",
+                password.name, name
+            ),
+            OutputType::Error,
+        );
+        return Err(CommandError::NotFound);
+    }
+
+    let attachments = password
+        .attachments
+        .iter()
+        .filter(|(n, _)| n.to_lowercase() != name.to_lowercase())
+        .cloned()
+        .collect();
+    save_attachments(password, attachments, store, io)?;
+
+    io.success(
+        format!("Done! I've removed {} from {}.", name, password.name),
+        OutputType::Error,
+    );
+    Ok(())
+}
@@ -0,0 +1,389 @@
+use crate::commands::CommandError;
+use crate::date;
+use crate::ffi;
+use crate::hibp;
+use crate::password;
+use crate::password::v2::{Password, RotationStatus};
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::thread;
+use std::time::Duration;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let subcommand_name = matches.subcommand_name().unwrap();
+
+    if subcommand_name == "expired" {
+        audit_expired(store, ffi::time(), io)
+    } else if subcommand_name == "duplicates" {
+        audit_duplicates(store, io)
+    } else if subcommand_name == "pwned" {
+        audit_pwned(store, io)
+    } else if subcommand_name == "empty" {
+        audit_empty(store, io)
+    } else {
+        unimplemented!("Invalid audit subcommand")
+    }
+}
+
+/// One line per password that should be rotated or has expired, `None` for the others.
+fn get_expiry_line(p: &Password, now: ffi::time_t) -> Option<String> {
+    if let Some(expires_at) = p.expires_at {
+        if p.is_expired(now) {
+            return Some(format!("expired on {}", date::format_date(expires_at)));
+        }
+    }
+
+    let due_at = p.rotation_due_at()?;
+    match p.rotation_status(now)? {
+        RotationStatus::Overdue => Some(format!(
+            "rotate now (was due on {})",
+            date::format_date(due_at)
+        )),
+        RotationStatus::DueSoon => Some(format!(
+            "rotate soon (due on {})",
+            date::format_date(due_at)
+        )),
+        RotationStatus::UpToDate => None,
+    }
+}
+
+fn audit_expired(
+    store: &mut password::v2::PasswordStore,
+    now: ffi::time_t,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let passwords = store.get_all_passwords();
+    let flagged: Vec<(&Password, String)> = passwords
+        .iter()
+        .filter_map(|p| get_expiry_line(p, now).map(|line| (*p, line)))
+        .collect();
+
+    if flagged.is_empty() {
+        io.success(
+            "All good! No password is expired or due for rotation.",
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    let longest_app_name = flagged.iter().map(|(p, _)| p.name.len()).max().unwrap();
+    let longest_username = flagged.iter().map(|(p, _)| p.username.len()).max().unwrap();
+
+    let mut overdue = 0;
+    for (p, line) in flagged.iter() {
+        let s = format!(
+            "{:app_name_width$} {:username_width$} {}",
+            p.name,
+            p.username,
+            line,
+            app_name_width = longest_app_name,
+            username_width = longest_username,
+        );
+        if p.rotation_status(now) == Some(RotationStatus::DueSoon) && !p.is_expired(now) {
+            io.warning(s, OutputType::Standard);
+        } else {
+            overdue += 1;
+            io.error(s, OutputType::Standard);
+        }
+    }
+
+    io.info(
+        format!(
+            "{} password(s) need attention: {} overdue or expired, {} due soon.",
+            flagged.len(),
+            overdue,
+            flagged.len() - overdue
+        ),
+        OutputType::Error,
+    );
+
+    Ok(())
+}
+
+/// Groups passwords that have the same value, in the order of `get_all_passwords`.
+///
+/// Passwords are compared through a keyed hash, with a key that only lives as long as the
+/// audit, so we never compare secrets byte by byte nor keep a reusable hash around.
+fn find_duplicates<'a>(passwords: &Vec<&'a Password>) -> Vec<Vec<&'a Password>> {
+    let mut key = [0u8; 32];
+    OsRng::default().fill_bytes(&mut key);
+
+    let mut groups: Vec<Vec<&Password>> = Vec::new();
+    let mut group_index_by_hash: HashMap<Vec<u8>, usize> = HashMap::new();
+    for p in passwords.iter() {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&key).unwrap();
+        mac.update(p.password.deref().as_bytes());
+        let hash = mac.finalize().into_bytes().to_vec();
+
+        match group_index_by_hash.get(&hash) {
+            Some(&i) => groups[i].push(*p),
+            None => {
+                group_index_by_hash.insert(hash, groups.len());
+                groups.push(vec![*p]);
+            }
+        }
+    }
+
+    groups.into_iter().filter(|group| group.len() > 1).collect()
+}
+
+fn audit_duplicates(
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let passwords = store.get_all_passwords();
+    let duplicates = find_duplicates(&passwords);
+
+    if duplicates.is_empty() {
+        io.success(
+            "All good! Each password is only used once.",
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    for (i, group) in duplicates.iter().enumerate() {
+        if i > 0 {
+            io.nl(OutputType::Standard);
+        }
+        io.warning(
+            format!("These {} apps share the same password:", group.len()),
+            OutputType::Standard,
+        );
+        for p in group.iter() {
+            io.info(format!("  {} {}", p.name, p.username), OutputType::Standard);
+        }
+    }
+
+    io.error(
+        format!(
+            "{} password(s) are reused. Change them with `rooster regenerate <app>`.",
+            duplicates.len()
+        ),
+        OutputType::Error,
+    );
+    Err(CommandError::CheckFailed)
+}
+
+/// Values that are typed in while setting up an account, and forgotten there.
+const PLACEHOLDER_PASSWORDS: [&'static str; 12] = [
+    "changeme",
+    "change me",
+    "change_me",
+    "password",
+    "placeholder",
+    "tbd",
+    "todo",
+    "temp",
+    "xxx",
+    "xxxx",
+    "1234",
+    "123456",
+];
+
+/// Whether the password is empty, only whitespace, or a known placeholder.
+fn is_empty_or_placeholder(password: &str) -> bool {
+    let password = password.trim().to_lowercase();
+    password.is_empty() || PLACEHOLDER_PASSWORDS.contains(&password.as_str())
+}
+
+fn audit_empty(
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let passwords: Vec<&Password> = store
+        .get_all_passwords()
+        .into_iter()
+        .filter(|p| is_empty_or_placeholder(p.password.deref()))
+        .collect();
+
+    if passwords.is_empty() {
+        io.success(
+            "All good! No password is empty or a placeholder.",
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    for p in passwords.iter() {
+        io.warning(format!("{} {}", p.name, p.username), OutputType::Standard);
+    }
+    io.error(
+        format!(
+            "{} password(s) are empty or placeholders. Set them with `rooster change <app>`.",
+            passwords.len()
+        ),
+        OutputType::Error,
+    );
+    Err(CommandError::CheckFailed)
+}
+
+/// How many times we ask again when the Have I Been Pwned API is rate limiting us.
+const PWNED_MAX_RETRIES: u32 = 3;
+const PWNED_RETRY_WAIT: Duration = Duration::from_secs(2);
+
+fn audit_pwned(
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    audit_pwned_with(store, hibp::fetch_range, io)
+}
+
+fn audit_pwned_with(
+    store: &mut password::v2::PasswordStore,
+    fetch: impl Fn(&str) -> Result<String, hibp::HibpError>,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let passwords = store.get_all_passwords();
+    io.info(
+        format!(
+            "Checking {} password(s) with Have I Been Pwned. Only the first 5 characters of \
+             each password's SHA-1 hash are sent.",
+            passwords.len()
+        ),
+        OutputType::Error,
+    );
+
+    let mut pwned = 0;
+    for p in passwords.iter() {
+        let mut retries = 0;
+        let count = loop {
+            match hibp::pwned_count(&p.password, &fetch) {
+                Err(hibp::HibpError::RateLimited) if retries < PWNED_MAX_RETRIES => {
+                    retries += 1;
+                    thread::sleep(PWNED_RETRY_WAIT * retries);
+                }
+                result => break result,
+            }
+        };
+
+        match count {
+            Ok(0) => {}
+            Ok(count) => {
+                pwned += 1;
+                io.error(
+                    format!("{} appears in {} breach(es)", p.name, count),
+                    OutputType::Standard,
+                );
+            }
+            Err(hibp::HibpError::RateLimited) => {
+                io.error(
+                    "Woops, Have I Been Pwned is rate limiting us. Try again in a few minutes.",
+                    OutputType::Error,
+                );
+                return Err(CommandError::Io);
+            }
+            Err(hibp::HibpError::Unavailable(reason)) => {
+                io.error(
+                    format!(
+                        "Woops, I couldn't reach Have I Been Pwned, are you offline? (reason: {})",
+                        reason
+                    ),
+                    OutputType::Error,
+                );
+                return Err(CommandError::Io);
+            }
+            Err(hibp::HibpError::Disabled) => {
+                io.error(
+                    "Woops, this Rooster was built without network access. Install it with \
+                     `cargo install rooster --features hibp` to check for breached passwords.",
+                    OutputType::Error,
+                );
+                return Err(CommandError::Unavailable);
+            }
+            Err(hibp::HibpError::InvalidResponse) => {
+                io.error(
+                    "Woops, Have I Been Pwned sent a response I don't understand.",
+                    OutputType::Error,
+                );
+                return Err(CommandError::Io);
+            }
+        }
+    }
+
+    if pwned == 0 {
+        io.success(
+            "All good! None of your passwords appear in known breaches.",
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    io.error(
+        format!(
+            "{} password(s) appear in known breaches. Change them with `rooster regenerate <app>`.",
+            pwned
+        ),
+        OutputType::Error,
+    );
+    Err(CommandError::CheckFailed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_duplicates, get_expiry_line, is_empty_or_placeholder};
+    use crate::password::v2::Password;
+
+    #[test]
+    fn test_get_expiry_line() {
+        let mut p = Password::new("name", "username", "password");
+        p.updated_at = 0;
+        assert_eq!(get_expiry_line(&p, 86400), None);
+
+        p.expires_after_days = Some(30);
+        assert_eq!(get_expiry_line(&p, 86400), None);
+        assert_eq!(
+            get_expiry_line(&p, 20 * 86400),
+            Some("rotate soon (due on 1970-01-31)".to_owned())
+        );
+        assert_eq!(
+            get_expiry_line(&p, 40 * 86400),
+            Some("rotate now (was due on 1970-01-31)".to_owned())
+        );
+
+        p.expires_at = Some(86400);
+        assert_eq!(
+            get_expiry_line(&p, 40 * 86400),
+            Some("expired on 1970-01-02".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let passwords = vec![
+            Password::new("a", "username", "one"),
+            Password::new("b", "username", "two"),
+            Password::new("c", "username", "one"),
+            Password::new("d", "username", "three"),
+            Password::new("e", "username", "one"),
+            Password::new("f", "username", "three"),
+        ];
+
+        let duplicates = find_duplicates(&passwords.iter().collect());
+        let names: Vec<Vec<&str>> = duplicates
+            .iter()
+            .map(|group| group.iter().map(|p| p.name.as_str()).collect())
+            .collect();
+        assert_eq!(names, vec![vec!["a", "c", "e"], vec!["d", "f"]]);
+
+        assert!(find_duplicates(&passwords[..2].iter().collect()).is_empty());
+    }
+
+    #[test]
+    fn test_is_empty_or_placeholder() {
+        assert!(is_empty_or_placeholder(""));
+        assert!(is_empty_or_placeholder(" \t "));
+        assert!(is_empty_or_placeholder("ChangeMe"));
+        assert!(is_empty_or_placeholder(" todo "));
+        assert!(!is_empty_or_placeholder("correct horse battery staple"));
+    }
+}
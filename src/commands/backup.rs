@@ -0,0 +1,108 @@
+use crate::commands::CommandError;
+use crate::date;
+use crate::ffi;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use std::fs::File;
+use std::io::{ErrorKind, Result as IoResult};
+use std::path::{Path, PathBuf};
+
+/// Copies the Rooster file byte for byte. It is already encrypted, so the copy is as safe as
+/// the original. The destination must not exist yet, we never overwrite a file.
+fn copy_password_file(from: &Path, to: &Path) -> IoResult<()> {
+    let mut source = File::open(from)?;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut destination = options.open(to)?;
+
+    std::io::copy(&mut source, &mut destination)?;
+    destination.sync_all()
+}
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &PathBuf,
+) -> Result<(), CommandError> {
+    if !rooster_file_path.exists() {
+        io.error(
+            format!(
+                "Woops, there is no Rooster file to back up at {}.",
+                rooster_file_path.to_string_lossy()
+            ),
+            OutputType::Error,
+        );
+        return Err(CommandError::NotFound);
+    }
+
+    // By default, the backup goes next to the original.
+    let dir = match matches.get_one::<String>("dir") {
+        Some(dir) => PathBuf::from(dir),
+        None => match rooster_file_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        },
+    };
+    if !dir.is_dir() {
+        io.error(
+            format!("Woops, {} is not a directory.", dir.to_string_lossy()),
+            OutputType::Error,
+        );
+        return Err(CommandError::InvalidInput);
+    }
+
+    let backup_path = dir.join(format!(
+        "rooster-backup-{}.file",
+        date::format_iso8601_basic(ffi::time())
+    ));
+    let backup_path_as_string = backup_path.to_string_lossy().into_owned();
+
+    if matches.get_flag("dry-run") {
+        io.info(
+            format!(
+                "I would have backed up your Rooster file to {}.",
+                backup_path_as_string
+            ),
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    if let Err(err) = copy_password_file(rooster_file_path, &backup_path) {
+        match err.kind() {
+            ErrorKind::AlreadyExists => io.error(
+                format!(
+                    "Woops, {} already exists. Wait a second and try again.",
+                    backup_path_as_string
+                ),
+                OutputType::Error,
+            ),
+            ErrorKind::PermissionDenied => io.error(
+                format!(
+                    "Woops, I am not allowed to write in {}. Pick another directory with \
+                     `rooster backup <dir>`.",
+                    dir.to_string_lossy()
+                ),
+                OutputType::Error,
+            ),
+            _ => io.error(
+                format!("Woops, I could not make the backup (reason: {}).", err),
+                OutputType::Error,
+            ),
+        }
+        return Err(CommandError::Io);
+    }
+
+    io.success(
+        "Alright! Your Rooster file is backed up to:",
+        OutputType::Error,
+    );
+    io.write(format!("{}\n", backup_path_as_string), OutputType::Standard);
+    Ok(())
+}
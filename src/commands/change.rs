@@ -1,27 +1,37 @@
-use crate::clip;
+use crate::clip::{clear_after_message, copy_to_clipboard_with_timeout, must_show, paste_keys};
+use crate::commands::note::change_note;
+use crate::commands::CommandError;
 use crate::ffi;
 use crate::list;
 use crate::password;
 use rclio::CliInputOutput;
 use rclio::OutputType;
+use rtoolbox::safe_string::SafeString;
+use std::ops::Deref;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
     let query = matches.get_one::<String>("app").unwrap();
+    let username = matches.get_one::<String>("username").map(|s| s.as_str());
 
-    let password = list::search_and_choose_password(
+    let password = list::search_and_choose_password_for_account(
         store,
         query,
+        username,
         list::WITH_NUMBERS,
         "Which password would like to update?",
         io,
     )
-    .ok_or(1)?
+    .ok_or(CommandError::NotFound)?
     .clone();
 
+    if let Some(note) = matches.get_one::<String>("note") {
+        return change_note(&password, SafeString::from_string(note.clone()), store, io);
+    }
+
     let password_as_string = io
         .prompt_password(format!(
             "What password do you want for \"{}\"? ",
@@ -32,17 +42,15 @@ pub fn callback_exec(
                 format!("\nI couldn't read the app's password (reason: {:?}).", err),
                 OutputType::Error,
             );
-            1
+            CommandError::Io
         })?;
 
     let password = store
         .change_password(&password.name, &|old_password: password::v2::Password| {
             password::v2::Password {
-                name: old_password.name,
-                username: old_password.username,
                 password: password_as_string.clone(),
-                created_at: old_password.created_at,
                 updated_at: ffi::time(),
+                ..old_password
             }
         })
         .map_err(|err| {
@@ -53,10 +61,45 @@ pub fn callback_exec(
                 ),
                 OutputType::Error,
             );
-            1
+            CommandError::Store
         })?;
 
-    let show = matches.get_flag("show");
-    clip::confirm_password_retrieved(show, &password, io);
+    if matches.get_flag("dry-run") {
+        io.info(
+            format!("I would have changed the password for {}.", password.name),
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    if must_show(matches.get_flag("show"), &password, io) {
+        io.success("Alright! Here is your new password:", OutputType::Error);
+        io.success(
+            format!("{}", password.password.deref()),
+            OutputType::Standard,
+        );
+        return Ok(());
+    }
+
+    if copy_to_clipboard_with_timeout(&password.password, None).is_err() {
+        io.success(
+            format!(
+                "Hmm, I tried to copy your new password to your clipboard, but something \
+                 went wrong. Don't worry, it's saved, and you can see it with \
+                 `rooster get '{}' --show`",
+                password.name
+            ),
+            OutputType::Error,
+        );
+    } else {
+        io.success(
+            format!(
+                "Alright! I've saved your new password. You can paste it anywhere with {}{}.",
+                paste_keys(),
+                clear_after_message(None)
+            ),
+            OutputType::Error,
+        );
+    }
     Ok(())
 }
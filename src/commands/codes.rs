@@ -0,0 +1,201 @@
+use crate::commands::CommandError;
+use crate::ffi;
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use rtoolbox::safe_string::SafeString;
+use std::ops::Deref;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let subcommand_name = matches.subcommand_name().unwrap();
+    let subcommand_matches = matches.subcommand_matches(subcommand_name).unwrap();
+    let query = subcommand_matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which backup codes would you like?",
+        io,
+    )
+    .ok_or(CommandError::NotFound)?
+    .clone();
+
+    if subcommand_name == "set" {
+        set_backup_codes(&password, store, io)
+    } else if subcommand_name == "use" {
+        use_backup_code(&password, store, io)
+    } else {
+        unimplemented!("Invalid codes subcommand")
+    }
+}
+
+/// Reads one code per line, until an empty line or the end of the input.
+fn read_backup_codes(io: &mut impl CliInputOutput) -> Result<Vec<SafeString>, CommandError> {
+    let mut codes = Vec::new();
+    loop {
+        match io.read_line() {
+            Ok(line) => {
+                let code = line.trim();
+                if code.is_empty() {
+                    return Ok(codes);
+                }
+                codes.push(SafeString::from_string(code.to_owned()));
+            }
+            Err(_) if !codes.is_empty() => return Ok(codes),
+            Err(err) => {
+                io.error(
+                    format!("Woops, I couldn't read the codes (reason: {:?}).", err),
+                    OutputType::Error,
+                );
+                return Err(CommandError::Io);
+            }
+        }
+    }
+}
+
+fn set_backup_codes(
+    password: &password::v2::Password,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    io.info(
+        format!(
+            "Type the backup codes for {}, one per line, then an empty line:",
+            password.name
+        ),
+        OutputType::Error,
+    );
+    let codes = read_backup_codes(io)?;
+    let backup_codes: Vec<(SafeString, bool)> =
+        codes.into_iter().map(|code| (code, false)).collect();
+
+    let change_result =
+        store.change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                backup_codes: backup_codes.clone(),
+                updated_at: ffi::time(),
+                ..old_password
+            }
+        });
+
+    match change_result {
+        Ok(_) => {
+            io.success(
+                format!(
+                    "Done! I've saved {} backup code(s) for {}.",
+                    backup_codes.len(),
+                    password.name
+                ),
+                OutputType::Error,
+            );
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I couldn't save the codes (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            Err(CommandError::Store)
+        }
+    }
+}
+
+/// Marks the first unused code as used, returns it with the updated list.
+fn take_next_backup_code(
+    backup_codes: &Vec<(SafeString, bool)>,
+) -> Option<(SafeString, Vec<(SafeString, bool)>)> {
+    let index = backup_codes.iter().position(|(_, used)| !used)?;
+    let mut backup_codes = backup_codes.clone();
+    backup_codes[index].1 = true;
+    Some((backup_codes[index].0.clone(), backup_codes))
+}
+
+fn use_backup_code(
+    password: &password::v2::Password,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let (code, backup_codes) = match take_next_backup_code(&password.backup_codes) {
+        Some(next) => next,
+        None => {
+            io.error(
+                format!(
+                    "Woops, there are no backup codes left for {}. Save new ones with `rooster codes set '{}'`.",
+                    password.name, password.name
+                ),
+                OutputType::Error,
+            );
+            return Err(CommandError::NotFound);
+        }
+    };
+
+    // Save before showing the code, so that a code is never shown twice.
+    let change_result =
+        store.change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                backup_codes: backup_codes.clone(),
+                updated_at: ffi::time(),
+                ..old_password
+            }
+        });
+
+    match change_result {
+        Ok(password) => {
+            io.success(
+                format!("Here is your next backup code for {}:", password.name),
+                OutputType::Error,
+            );
+            io.success(format!("{}", code.deref()), OutputType::Standard);
+            io.info(
+                format!(
+                    "{} backup code(s) left.",
+                    password.unused_backup_codes().len()
+                ),
+                OutputType::Error,
+            );
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I couldn't save the codes (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            Err(CommandError::Store)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::take_next_backup_code;
+    use rtoolbox::safe_string::SafeString;
+
+    #[test]
+    fn test_take_next_backup_code() {
+        let backup_codes = vec![
+            (SafeString::from_string("1111".to_owned()), true),
+            (SafeString::from_string("2222".to_owned()), false),
+            (SafeString::from_string("3333".to_owned()), false),
+        ];
+
+        let (code, backup_codes) = take_next_backup_code(&backup_codes).unwrap();
+        assert_eq!(code, SafeString::from_string("2222".to_owned()));
+        assert_eq!(
+            backup_codes
+                .iter()
+                .map(|(_, used)| *used)
+                .collect::<Vec<_>>(),
+            vec![true, true, false]
+        );
+
+        let (code, backup_codes) = take_next_backup_code(&backup_codes).unwrap();
+        assert_eq!(code, SafeString::from_string("3333".to_owned()));
+        assert!(take_next_backup_code(&backup_codes).is_none());
+    }
+}
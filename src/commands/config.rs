@@ -0,0 +1,161 @@
+use crate::commands::import::{
+    DEFAULT_HARD_LIMIT, DEFAULT_SOFT_LIMIT, HARD_LIMIT_ENV_VAR, SOFT_LIMIT_ENV_VAR,
+};
+use crate::commands::set_scrypt_params::THRESHOLD_SETTINGS;
+use crate::commands::trash::{DEFAULT_RETENTION_DAYS, RETENTION_DAYS_ENV_VAR};
+use crate::commands::CommandError;
+use crate::generate::DEFAULT_PASSWORD_LEN;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use std::env;
+use std::path::PathBuf;
+
+/// A setting in effect, and where its value comes from (default, env or flag).
+struct Setting {
+    name: String,
+    value: String,
+    source: String,
+}
+
+impl Setting {
+    fn new<S1: Into<String>, S2: Into<String>, S3: Into<String>>(
+        name: S1,
+        value: S2,
+        source: S3,
+    ) -> Setting {
+        Setting {
+            name: name.into(),
+            value: value.into(),
+            source: source.into(),
+        }
+    }
+
+    /// A setting that can be overridden with an environment variable.
+    fn from_env<S: Into<String>>(name: S, env_var: &str, default: String) -> Setting {
+        match env::var(env_var) {
+            Ok(value) => Setting::new(name, value, format!("env: {}", env_var)),
+            Err(_) => Setting::new(name, default, "default"),
+        }
+    }
+}
+
+fn get_settings(matches: &clap::ArgMatches, rooster_file_path: &PathBuf) -> Vec<Setting> {
+    let mut settings = Vec::new();
+
+    if matches.get_flag("memory") {
+        settings.push(Setting::new("file", "none, in memory", "flag: --memory"));
+    } else {
+        settings.push(Setting::new(
+            "file",
+            rooster_file_path.to_string_lossy(),
+            match env::var_os("ROOSTER_FILE") {
+                Some(_) => "env: ROOSTER_FILE",
+                None => "default",
+            },
+        ));
+    }
+
+    settings.push(Setting::new(
+        "generate.length",
+        DEFAULT_PASSWORD_LEN.to_string(),
+        "default, see --length",
+    ));
+    settings.push(Setting::new(
+        "generate.alnum",
+        "false",
+        "default, see --alnum",
+    ));
+    settings.push(Setting::new(
+        "clipboard.clear-after",
+        "never",
+        "default, see --clear-after",
+    ));
+
+    settings.push(Setting::from_env(
+        "trash.retention-days",
+        RETENTION_DAYS_ENV_VAR,
+        DEFAULT_RETENTION_DAYS.to_string(),
+    ));
+    settings.push(Setting::from_env(
+        "import.soft-limit",
+        SOFT_LIMIT_ENV_VAR,
+        DEFAULT_SOFT_LIMIT.to_string(),
+    ));
+    settings.push(Setting::from_env(
+        "import.hard-limit",
+        HARD_LIMIT_ENV_VAR,
+        DEFAULT_HARD_LIMIT.to_string(),
+    ));
+
+    for (env_var, default) in THRESHOLD_SETTINGS.iter() {
+        let name = format!(
+            "scrypt.{}",
+            env_var
+                .trim_start_matches("ROOSTER_SCRYPT_")
+                .to_lowercase()
+                .replace('_', "-")
+        );
+        settings.push(Setting::from_env(name, env_var, default.to_string()));
+    }
+
+    settings
+}
+
+fn get_list_of_settings(settings: &Vec<Setting>) -> Vec<String> {
+    let longest_name = settings.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    let longest_value = settings.iter().map(|s| s.value.len()).max().unwrap_or(0);
+
+    settings
+        .iter()
+        .map(|s| {
+            format!(
+                "{:name_width$} {:value_width$} ({})",
+                s.name,
+                s.value,
+                s.source,
+                name_width = longest_name,
+                value_width = longest_value,
+            )
+        })
+        .collect()
+}
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &PathBuf,
+) -> Result<(), CommandError> {
+    let subcommand_name = matches.subcommand_name().unwrap();
+    let subcommand_matches = matches.subcommand_matches(subcommand_name).unwrap();
+
+    if subcommand_name == "show" {
+        let settings = get_settings(subcommand_matches, rooster_file_path);
+        for s in get_list_of_settings(&settings) {
+            io.info(s, OutputType::Standard);
+        }
+        Ok(())
+    } else {
+        unimplemented!("Invalid config subcommand")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{get_list_of_settings, Setting};
+
+    #[test]
+    fn settings_are_aligned_with_their_source() {
+        let settings = vec![
+            Setting::new("file", "/tmp/rooster", "default"),
+            Setting::new("generate.length", "32", "default, see --length"),
+        ];
+
+        assert_eq!(
+            get_list_of_settings(&settings),
+            &[
+                "file            /tmp/rooster (default)",
+                "generate.length 32           (default, see --length)",
+            ]
+        );
+    }
+}
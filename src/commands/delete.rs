@@ -1,5 +1,7 @@
-use crate::list;
+use crate::commands::import::confirm;
+use crate::commands::CommandError;
 use crate::password;
+use crate::select;
 use rclio::CliInputOutput;
 use rclio::OutputType;
 
@@ -7,20 +9,69 @@ pub fn callback_exec(
     matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
+    let filter = select::Filter::from_matches(matches).map_err(|err| {
+        io.error(err, OutputType::Error);
+        CommandError::InvalidInput
+    })?;
+    if !filter.is_empty() {
+        return delete_selection(matches, &filter, store, io);
+    }
+
     let query = matches.get_one::<String>("app").unwrap();
+    let username = matches.get_one::<String>("username").map(|s| s.as_str());
+
+    // Unlike other commands, we don't let the user pick among several matches: a typo
+    // shouldn't be one keystroke away from deleting the wrong password.
+    let password = {
+        let passwords = store.search_passwords_for_account(query, username);
+        match passwords
+            .iter()
+            .find(|p| p.name.to_lowercase() == query.to_lowercase())
+        {
+            Some(&password) => password.clone(),
+            None if passwords.len() == 1 => passwords[0].clone(),
+            None if passwords.is_empty() => {
+                io.error(
+                    format!("Woops, I can't find any passwords for \"{}\".", query),
+                    OutputType::Error,
+                );
+                return Err(CommandError::NotFound);
+            }
+            None => {
+                io.error(
+                    format!(
+                        "Woops, \"{}\" matches several passwords, so I won't guess which one to delete:",
+                        query
+                    ),
+                    OutputType::Error,
+                );
+                for p in passwords.iter() {
+                    io.info(format!("  {} {}", p.name, p.username), OutputType::Error);
+                }
+                io.info(
+                    "Run `rooster delete` again with the full app name, or with --username.",
+                    OutputType::Error,
+                );
+                return Err(CommandError::InvalidInput);
+            }
+        }
+    };
+
+    if !matches.get_flag("yes")
+        && !confirm(
+            format!(
+                "Delete the password for {} ({})? [y/N]",
+                password.name, password.username
+            ),
+            io,
+        )
+    {
+        io.info("Alright, I didn't delete anything.", OutputType::Error);
+        return Err(CommandError::UserAbort);
+    }
 
-    let password = list::search_and_choose_password(
-        store,
-        query,
-        list::WITH_NUMBERS,
-        "Which password would you like me to delete?",
-        io,
-    )
-    .ok_or(1)?
-    .clone();
-
-    if let Err(err) = store.delete_password(&password.name) {
+    if let Err(err) = store.trash_password(&password.name) {
         io.error(
             format!(
                 "Woops, I couldn't delete this password (reason: {:?}).",
@@ -28,13 +79,100 @@ pub fn callback_exec(
             ),
             OutputType::Error,
         );
-        return Err(1);
+        return Err(CommandError::Store);
+    }
+
+    if matches.get_flag("dry-run") {
+        io.info(
+            format!(
+                "I would have moved the password for \"{}\" to the trash.",
+                password.name
+            ),
+            OutputType::Error,
+        );
+        return Ok(());
     }
 
     io.success(
-        format!("Done! I've deleted the password for \"{}\".", password.name),
-        OutputType::Standard,
+        format!(
+            "Done! I've moved the password for \"{}\" to the trash. \
+             You can restore it with `rooster trash restore '{}'`.",
+            password.name, password.name
+        ),
+        OutputType::Error,
+    );
+
+    Ok(())
+}
+
+/// Deletes every password picked with --contains, --regex, --tag or --exact, all or nothing.
+fn delete_selection(
+    matches: &clap::ArgMatches,
+    filter: &select::Filter,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let passwords: Vec<password::v2::Password> = select::select_entries(store, filter)
+        .into_iter()
+        .cloned()
+        .collect();
+    if passwords.is_empty() {
+        io.error(
+            "Woops, no password matches, so there is nothing to delete.",
+            OutputType::Error,
+        );
+        return Err(CommandError::NotFound);
+    }
+
+    io.info(
+        format!("{} password(s) match:", passwords.len()),
+        OutputType::Error,
     );
+    for p in passwords.iter() {
+        io.info(format!("  {} {}", p.name, p.username), OutputType::Error);
+    }
+    if !matches.get_flag("yes")
+        && !confirm(
+            format!("Delete these {} password(s)? [y/N]", passwords.len()),
+            io,
+        )
+    {
+        io.info("Alright, I didn't delete anything.", OutputType::Error);
+        return Err(CommandError::UserAbort);
+    }
 
+    for p in passwords.iter() {
+        // Nothing is saved when we return an error, so the other passwords are kept too.
+        if let Err(err) = store.trash_password(&p.name) {
+            io.error(
+                format!(
+                    "Woops, I couldn't delete the password for {} (reason: {:?}).",
+                    p.name, err
+                ),
+                OutputType::Error,
+            );
+            return Err(CommandError::Store);
+        }
+    }
+
+    if matches.get_flag("dry-run") {
+        io.info(
+            format!(
+                "I would have moved {} password(s) to the trash.",
+                passwords.len()
+            ),
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    io.success(
+        format!(
+            "Done! I've moved {} password(s) to the trash. \
+             You can restore them with `rooster trash restore`.",
+            passwords.len()
+        ),
+        OutputType::Error,
+    );
     Ok(())
 }
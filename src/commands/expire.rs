@@ -0,0 +1,80 @@
+use crate::commands::CommandError;
+use crate::date;
+use crate::ffi;
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let query = matches.get_one::<String>("app").unwrap();
+    let date = matches.get_one::<String>("date").unwrap();
+
+    let expires_at = if date == "never" {
+        None
+    } else {
+        match date::parse_date(date) {
+            Some(timestamp) => Some(timestamp),
+            None => {
+                io.error(
+                    format!(
+                        "Woops, \"{}\" is not a valid date. Use the YYYY-MM-DD format, or \"never\".",
+                        date
+                    ),
+                    OutputType::Error,
+                );
+                return Err(CommandError::InvalidInput);
+            }
+        }
+    };
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which password would you like to set the expiry date for?",
+        io,
+    )
+    .ok_or(CommandError::NotFound)?
+    .clone();
+
+    let change_result =
+        store.change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                expires_at,
+                updated_at: ffi::time(),
+                ..old_password
+            }
+        });
+
+    match change_result {
+        Ok(_) => {
+            match expires_at {
+                Some(timestamp) => io.success(
+                    format!(
+                        "Done! The password for {} expires on {}.",
+                        password.name,
+                        date::format_date(timestamp)
+                    ),
+                    OutputType::Error,
+                ),
+                None => io.success(
+                    format!("Done! The password for {} never expires.", password.name),
+                    OutputType::Error,
+                ),
+            }
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I couldn't save the expiry date (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            Err(CommandError::Store)
+        }
+    }
+}
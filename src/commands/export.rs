@@ -1,12 +1,18 @@
+use crate::armor;
+use crate::commands::CommandError;
 use crate::password;
-use crate::password::v2::Password;
+use crate::password::v2::{Password, PasswordStore};
+use crate::password::PasswordError;
+use crate::select;
 use csv::Writer;
 use rclio::CliInputOutput;
 use rclio::OutputType;
 use rtoolbox::safe_string::SafeString;
+use rtoolbox::safe_vec::SafeVec;
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::io::Cursor;
+use std::fs::File;
+use std::io::{Cursor, Result as IoResult, Write};
 use std::ops::Deref;
 
 #[derive(Serialize, Deserialize)]
@@ -18,27 +24,103 @@ pub fn callback_exec(
     matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
     let subcommand_name = matches.subcommand_name().unwrap();
     let subcommand_matches = matches.subcommand_matches(subcommand_name).unwrap();
 
+    let filter = select::Filter::from_matches(subcommand_matches).map_err(|err| {
+        io.error(err, OutputType::Error);
+        CommandError::InvalidInput
+    })?;
+    if !filter.is_empty() && select::select_entries(store, &filter).is_empty() {
+        io.error(
+            "Woops, no password matches, so there is nothing to export.",
+            OutputType::Error,
+        );
+        return Err(CommandError::NotFound);
+    }
+
+    // Exports without a file go to stdout, which is fine in a dry run.
+    let path = match subcommand_name {
+        "encrypted" | "markdown" => subcommand_matches.get_one::<String>("path"),
+        _ => subcommand_matches.get_one::<String>("output"),
+    };
+    if let (true, Some(path)) = (matches.get_flag("dry-run"), path) {
+        io.info(
+            format!("I would have exported your passwords to {}.", path),
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    if subcommand_name == "encrypted" {
+        return export_encrypted(subcommand_matches, &filter, store, io);
+    } else if subcommand_name == "markdown" {
+        return export_to_markdown(subcommand_matches, &filter, store, io);
+    }
+
+    let output = subcommand_matches.get_one::<String>("output");
+    if !subcommand_matches.get_flag("plaintext") {
+        return export_armored(subcommand_name, output, &filter, store, io);
+    }
+    io.warning(
+        "This export contains your passwords in clear text. Keep it safe, and delete it \
+         once you're done.",
+        OutputType::Error,
+    );
+
     if subcommand_name == "json" {
-        export_to_json(subcommand_matches, store, io)
+        export_to_json(output, &filter, store, io)
     } else if subcommand_name == "csv" {
-        export_to_csv(subcommand_matches, store, io)
+        export_to_csv(output, &filter, store, io)
     } else if subcommand_name == "1password" {
-        export_to_csv(subcommand_matches, store, io)
+        export_to_csv(output, &filter, store, io)
     } else {
         unimplemented!("Invalid export destination")
     }
 }
 
+/// Writes the export to `--output`, readable by its owner only, or to stdout so that it can
+/// be piped.
+fn write_export(
+    export: &str,
+    output: Option<&String>,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let path = match output {
+        Some(path) => path,
+        None => {
+            io.write(export, OutputType::Standard);
+            return Ok(());
+        }
+    };
+
+    create_export_file(path)
+        .and_then(|mut file| {
+            file.write_all(export.as_bytes())
+                .and_then(|_| file.sync_all())
+        })
+        .map_err(|err| {
+            io.error(
+                format!("Woops, I could not write the export (reason: {}).", err),
+                OutputType::Error,
+            );
+            CommandError::Io
+        })?;
+    io.success(
+        format!("Alright! Your passwords are exported to {}.", path),
+        OutputType::Error,
+    );
+    Ok(())
+}
+
 fn export_to_csv(
-    _matches: &clap::ArgMatches,
+    output: Option<&String>,
+    filter: &select::Filter,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
-    let passwords_ref = store.get_all_passwords();
+) -> Result<(), CommandError> {
+    let passwords_ref = select::select_entries(store, filter);
     let output_cursor: Cursor<Vec<u8>> = Cursor::new(Vec::new());
     let mut csv_writer = Writer::from_writer(output_cursor);
     for password in passwords_ref {
@@ -48,25 +130,23 @@ fn export_to_csv(
             password.password.deref().as_str(),
         ]) {
             Ok(_) => {}
-            Err(_) => return Err(1),
+            Err(_) => return Err(CommandError::Io),
         }
     }
-    io.write(
+    let csv = SafeString::from_string(
         String::from_utf8(csv_writer.into_inner().unwrap().into_inner()).unwrap(),
-        OutputType::Standard,
     );
-
-    return Ok(());
+    write_export(csv.deref(), output, io)
 }
 
 fn export_to_json(
-    _matches: &clap::ArgMatches,
+    output: Option<&String>,
+    filter: &select::Filter,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
     let export = JsonExport {
-        passwords: store
-            .get_all_passwords()
+        passwords: select::select_entries(store, filter)
             .into_iter()
             .map(|password| password.clone())
             .collect(),
@@ -81,11 +161,246 @@ fn export_to_json(
                 ),
                 OutputType::Error,
             );
-            return Err(1);
+            return Err(CommandError::Store);
         }
     };
 
     let passwords = SafeString::from_string(passwords_json);
-    io.write(format!("{}", passwords.deref()), OutputType::Standard);
-    return Ok(());
+    write_export(passwords.deref(), output, io)
+}
+
+/// Creates the export file, readable by its owner only. An existing file is overwritten,
+/// and its permissions are tightened first, since the mode only applies to new files.
+fn create_export_file(path: &str) -> IoResult<File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(file)
+}
+
+/// The whole store, trash included, unless only some passwords are selected.
+fn encrypt_selection(
+    filter: &select::Filter,
+    store: &PasswordStore,
+) -> Result<SafeVec, PasswordError> {
+    if filter.is_empty() {
+        return store.to_bytes();
+    }
+    store.to_bytes_with_passwords(
+        select::select_entries(store, filter)
+            .into_iter()
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Writes the store as it is on disk, encrypted with the master password, so that
+/// `rooster import encrypted` can read it back. It is never written in clear text.
+fn export_encrypted(
+    matches: &clap::ArgMatches,
+    filter: &select::Filter,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let path = matches.get_one::<String>("path").unwrap();
+
+    let encrypted = encrypt_selection(filter, store).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I could not encrypt the passwords (reason: {:?}).",
+                err
+            ),
+            OutputType::Error,
+        );
+        CommandError::Store
+    })?;
+
+    create_export_file(path)
+        .and_then(|mut file| file.write_all(&encrypted).and_then(|_| file.sync_all()))
+        .map_err(|err| {
+            io.error(
+                format!("Woops, I could not write the export (reason: {}).", err),
+                OutputType::Error,
+            );
+            CommandError::Io
+        })?;
+
+    io.success(
+        format!(
+            "Alright! Your passwords are exported to {}, protected by your master password.",
+            path
+        ),
+        OutputType::Error,
+    );
+    Ok(())
+}
+
+/// The default for the json, csv and 1password exports: the store, encrypted like on disk,
+/// as text so that it can be piped. `rooster import encrypted` reads it back.
+fn export_armored(
+    format: &str,
+    output: Option<&String>,
+    filter: &select::Filter,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let encrypted = encrypt_selection(filter, store).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I could not encrypt the passwords (reason: {:?}).",
+                err
+            ),
+            OutputType::Error,
+        );
+        CommandError::Store
+    })?;
+
+    write_export(&armor::armor(encrypted.deref()), output, io)?;
+    io.info(
+        format!(
+            "This export is encrypted with your master password, import it with `rooster \
+             import encrypted`. To export in {} instead, add --plaintext.",
+            format
+        ),
+        OutputType::Error,
+    );
+    Ok(())
+}
+
+/// Escapes the characters that would break a Markdown table or be rendered as formatting.
+fn escape_markdown(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '~' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("<br>"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A Markdown document listing every password, meant to be printed and kept somewhere safe.
+fn get_markdown_export(passwords: &Vec<&Password>, with_secrets: bool) -> SafeString {
+    let mut markdown = String::from("# Rooster passwords\n\n");
+    if with_secrets {
+        markdown.push_str("> **⚠ This document contains secrets. Keep it somewhere safe.**\n\n");
+        markdown.push_str("| App | Username | Password | Tags | Note |\n");
+        markdown.push_str("| --- | --- | --- | --- | --- |\n");
+    } else {
+        markdown.push_str("| App | Username | Tags |\n");
+        markdown.push_str("| --- | --- | --- |\n");
+    }
+
+    for p in passwords.iter() {
+        let tags = escape_markdown(p.tags.join(", ").as_str());
+        if with_secrets {
+            markdown.push_str(
+                format!(
+                    "| {} | {} | {} | {} | {} |\n",
+                    escape_markdown(p.name.as_str()),
+                    escape_markdown(p.username.as_str()),
+                    escape_markdown(p.password.deref()),
+                    tags,
+                    escape_markdown(p.notes.deref())
+                )
+                .as_str(),
+            );
+        } else {
+            markdown.push_str(
+                format!(
+                    "| {} | {} | {} |\n",
+                    escape_markdown(p.name.as_str()),
+                    escape_markdown(p.username.as_str()),
+                    tags
+                )
+                .as_str(),
+            );
+        }
+    }
+
+    SafeString::from_string(markdown)
+}
+
+fn export_to_markdown(
+    matches: &clap::ArgMatches,
+    filter: &select::Filter,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let path = matches.get_one::<String>("path").unwrap();
+    let with_secrets = !matches.get_flag("no-secrets");
+    let markdown = get_markdown_export(&select::select_entries(store, filter), with_secrets);
+
+    create_export_file(path)
+        .and_then(|mut file| {
+            file.write_all(markdown.deref().as_bytes())
+                .and_then(|_| file.sync_all())
+        })
+        .map_err(|err| {
+            io.error(
+                format!("Woops, I could not write the export (reason: {}).", err),
+                OutputType::Error,
+            );
+            CommandError::Io
+        })?;
+
+    io.success(
+        format!("Alright! Your passwords are exported to {}.", path),
+        OutputType::Error,
+    );
+    if with_secrets {
+        io.warning(
+            "This file contains your passwords in clear text. Delete it once printed.",
+            OutputType::Error,
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{escape_markdown, get_markdown_export};
+    use crate::password::v2::Password;
+    use rtoolbox::safe_string::SafeString;
+    use std::ops::Deref;
+
+    #[test]
+    fn test_escape_markdown() {
+        assert_eq!(escape_markdown("Google"), "Google");
+        assert_eq!(escape_markdown("a|b"), "a\\|b");
+        assert_eq!(escape_markdown("*bold* _it_"), "\\*bold\\* \\_it\\_");
+        assert_eq!(escape_markdown("line 1\nline 2"), "line 1<br>line 2");
+    }
+
+    #[test]
+    fn test_get_markdown_export() {
+        let mut password = Password::new("my|app", "me", "ab`c");
+        password.notes = SafeString::from_string("a note".to_owned());
+
+        let markdown = get_markdown_export(&vec![&password], true);
+        assert!(markdown.deref().contains("contains secrets"));
+        assert!(markdown
+            .deref()
+            .contains("| my\\|app | me | ab\\`c |  | a note |"));
+
+        let markdown = get_markdown_export(&vec![&password], false);
+        assert!(!markdown.deref().contains("contains secrets"));
+        assert!(!markdown.deref().contains("ab\\`c"));
+        assert!(markdown.deref().contains("| my\\|app | me |  |"));
+    }
 }
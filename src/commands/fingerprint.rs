@@ -0,0 +1,18 @@
+use crate::commands::CommandError;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+/// Prints the fingerprint of the store that was opened, which is the one given with
+/// `--file` if any. Two stores with the same passwords have the same fingerprint.
+pub fn callback_exec(
+    _matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    io.write(
+        format!("{}\n", store.content_fingerprint()),
+        OutputType::Standard,
+    );
+    Ok(())
+}
@@ -1,6 +1,8 @@
-use crate::clip::{copy_to_clipboard, paste_keys};
-use crate::generate::{check_password_len, PasswordSpec};
+use crate::clip::{clear_after_message, copy_to_clipboard_with_timeout, paste_keys, ClearAfter};
+use crate::commands::CommandError;
+use crate::generate::{check_charset, check_password_len, PasswordSpec};
 use crate::password;
+use fancy_regex::Regex;
 use rclio::CliInputOutput;
 use rclio::OutputType;
 
@@ -10,24 +12,59 @@ pub fn callback_exec(
     matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
     let app_name = matches.get_one::<String>("app").unwrap();
     let username = matches.get_one::<String>("username").unwrap();
+    let clear_after = ClearAfter::from_matches(matches);
 
     if store.has_password(app_name.deref()) {
         io.error(
             "Woops: alnum, there is already an app with that name.",
             OutputType::Error,
         );
-        return Err(1);
+        return Err(CommandError::Duplicate);
     }
 
+    let charset = match matches.get_one::<String>("charset") {
+        Some(charset) => Some(check_charset(charset, io).ok_or(CommandError::InvalidInput)?),
+        None => None,
+    };
+
     let pwspec = PasswordSpec::new(
         matches.get_flag("alnum"),
         check_password_len(*matches.get_one::<usize>("length").unwrap(), io),
-    );
+    )
+    .with_charset(charset);
 
-    let password_as_string = match pwspec.generate_hard_password() {
+    let generated = match matches.get_one::<String>("match-regex") {
+        Some(pattern) => {
+            let regex = match Regex::new(pattern) {
+                Ok(regex) => regex,
+                Err(err) => {
+                    io.error(
+                        format!("Woops, this regex is not valid (reason: {}).", err),
+                        OutputType::Error,
+                    );
+                    return Err(CommandError::InvalidInput);
+                }
+            };
+            match pwspec.generate_password_matching(&regex) {
+                Ok(Some(password_as_string)) => Ok(password_as_string),
+                Ok(None) => {
+                    io.error(
+                        "Woops, I could not generate a password that matches this regex. \
+                         Check that it allows the password length and characters.",
+                        OutputType::Error,
+                    );
+                    return Err(CommandError::InvalidInput);
+                }
+                Err(err) => Err(err),
+            }
+        }
+        None => pwspec.generate_hard_password(),
+    };
+
+    let password_as_string = match generated {
         Ok(password_as_string) => password_as_string,
         Err(io_err) => {
             io.error(
@@ -37,7 +74,7 @@ pub fn callback_exec(
                 ),
                 OutputType::Error,
             );
-            return Err(1);
+            return Err(CommandError::Io);
         }
     };
 
@@ -47,18 +84,24 @@ pub fn callback_exec(
 
     match store.add_password(password) {
         Ok(_) => {
+            if matches.get_flag("dry-run") {
+                io.info(
+                    format!("I would have generated a password for {}.", app_name),
+                    OutputType::Error,
+                );
+                return Ok(());
+            }
+
             if matches.get_flag("show") {
+                io.success("Alright! Here is your password:", OutputType::Error);
                 io.success(
-                    format!(
-                        "Alright! Here is your password: {}",
-                        password_as_string_clipboard.deref()
-                    ),
+                    format!("{}", password_as_string_clipboard.deref()),
                     OutputType::Standard,
                 );
                 return Ok(());
             }
 
-            if copy_to_clipboard(&password_as_string_clipboard).is_err() {
+            if copy_to_clipboard_with_timeout(&password_as_string_clipboard, clear_after).is_err() {
                 io.success(
                     format!(
                         "Hmm, I tried to copy your new password to your clipboard, but \
@@ -66,15 +109,16 @@ pub fn callback_exec(
                          with `rooster get {} --show`",
                         app_name
                     ),
-                    OutputType::Standard,
+                    OutputType::Error,
                 );
             } else {
                 io.success(
                     format!(
-                        "Alright! I've saved your new password. You can paste it anywhere with {}.",
-                        paste_keys()
+                        "Alright! I've saved your new password. You can paste it anywhere with {}{}.",
+                        paste_keys(),
+                        clear_after_message(clear_after)
                     ),
-                    OutputType::Standard,
+                    OutputType::Error,
                 );
             }
 
@@ -85,7 +129,7 @@ pub fn callback_exec(
                 format!("\nI couldn't add this password (reason: {:?}).", err),
                 OutputType::Error,
             );
-            Err(1)
+            Err(CommandError::Store)
         }
     }
 }
@@ -1,14 +1,34 @@
 use crate::clip;
-
+use crate::commands::CommandError;
+use crate::date;
+use crate::ffi;
 use crate::list;
+use crate::otp;
 use crate::password;
+use crate::qr;
 use rclio::CliInputOutput;
+use rclio::OutputType;
+use rtoolbox::safe_string::SafeString;
+use serde::Serialize;
+use std::ops::Deref;
+
+/// A password in `--format json` output. The password itself is only there with `--show`.
+#[derive(Serialize)]
+struct JsonPassword<'a> {
+    app: &'a str,
+    username: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password: Option<&'a str>,
+}
+
+/// Warn when getting a password that expires within this many days.
+const EXPIRY_WARNING_DAYS: i64 = 7;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
     let show = matches.get_flag("show");
     let query = matches.get_one::<String>("app").unwrap();
 
@@ -20,10 +40,312 @@ pub fn callback_exec(
             "to copy to your clipboard"
         },
     );
-    let password =
-        list::search_and_choose_password(store, query, list::WITH_NUMBERS, &prompt, io).ok_or(1)?;
+    let username = matches.get_one::<String>("username").map(|s| s.as_str());
+    let password = list::search_and_choose_password_for_account(
+        store,
+        query,
+        username,
+        list::WITH_NUMBERS,
+        &prompt,
+        io,
+    )
+    .ok_or(CommandError::NotFound)?;
+
+    let now = ffi::time();
+    if let Some(expires_at) = password.expires_at {
+        if password.is_expired(now) {
+            io.error(
+                format!(
+                    "The password for {} expired on {}. If you still need it, run `rooster expire '{}' never`.",
+                    password.name,
+                    date::format_date(expires_at),
+                    password.name
+                ),
+                OutputType::Error,
+            );
+            return Err(CommandError::CheckFailed);
+        }
+
+        let days_left = date::days_between(now, expires_at);
+        if days_left < EXPIRY_WARNING_DAYS {
+            io.warning(
+                format!(
+                    "Heads up, the password for {} expires on {}.",
+                    password.name,
+                    date::format_date(expires_at)
+                ),
+                OutputType::Error,
+            );
+        }
+    }
+
+    if matches.get_flag("show-qr") {
+        return retrieve_as_qr(matches.get_flag("totp"), &password, io);
+    }
+
+    let show = clip::must_show(show, &password, io);
+    let clear_after = clip::ClearAfter::from_matches(matches);
+    let verbose = matches.get_flag("verbose");
+    if !show && matches.get_flag("dry-run") {
+        io.info(
+            format!(
+                "I would have copied your {} for {} to your clipboard.",
+                if matches.get_flag("copy-username") {
+                    "username"
+                } else {
+                    "password"
+                },
+                password.name
+            ),
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+    if list::wants_json(matches) {
+        return retrieve_as_json(
+            show,
+            matches.get_flag("copy-username"),
+            clear_after,
+            verbose,
+            &password,
+            io,
+        );
+    }
+    if matches.get_flag("copy-username") {
+        return retrieve_username(show, clear_after, verbose, &password, io);
+    }
+    clip::confirm_password_retrieved(show, clear_after, verbose, &password, io);
+
+    if matches.get_flag("totp") {
+        return retrieve_totp_code(show, clear_after, &password, io);
+    }
+
+    Ok(())
+}
+
+/// The second step of `--totp`: once the password is in, the one-time code. When copying,
+/// we wait for the user to paste the password first, since both go through the clipboard.
+fn retrieve_totp_code(
+    show: bool,
+    clear_after: Option<clip::ClearAfter>,
+    password: &password::v2::Password,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let secret = match password.otp_secret {
+        Some(ref secret) => secret,
+        None => {
+            io.info(
+                format!(
+                    "There is no TOTP secret for {}, so there is no code to copy.",
+                    password.name
+                ),
+                OutputType::Error,
+            );
+            return Ok(());
+        }
+    };
+
+    if !show {
+        io.info(
+            "Press ENTER once you have pasted your password, and I will copy the TOTP code.",
+            OutputType::Error,
+        );
+        if let Err(err) = io.read_line() {
+            io.error(
+                format!("Woops, I didn't see the ENTER key (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            return Err(CommandError::Io);
+        }
+    }
 
-    clip::confirm_password_retrieved(show, &password, io);
+    // Only compute the code now, so that it doesn't expire while the user was pasting.
+    let code =
+        otp::totp_now(secret, otp::DEFAULT_PERIOD, otp::DEFAULT_DIGITS).ok_or_else(|| {
+            io.error(
+                format!(
+                    "Woops, the TOTP secret for {} is not valid base32.",
+                    password.name
+                ),
+                OutputType::Error,
+            );
+            CommandError::InvalidInput
+        })?;
+    let seconds_remaining = otp::seconds_remaining(otp::DEFAULT_PERIOD);
 
+    if show {
+        io.success(format!("Code: {}", code), OutputType::Standard);
+    } else if clip::copy_to_clipboard_with_timeout(&SafeString::from_string(code), clear_after)
+        .is_err()
+    {
+        io.success(
+            format!(
+                "Hmm, I tried to copy the TOTP code to your clipboard, but something went \
+                 wrong. You can see it with `rooster otp '{}'`",
+                password.name
+            ),
+            OutputType::Error,
+        );
+        return Ok(());
+    } else {
+        io.success(
+            format!(
+                "Alright! The TOTP code is in your clipboard, paste it with {}.",
+                clip::paste_keys()
+            ),
+            OutputType::Error,
+        );
+    }
+    io.info(
+        format!("This code is valid for {} more seconds.", seconds_remaining),
+        OutputType::Error,
+    );
+    Ok(())
+}
+
+/// Shows the password, or with `--totp` the `otpauth://` URI of the TOTP secret, as a QR
+/// code. Whoever can see the screen can scan it too, so the user is warned first.
+fn retrieve_as_qr(
+    totp: bool,
+    password: &password::v2::Password,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let (secret, what) = if totp {
+        let otp_secret = match password.otp_secret {
+            Some(ref secret) => secret,
+            None => {
+                io.error(
+                    format!("Woops, there is no TOTP secret for {}.", password.name),
+                    OutputType::Error,
+                );
+                return Err(CommandError::NotFound);
+            }
+        };
+        let uri =
+            otp::otpauth_uri(&password.name, &password.username, otp_secret).ok_or_else(|| {
+                io.error(
+                    format!(
+                        "Woops, the TOTP secret for {} is not valid base32.",
+                        password.name
+                    ),
+                    OutputType::Error,
+                );
+                CommandError::InvalidInput
+            })?;
+        (uri, "TOTP secret")
+    } else {
+        (password.password.clone(), "password")
+    };
+
+    let rendered = match qr::render_qr(secret.deref()) {
+        Some(rendered) => rendered,
+        None => {
+            io.error(
+                format!(
+                    "Woops, your {} for {} is too long for a QR code (at most {} bytes).",
+                    what,
+                    password.name,
+                    qr::MAX_DATA_LEN
+                ),
+                OutputType::Error,
+            );
+            return Err(CommandError::InvalidInput);
+        }
+    };
+
+    io.warning(
+        format!(
+            "Here is your {} for {} as a QR code. Anyone who can see your screen can scan it \
+             too, so clear your terminal once your phone has it.",
+            what, password.name
+        ),
+        OutputType::Error,
+    );
+    io.write(rendered.deref().as_str(), OutputType::Standard);
+    Ok(())
+}
+
+fn retrieve_username(
+    show: bool,
+    clear_after: Option<clip::ClearAfter>,
+    verbose: bool,
+    password: &password::v2::Password,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    if show {
+        io.write(format!("{}\n", password.username), OutputType::Standard);
+        return Ok(());
+    }
+
+    let username = SafeString::from_string(password.username.clone());
+    let copied = clip::copy_to_clipboard_with_timeout(&username, clear_after);
+    if verbose {
+        clip::report_clipboard_backend(&copied, io);
+    }
+    if copied.is_err() {
+        io.success(
+            format!(
+                "Hmm, I tried to copy your username to your clipboard, but something went \
+                 wrong. You can see it with `rooster get '{}' -u --show`",
+                password.name
+            ),
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    io.success(
+        format!(
+            "Alright! Your username for {} is in your clipboard, paste it with {}{}.",
+            password.name,
+            clip::paste_keys(),
+            clip::clear_after_message(clear_after)
+        ),
+        OutputType::Error,
+    );
+    Ok(())
+}
+
+fn retrieve_as_json(
+    show: bool,
+    copy_username: bool,
+    clear_after: Option<clip::ClearAfter>,
+    verbose: bool,
+    password: &password::v2::Password,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    if !show {
+        let copied = if copy_username {
+            SafeString::from_string(password.username.clone())
+        } else {
+            password.password.clone()
+        };
+        let result = clip::copy_to_clipboard_with_timeout(&copied, clear_after);
+        if verbose {
+            clip::report_clipboard_backend(&result, io);
+        }
+        if result.is_err() {
+            io.error(
+                "Woops, I couldn't copy to your clipboard. Use --show to get the password instead.",
+                OutputType::Error,
+            );
+            return Err(CommandError::Io);
+        }
+    }
+
+    let output = JsonPassword {
+        app: password.name.as_str(),
+        username: password.username.as_str(),
+        password: if show {
+            Some(password.password.deref().as_str())
+        } else {
+            None
+        },
+    };
+    // This should never fail. The structs are all encodable.
+    io.write(
+        format!("{}\n", serde_json::to_string(&output).unwrap()),
+        OutputType::Standard,
+    );
     Ok(())
 }
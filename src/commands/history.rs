@@ -0,0 +1,50 @@
+use crate::commands::CommandError;
+use crate::date;
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use std::ops::Deref;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which password history would you like to see?",
+        io,
+    )
+    .ok_or(CommandError::NotFound)?;
+
+    if password.history.is_empty() {
+        io.info(
+            format!("There are no previous passwords for {}.", password.name),
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    io.success(
+        format!("Here are the previous passwords for {}:", password.name),
+        OutputType::Error,
+    );
+    for previous in password.history.iter() {
+        io.info(
+            format!(
+                "{} {} {}",
+                date::format_datetime(previous.changed_at),
+                previous.username,
+                previous.password.deref()
+            ),
+            OutputType::Standard,
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use crate::commands::CommandError;
+use crate::ffi;
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+/// Emoji can be made of several code points, like flags or skin tones, but an icon is still
+/// meant to be a single glyph.
+const MAX_ICON_CHARS: usize = 8;
+
+fn is_valid_icon(icon: &str) -> bool {
+    !icon.is_empty()
+        && icon.chars().count() <= MAX_ICON_CHARS
+        && !icon.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let query = matches.get_one::<String>("app").unwrap();
+    let icon = matches.get_one::<String>("icon").cloned();
+
+    if let Some(ref icon) = icon {
+        if !is_valid_icon(icon) {
+            io.error(
+                format!(
+                    "Woops, \"{}\" is not a valid icon. Use a single emoji or character.",
+                    icon
+                ),
+                OutputType::Error,
+            );
+            return Err(CommandError::InvalidInput);
+        }
+    }
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which password would you like to set an icon for?",
+        io,
+    )
+    .ok_or(CommandError::NotFound)?
+    .clone();
+
+    let change_result =
+        store.change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                icon: icon.clone(),
+                updated_at: ffi::time(),
+                ..old_password
+            }
+        });
+
+    match change_result {
+        Ok(_) => {
+            match icon {
+                Some(icon) => io.success(
+                    format!("Done! {} now shows as {} in the list.", password.name, icon),
+                    OutputType::Error,
+                ),
+                None => io.success(
+                    format!("Done! I've removed the icon of {}.", password.name),
+                    OutputType::Error,
+                ),
+            }
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I couldn't save the icon (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            Err(CommandError::Store)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::is_valid_icon;
+
+    #[test]
+    fn test_is_valid_icon() {
+        assert!(is_valid_icon("🔑"));
+        assert!(is_valid_icon("*"));
+        assert!(is_valid_icon("🇫🇷"));
+        assert!(!is_valid_icon(""));
+        assert!(!is_valid_icon("a b"));
+        assert!(!is_valid_icon("\t"));
+        assert!(!is_valid_icon("much too long"));
+    }
+}
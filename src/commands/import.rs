@@ -1,60 +1,309 @@
+use crate::armor;
+use crate::commands::CommandError;
 use crate::ffi;
+use crate::otp;
 use crate::password;
 use crate::password::v2::{Password, PasswordStore};
+use crate::password::PasswordError;
 use rclio::{CliInputOutput, OutputType};
+use rtoolbox::safe_string::SafeString;
+use rtoolbox::safe_vec::SafeVec;
 use serde::{Deserialize, Serialize};
 use serde_json;
+use std::env;
 use std::fs::File;
+use std::io::Read;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Imports with more entries than the soft limit need a confirmation, and imports with more
+/// entries than the hard limit are refused. Large legitimate imports can raise both.
+pub const SOFT_LIMIT_ENV_VAR: &'static str = "ROOSTER_IMPORT_SOFT_LIMIT";
+pub const HARD_LIMIT_ENV_VAR: &'static str = "ROOSTER_IMPORT_HARD_LIMIT";
+pub const DEFAULT_SOFT_LIMIT: usize = 100_000;
+pub const DEFAULT_HARD_LIMIT: usize = 1_000_000;
 
 #[derive(Serialize, Deserialize)]
 pub struct JsonExport {
     passwords: Vec<Password>,
 }
 
+/// The parts of an unencrypted Bitwarden JSON export that we can import.
+#[derive(Deserialize)]
+struct BitwardenExport {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    items: Vec<BitwardenItem>,
+}
+
+/// Bitwarden item types are 1/Login, 2/Secure note, 3/Card and 4/Identity.
+const BITWARDEN_LOGIN_TYPE: u8 = 1;
+
+#[derive(Deserialize)]
+struct BitwardenItem {
+    #[serde(rename = "type")]
+    item_type: u8,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    login: Option<BitwardenLogin>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenLogin {
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    totp: Option<String>,
+    #[serde(default)]
+    uris: Option<Vec<BitwardenUri>>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenUri {
+    #[serde(default)]
+    uri: Option<String>,
+}
+
 pub fn callback_exec(
     matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
     let subcommand_name = matches.subcommand_name().unwrap();
     let subcommand_matches = matches.subcommand_matches(subcommand_name).unwrap();
+    let path_str = subcommand_matches.get_one::<String>("path").unwrap();
+
+    // KeePass exports are usually a whole vault, so we ask before overwriting anything.
+    let on_conflict = match subcommand_matches.get_one::<String>("on-conflict") {
+        Some(s) if s == "update" => OnConflict::Update,
+        Some(s) if s == "ask" => OnConflict::Ask,
+        Some(_) => OnConflict::Skip,
+        None if subcommand_name == "keepass" => OnConflict::Ask,
+        None => OnConflict::Skip,
+    };
+
+    import_from(subcommand_name, path_str, on_conflict, store, io)?;
 
-    let (valid, invalid) = if subcommand_name == "json" {
-        create_imported_passwords_from_json(subcommand_matches, io)
-    } else if subcommand_name == "csv" {
-        create_imported_passwords_from_csv(subcommand_matches, io)
-    } else if subcommand_name == "1password" {
-        create_imported_passwords_from_1password(subcommand_matches, io)
+    if matches.get_flag("dry-run") {
+        io.info(
+            "I would have imported the passwords counted above.",
+            OutputType::Error,
+        );
+    }
+    Ok(())
+}
+
+/// The import sources, as named on the command line.
+pub const SOURCES: [&'static str; 7] = [
+    "json",
+    "csv",
+    "1password",
+    "bitwarden",
+    "keepass",
+    "pass",
+    "encrypted",
+];
+
+/// Imports the passwords from `path_str`, `source` being one of `SOURCES`.
+pub fn import_from(
+    source: &str,
+    path_str: &str,
+    on_conflict: OnConflict,
+    store: &mut PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let soft_limit = read_limit(SOFT_LIMIT_ENV_VAR, DEFAULT_SOFT_LIMIT).map_err(|err| {
+        io.error(err, OutputType::Error);
+        CommandError::InvalidInput
+    })?;
+    let hard_limit = read_limit(HARD_LIMIT_ENV_VAR, DEFAULT_HARD_LIMIT).map_err(|err| {
+        io.error(err, OutputType::Error);
+        CommandError::InvalidInput
+    })?;
+
+    let (valid, invalid) = if source == "json" {
+        create_imported_passwords_from_json(path_str, hard_limit, io)
+    } else if source == "csv" {
+        create_imported_passwords_from_csv(path_str, hard_limit, io)
+    } else if source == "1password" {
+        create_imported_passwords_from_1password(path_str, hard_limit, io)
+    } else if source == "pass" {
+        create_imported_passwords_from_pass(path_str, hard_limit, io)
+    } else if source == "bitwarden" {
+        create_imported_passwords_from_bitwarden(path_str, hard_limit, io)
+    } else if source == "keepass" {
+        create_imported_passwords_from_keepass(path_str, hard_limit, io)
+    } else if source == "encrypted" {
+        create_imported_passwords_from_encrypted(path_str, hard_limit, io)
     } else {
         unimplemented!("Invalid import source")
     }?;
 
-    import_passwords(valid, invalid, store, io)
+    check_import_size(valid.len() + invalid.len(), soft_limit, io)?;
+
+    import_passwords(valid, invalid, on_conflict, store, io)
+}
+
+fn read_limit(env_var: &str, default: usize) -> Result<usize, String> {
+    match env::var(env_var) {
+        Ok(value) => value.parse::<usize>().map_err(|_| {
+            format!(
+                "The {} environment variable must be a number (got \"{}\").",
+                env_var, value
+            )
+        }),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Called by the importers as they read entries, so that a file with more entries than the
+/// hard limit is refused before it is read whole, and before pass entries are decrypted.
+fn check_hard_limit(
+    count: usize,
+    hard_limit: usize,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    if count > hard_limit {
+        io.error(
+            format!(
+                "Woops, this file has more than the {} entries I can import at once. If it is \
+                 really what you want to import, raise the limit with the {} environment \
+                 variable.",
+                hard_limit, HARD_LIMIT_ENV_VAR
+            ),
+            OutputType::Error,
+        );
+        return Err(CommandError::InvalidInput);
+    }
+    Ok(())
+}
+
+/// Guards against importing garbage, like a malformed file that turns into thousands of
+/// entries and makes the password file unusable.
+fn check_import_size(
+    count: usize,
+    soft_limit: usize,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    if count > soft_limit
+        && !confirm(
+            format!(
+                "This file has {} entries, which is a lot. Import them anyway? [y/N]",
+                count
+            ),
+            io,
+        )
+    {
+        io.info(
+            format!(
+                "Nothing was imported. To skip this question, raise the limit with the {} \
+                 environment variable.",
+                SOFT_LIMIT_ENV_VAR
+            ),
+            OutputType::Error,
+        );
+        return Err(CommandError::UserAbort);
+    }
+
+    Ok(())
+}
+
+/// What to do with imported apps that are already in the password file.
+#[derive(Clone, Copy, PartialEq)]
+pub enum OnConflict {
+    Skip,
+    Update,
+    Ask,
+}
+
+/// Asks a yes/no question. Anything but "y" is a no.
+pub fn confirm(question: String, io: &mut impl CliInputOutput) -> bool {
+    io.info(question, OutputType::Error);
+    match io.read_line() {
+        Ok(line) => line.trim().to_lowercase().starts_with('y'),
+        Err(_) => false,
+    }
 }
 
 fn import_passwords(
     valid: Vec<Password>,
     invalid: Vec<Password>,
+    on_conflict: OnConflict,
     store: &mut PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
     let mut errors = 0;
     let mut warnings = 0;
     let mut successes = 0;
+    let mut updates = 0;
+    let mut skipped = 0;
+    let mut with_notes = 0;
     for password in invalid {
         io.error(
             format!("{}, invalid format, skipping", password.name),
             OutputType::Error,
         );
         errors += 1;
+        skipped += 1;
     }
     for password in valid {
-        if let Some(_) = store.get_password(&password.name) {
+        if let Some(existing) = store.get_password(&password.name) {
+            let overwrite = match on_conflict {
+                OnConflict::Skip => false,
+                OnConflict::Update => true,
+                OnConflict::Ask => confirm(
+                    format!(
+                        "{} is already in the password store. Overwrite it? [y/N]",
+                        existing.name
+                    ),
+                    io,
+                ),
+            };
+            if overwrite {
+                let change_result = store.change_password(&existing.name, &|old_password| {
+                    // Keep the existing note unless the import brings its own.
+                    let notes = if password.notes.is_empty() {
+                        old_password.notes.clone()
+                    } else {
+                        password.notes.clone()
+                    };
+                    Password {
+                        username: password.username.clone(),
+                        password: password.password.clone(),
+                        notes,
+                        updated_at: ffi::time(),
+                        ..old_password
+                    }
+                });
+                if let Err(err) = change_result {
+                    io.error(
+                        format!("{}, error ({:?})", password.name, err),
+                        OutputType::Error,
+                    );
+                    errors += 1;
+                    continue;
+                }
+
+                updates += 1;
+                if !password.notes.is_empty() {
+                    with_notes += 1;
+                }
+                continue;
+            }
+
             io.warning(
                 format!("{}, already in password store, skipping", password.name),
                 OutputType::Error,
             );
             warnings += 1;
+            skipped += 1;
             continue;
         }
 
@@ -68,9 +317,19 @@ fn import_passwords(
         }
 
         successes += 1;
+        if !password.notes.is_empty() {
+            with_notes += 1;
+        }
     }
 
-    io.success(format!("Imported: {}", successes), OutputType::Standard);
+    io.success(format!("Imported: {}", successes), OutputType::Error);
+    if on_conflict != OnConflict::Skip {
+        io.success(format!("Updated: {}", updates), OutputType::Error);
+    }
+    if with_notes > 0 {
+        io.info(format!("With notes: {}", with_notes), OutputType::Error);
+    }
+    io.info(format!("Skipped: {}", skipped), OutputType::Error);
     io.warning(format!("Warnings: {}", warnings), OutputType::Error);
     io.error(format!("Errors: {}", errors), OutputType::Error);
 
@@ -78,93 +337,245 @@ fn import_passwords(
 }
 
 fn create_imported_passwords_from_csv(
-    matches: &clap::ArgMatches,
+    path_str: &str,
+    hard_limit: usize,
     io: &mut impl CliInputOutput,
-) -> Result<(Vec<Password>, Vec<Password>), i32> {
-    let path_str = matches.get_one::<String>("path").unwrap();
+) -> Result<(Vec<Password>, Vec<Password>), CommandError> {
     let mut reader = csv::ReaderBuilder::new()
         .has_headers(false)
+        .flexible(true)
         .from_path(path_str)
         .map_err(|err| {
             io.error(
                 format!("Uh oh, could not open or read the file (reason: {})", err),
                 OutputType::Error,
             );
-            1
+            CommandError::Io
         })?;
     let mut valid = vec![];
-    for record_result in reader.records() {
+    let mut invalid = vec![];
+    for (i, record_result) in reader.records().enumerate() {
+        check_hard_limit(i + 1, hard_limit, io)?;
         if let Ok(record) = record_result {
-            valid.push(Password {
-                name: record[0].into(),
-                username: record[1].into(),
-                password: record[2].into(),
-                created_at: ffi::time(),
-                updated_at: ffi::time(),
-            });
+            // Rows may have any number of fields, since the reader is flexible.
+            let mut password = match (record.get(0), record.get(1), record.get(2)) {
+                (Some(name), Some(username), Some(password)) => {
+                    Password::new(name, username, password)
+                }
+                _ => {
+                    invalid.push(Password::new(record.get(0).unwrap_or(""), "", ""));
+                    continue;
+                }
+            };
+            // An optional 4th column holds notes, as added by other tools or by hand.
+            if let Some(notes) = record.get(3) {
+                password.notes = SafeString::from_string(notes.to_owned());
+            }
+            valid.push(password);
         } else {
-            return Err(1);
+            return Err(CommandError::InvalidInput);
         }
     }
-    return Ok((valid, vec![]));
+    return Ok((valid, invalid));
 }
 
 fn create_imported_passwords_from_1password(
-    matches: &clap::ArgMatches,
+    path_str: &str,
+    hard_limit: usize,
     io: &mut impl CliInputOutput,
-) -> Result<(Vec<Password>, Vec<Password>), i32> {
-    let path_str = matches.get_one::<String>("path").unwrap();
+) -> Result<(Vec<Password>, Vec<Password>), CommandError> {
     let mut reader = csv::ReaderBuilder::new()
         .has_headers(false)
+        .flexible(true)
         .from_path(path_str)
         .map_err(|err| {
             io.error(
                 format!("Uh oh, could not open or read the file (reason: {})", err),
                 OutputType::Error,
             );
-            1
+            CommandError::Io
         })?;
     let mut valid = vec![];
     let mut invalid = vec![];
-    for record_result in reader.records() {
+    for (i, record_result) in reader.records().enumerate() {
+        check_hard_limit(i + 1, hard_limit, io)?;
         if let Ok(record) = record_result {
-            if &record[3] != "Login" {
-                invalid.push(Password {
-                    name: record[2].into(),
-                    username: record[5].into(),
-                    password: record[1].into(),
-                    created_at: ffi::time(),
-                    updated_at: ffi::time(),
-                });
-                continue;
-            }
-
             // Fields are, in order: 0/Notes, 1/Password, 2/Title, 3/Type (we can only import "Login"), 4/URL, 5/Username
-            valid.push(Password {
-                name: record[2].into(),
-                username: record[5].into(),
-                password: record[1].into(),
-                created_at: ffi::time(),
-                updated_at: ffi::time(),
-            });
+            let (notes, password, name, username) = match (
+                record.get(0),
+                record.get(1),
+                record.get(2),
+                record.get(3),
+                record.get(5),
+            ) {
+                (Some(notes), Some(password), Some(name), Some("Login"), Some(username)) => {
+                    (notes, password, name, username)
+                }
+                _ => {
+                    invalid.push(Password::new(record.get(2).unwrap_or(""), "", ""));
+                    continue;
+                }
+            };
+
+            let mut password = Password::new(name, username, password);
+            password.notes = SafeString::from_string(notes.to_owned());
+            valid.push(password);
         } else {
-            return Err(1);
+            return Err(CommandError::InvalidInput);
         }
     }
     return Ok((valid, invalid));
 }
 
+/// Imports a KeePassXC CSV export. Columns are found by their header, so both the classic
+/// `Group,Title,Username,Password,URL,Notes` layout and newer exports with more columns work.
+fn create_imported_passwords_from_keepass(
+    path_str: &str,
+    hard_limit: usize,
+    io: &mut impl CliInputOutput,
+) -> Result<(Vec<Password>, Vec<Password>), CommandError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .from_path(path_str)
+        .map_err(|err| {
+            io.error(
+                format!("Uh oh, could not open or read the file (reason: {})", err),
+                OutputType::Error,
+            );
+            CommandError::Io
+        })?;
+
+    let headers = reader.headers().map_err(|err| {
+        io.error(
+            format!("Uh oh, could not read the CSV header (reason: {})", err),
+            OutputType::Error,
+        );
+        CommandError::InvalidInput
+    })?;
+    let column = |name: &str| headers.iter().position(|header| header.trim() == name);
+    let (title, username, password, notes) = match (
+        column("Title"),
+        column("Username"),
+        column("Password"),
+        column("Notes"),
+    ) {
+        (Some(title), Some(username), Some(password), notes) => (title, username, password, notes),
+        _ => {
+            io.error(
+                "Woops, this does not look like a KeePass CSV export. I need at least the \
+                 Title, Username and Password columns.",
+                OutputType::Error,
+            );
+            return Err(CommandError::InvalidInput);
+        }
+    };
+
+    let mut valid = vec![];
+    let mut invalid = vec![];
+    for (i, record_result) in reader.records().enumerate() {
+        check_hard_limit(i + 1, hard_limit, io)?;
+        let record = record_result.map_err(|err| {
+            io.error(
+                format!("Uh oh, could not read the file (reason: {})", err),
+                OutputType::Error,
+            );
+            CommandError::InvalidInput
+        })?;
+        let field = |i: usize| record.get(i).unwrap_or("");
+
+        let mut imported = Password::new(field(title), field(username), field(password));
+        if let Some(notes) = notes {
+            imported.notes = SafeString::from_string(field(notes).to_owned());
+        }
+        if imported.name.is_empty() || imported.password.is_empty() {
+            invalid.push(imported);
+        } else {
+            valid.push(imported);
+        }
+    }
+    Ok((valid, invalid))
+}
+
+/// Reads a file written by `rooster export encrypted`, which may have been exported with
+/// another master password than the current one.
+fn create_imported_passwords_from_encrypted(
+    path_str: &str,
+    hard_limit: usize,
+    io: &mut impl CliInputOutput,
+) -> Result<(Vec<Password>, Vec<Password>), CommandError> {
+    let mut input = SafeVec::new(Vec::new());
+    File::open(path_str)
+        .and_then(|mut file| file.read_to_end(input.inner_mut()))
+        .map_err(|err| {
+            io.error(
+                format!("Uh oh, could not open the file (reason: {})", err),
+                OutputType::Error,
+            );
+            CommandError::Io
+        })?;
+
+    // Exports without --plaintext are armored, so that they can go through stdout.
+    if armor::is_armored(input.deref()) {
+        let bytes = armor::dearmor(input.deref()).ok_or_else(|| {
+            io.error(
+                "Woops, this export looks damaged, I could not decode it.",
+                OutputType::Error,
+            );
+            CommandError::InvalidInput
+        })?;
+        input = SafeVec::new(bytes);
+    }
+
+    let master_password = io
+        .prompt_password("Type the master password this export was made with: ")
+        .map_err(|err| {
+            io.error(
+                format!(
+                    "Woops, I could not read the master password (reason: {}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            CommandError::Io
+        })?;
+
+    let export = PasswordStore::from_input(master_password, input).map_err(|err| match err {
+        PasswordError::DecryptionError => {
+            io.error(
+                "Woops, that's not the right master password for this export.",
+                OutputType::Error,
+            );
+            CommandError::Decryption
+        }
+        err => {
+            io.error(
+                format!("Woops, I could not read this export (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            CommandError::Store
+        }
+    })?;
+    check_hard_limit(export.get_all_passwords().len(), hard_limit, io)?;
+
+    let passwords = export
+        .get_all_passwords()
+        .into_iter()
+        .map(|password| password.clone())
+        .collect();
+    Ok((passwords, vec![]))
+}
+
 fn create_imported_passwords_from_json(
-    matches: &clap::ArgMatches,
+    path_str: &str,
+    hard_limit: usize,
     io: &mut impl CliInputOutput,
-) -> Result<(Vec<Password>, Vec<Password>), i32> {
-    let path_str = matches.get_one::<String>("path").unwrap();
+) -> Result<(Vec<Password>, Vec<Password>), CommandError> {
     let dump_file = File::open(path_str).map_err(|err| {
         io.error(
             format!("Uh oh, could not open the file (reason: {})", err),
             OutputType::Error,
         );
-        1
+        CommandError::Io
     })?;
     let export: JsonExport = serde_json::from_reader(dump_file).map_err(|json_err| {
         io.error(
@@ -174,7 +585,314 @@ fn create_imported_passwords_from_json(
             ),
             OutputType::Error,
         );
-        1
+        CommandError::InvalidInput
     })?;
+    check_hard_limit(export.passwords.len(), hard_limit, io)?;
     Ok((export.passwords, vec![]))
 }
+
+fn create_imported_passwords_from_bitwarden(
+    path_str: &str,
+    hard_limit: usize,
+    io: &mut impl CliInputOutput,
+) -> Result<(Vec<Password>, Vec<Password>), CommandError> {
+    let dump_file = File::open(path_str).map_err(|err| {
+        io.error(
+            format!("Uh oh, could not open the file (reason: {})", err),
+            OutputType::Error,
+        );
+        CommandError::Io
+    })?;
+    let export: BitwardenExport = serde_json::from_reader(dump_file).map_err(|json_err| {
+        io.error(
+            format!(
+                "Woops, I could not import the passwords from Bitwarden (reason: {}).",
+                json_err
+            ),
+            OutputType::Error,
+        );
+        CommandError::InvalidInput
+    })?;
+
+    if export.encrypted {
+        io.error(
+            "Woops, this Bitwarden export is encrypted. Export your vault as unencrypted JSON \
+             and try again.",
+            OutputType::Error,
+        );
+        return Err(CommandError::InvalidInput);
+    }
+    check_hard_limit(export.items.len(), hard_limit, io)?;
+
+    let mut valid = vec![];
+    let mut invalid = vec![];
+    let mut skipped = 0;
+    for item in export.items {
+        if item.item_type != BITWARDEN_LOGIN_TYPE {
+            skipped += 1;
+            continue;
+        }
+
+        let (password, warning) = bitwarden_item_to_password(item);
+        if let Some(warning) = warning {
+            io.warning(format!("{}, {}", password.name, warning), OutputType::Error);
+        }
+        if password.name.is_empty() || password.password.is_empty() {
+            invalid.push(password);
+        } else {
+            valid.push(password);
+        }
+    }
+
+    if skipped > 0 {
+        io.info(
+            format!(
+                "Skipped {} item(s) that are not logins (secure notes, cards or identities).",
+                skipped
+            ),
+            OutputType::Error,
+        );
+    }
+    Ok((valid, invalid))
+}
+
+/// Converts a Bitwarden login. When the item has no name, its first URI is used instead.
+fn bitwarden_item_to_password(item: BitwardenItem) -> (Password, Option<&'static str>) {
+    let login = item.login.unwrap_or(BitwardenLogin {
+        username: None,
+        password: None,
+        totp: None,
+        uris: None,
+    });
+
+    let name = item
+        .name
+        .filter(|name| !name.trim().is_empty())
+        .or_else(|| {
+            login
+                .uris
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|uri| uri.uri)
+                .find(|uri| !uri.trim().is_empty())
+        })
+        .unwrap_or_default();
+
+    let mut password = Password::new(
+        name,
+        login.username.unwrap_or_default(),
+        login.password.unwrap_or_default(),
+    );
+    if let Some(notes) = item.notes {
+        password.notes = SafeString::from_string(notes);
+    }
+
+    let mut warning = None;
+    if let Some(totp) = login.totp.filter(|totp| !totp.is_empty()) {
+        let secret = SafeString::from_string(totp);
+        if otp::is_valid_secret(&secret) {
+            password.otp_secret = Some(secret);
+        } else {
+            warning = Some("TOTP seed is not a base32 secret, not imported");
+        }
+    }
+    (password, warning)
+}
+
+/// Finds the `.gpg` files of a `pass` store, skipping hidden files like `.git` and `.gpg-id`.
+fn find_pass_entries(dir: &Path, entries: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .map(|name| name.to_string_lossy().starts_with('.'))
+            .unwrap_or(true);
+        if hidden {
+            continue;
+        }
+
+        if path.is_dir() {
+            find_pass_entries(&path, entries)?;
+        } else if path.extension().map(|ext| ext == "gpg").unwrap_or(false) {
+            entries.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The app name of a `pass` entry is its path in the store, like `email/gmail`.
+fn pass_entry_name(store_dir: &Path, entry: &Path) -> String {
+    entry
+        .strip_prefix(store_dir)
+        .unwrap_or(entry)
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// Parses a decrypted `pass` entry: the password is on the first line, and the username on a
+/// `login:`, `username:` or `user:` line. Everything else is kept as a note.
+fn parse_pass_entry(name: &str, content: &SafeString) -> Password {
+    let mut lines = content.lines();
+    let password = lines.next().unwrap_or("");
+
+    let mut username = String::new();
+    let mut notes = Vec::new();
+    for line in lines {
+        let is_username = match line.find(':') {
+            Some(i) if username.is_empty() => {
+                let key = line[..i].trim().to_lowercase();
+                key == "login" || key == "username" || key == "user"
+            }
+            _ => false,
+        };
+        if is_username {
+            username = line[line.find(':').unwrap() + 1..].trim().to_owned();
+        } else {
+            notes.push(line);
+        }
+    }
+
+    let mut imported = Password::new(name, username, password);
+    imported.notes = SafeString::from_string(notes.join("\n").trim().to_owned());
+    imported
+}
+
+fn decrypt_pass_entry(entry: &Path) -> Result<SafeString, String> {
+    let output = Command::new("gpg")
+        .args(&["--quiet", "--decrypt"])
+        .arg(entry)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|err| err.to_string())?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+
+    String::from_utf8(output.stdout)
+        .map(SafeString::from_string)
+        .map_err(|_| "not valid UTF-8".to_owned())
+}
+
+fn create_imported_passwords_from_pass(
+    path_str: &str,
+    hard_limit: usize,
+    io: &mut impl CliInputOutput,
+) -> Result<(Vec<Password>, Vec<Password>), CommandError> {
+    let store_dir = Path::new(path_str);
+
+    // We can't decrypt GPG files ourselves, so we need the gpg binary.
+    if Command::new("gpg")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .status()
+        .is_err()
+    {
+        io.error(
+            "Woops, I need the `gpg` program to import from pass, but I can't find it.",
+            OutputType::Error,
+        );
+        return Err(CommandError::Unavailable);
+    }
+
+    let mut entries = Vec::new();
+    find_pass_entries(store_dir, &mut entries).map_err(|err| {
+        io.error(
+            format!("Uh oh, could not read the directory (reason: {})", err),
+            OutputType::Error,
+        );
+        CommandError::Io
+    })?;
+    entries.sort();
+    // Before running gpg once per entry.
+    check_hard_limit(entries.len(), hard_limit, io)?;
+
+    let mut valid = vec![];
+    let mut invalid = vec![];
+    for entry in entries {
+        let name = pass_entry_name(store_dir, &entry);
+        match decrypt_pass_entry(&entry) {
+            Ok(content) => {
+                let password = parse_pass_entry(&name, &content);
+                if password.password.is_empty() {
+                    invalid.push(password);
+                } else {
+                    valid.push(password);
+                }
+            }
+            Err(reason) => {
+                io.error(
+                    format!("{}, could not decrypt ({}), skipping", name, reason),
+                    OutputType::Error,
+                );
+            }
+        }
+    }
+    Ok((valid, invalid))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bitwarden_item_to_password, parse_pass_entry, pass_entry_name, BitwardenExport};
+    use rtoolbox::safe_string::SafeString;
+    use std::path::Path;
+
+    #[test]
+    fn test_pass_entry_name() {
+        assert_eq!(
+            pass_entry_name(
+                Path::new("/home/me/.password-store"),
+                Path::new("/home/me/.password-store/email/gmail.gpg")
+            ),
+            "email/gmail"
+        );
+    }
+
+    #[test]
+    fn test_parse_pass_entry() {
+        let content = SafeString::from_string(
+            "hunter2\nLogin: me@example.com\nurl: https://example.com\n".to_owned(),
+        );
+        let password = parse_pass_entry("email/gmail", &content);
+        assert_eq!(password.name, "email/gmail");
+        assert_eq!(password.username, "me@example.com");
+        assert_eq!(password.password, "hunter2".into());
+        assert_eq!(password.notes, "url: https://example.com".into());
+
+        let password = parse_pass_entry("pin", &SafeString::from_string("1234".to_owned()));
+        assert_eq!(password.username, "");
+        assert_eq!(password.password, "1234".into());
+        assert!(password.notes.is_empty());
+    }
+
+    #[test]
+    fn test_bitwarden_item_to_password() {
+        let export: BitwardenExport = serde_json::from_str(
+            r#"{"encrypted":false,"items":[
+                {"type":1,"name":"GitHub","notes":"work account","login":{
+                    "username":"me@example.com","password":"hunter2","totp":"JBSWY3DPEHPK3PXP",
+                    "uris":[{"match":null,"uri":"https://github.com"}]}},
+                {"type":1,"name":"","login":{"username":null,"password":"abcd",
+                    "uris":[{"uri":"https://example.com"}]}}
+            ]}"#,
+        )
+        .unwrap();
+        let mut items = export.items.into_iter();
+
+        let (password, warning) = bitwarden_item_to_password(items.next().unwrap());
+        assert_eq!(password.name, "GitHub");
+        assert_eq!(password.username, "me@example.com");
+        assert_eq!(password.password, "hunter2".into());
+        assert_eq!(password.notes, "work account".into());
+        assert_eq!(password.otp_secret, Some("JBSWY3DPEHPK3PXP".into()));
+        assert!(warning.is_none());
+
+        let (password, _) = bitwarden_item_to_password(items.next().unwrap());
+        assert_eq!(password.name, "https://example.com");
+        assert_eq!(password.username, "");
+    }
+}
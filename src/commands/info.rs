@@ -0,0 +1,84 @@
+use crate::commands::CommandError;
+use crate::password;
+use crate::password::v2::FileFormat;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use std::path::PathBuf;
+
+/// Prints the format of the password file from its header, which is not encrypted, so this
+/// needs no master password.
+pub fn print_file_format(
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &PathBuf,
+) -> Result<(), CommandError> {
+    let input = match std::fs::read(rooster_file_path) {
+        Ok(input) => input,
+        Err(err) => {
+            io.error(
+                format!(
+                    "Woops, I couldn't read your password file ({} for \"{}\").",
+                    err,
+                    rooster_file_path.to_string_lossy()
+                ),
+                OutputType::Error,
+            );
+            return Err(CommandError::Io);
+        }
+    };
+
+    match password::v2::read_file_format(&input) {
+        Ok(FileFormat::V1) => {
+            io.write("version: 1\n", OutputType::Standard);
+            io.info(
+                "Rooster offers to upgrade it to version 2 the next time you unlock it.",
+                OutputType::Error,
+            );
+        }
+        Ok(FileFormat::V2 {
+            scrypt_log2_n,
+            scrypt_r,
+            scrypt_p,
+        }) => {
+            io.write(
+                format!(
+                    "version: 2\nlog2n: {}\nr: {}\np: {}\n",
+                    scrypt_log2_n, scrypt_r, scrypt_p
+                ),
+                OutputType::Standard,
+            );
+        }
+        Err(password::PasswordError::OutdatedRoosterBinaryError) => {
+            io.error(
+                "Woops, your password file was made by a newer version of Rooster. Try \
+                 upgrading Rooster to the latest version.",
+                OutputType::Error,
+            );
+            return Err(CommandError::Unavailable);
+        }
+        Err(_) => {
+            io.error(
+                "Woops, your password file is too short to be a Rooster file.",
+                OutputType::Error,
+            );
+            return Err(CommandError::InvalidInput);
+        }
+    }
+    Ok(())
+}
+
+/// With `--unlock`, prints what only the decrypted store tells, after `print_file_format`.
+pub fn callback_exec(
+    _matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    io.write(
+        format!(
+            "passwords: {}\ntrash: {}\n",
+            store.get_all_passwords().len(),
+            store.get_trashed_passwords().len()
+        ),
+        OutputType::Standard,
+    );
+    Ok(())
+}
@@ -1,13 +1,239 @@
+use crate::commands::import;
+use crate::commands::CommandError;
+use crate::example_environment_variable_configuration;
+use crate::password;
+use crate::password::v2::{
+    benchmark_scrypt_params, PasswordStore, SCRYPT_PARAM_LOG2_N, SCRYPT_PARAM_P, SCRYPT_PARAM_R,
+};
+use crate::read_master_password_non_interactive;
 use rclio::CliInputOutput;
 use rclio::OutputType;
+use rtoolbox::safe_string::SafeString;
 use std::path::PathBuf;
-use crate::example_environment_variable_configuration;
+use std::time::Duration;
+
+/// The scrypt settings offered by the wizard, from the fastest to unlock to the hardest to crack.
+const SCRYPT_PRESETS: [(&'static str, u8); 3] = [
+    ("Default", SCRYPT_PARAM_LOG2_N),
+    ("Strong", 15),
+    ("Very strong", 17),
+];
+
+pub fn format_duration(duration: Duration) -> String {
+    if duration.as_millis() < 1000 {
+        format!("{} ms", duration.as_millis())
+    } else {
+        format!("{:.1} s", duration.as_secs_f64())
+    }
+}
+
+/// Lets the user pick how expensive the key derivation is. Each step of log2n doubles the
+/// time, so we only benchmark the default and extrapolate from there.
+fn choose_scrypt_log2_n(io: &mut impl CliInputOutput) -> Result<u8, CommandError> {
+    io.title("Protecting your password file", OutputType::Error);
+    io.nl(OutputType::Error);
+    io.info(
+        "Your master password is stretched with scrypt, which makes it slow to guess. The \
+         stronger the setting, the longer Rooster takes to open your password file:",
+        OutputType::Error,
+    );
+
+    let benchmark = benchmark_scrypt_params(SCRYPT_PARAM_LOG2_N, SCRYPT_PARAM_R, SCRYPT_PARAM_P)
+        .expect("the default scrypt parameters are valid");
+    for (i, (name, log2_n)) in SCRYPT_PRESETS.iter().enumerate() {
+        let estimate = benchmark * 2u32.pow((log2_n - SCRYPT_PARAM_LOG2_N) as u32);
+        io.info(
+            format!(
+                "  {}) {} (log2n {}), about {} to unlock",
+                i + 1,
+                name,
+                log2_n,
+                format_duration(estimate)
+            ),
+            OutputType::Error,
+        );
+    }
+
+    loop {
+        io.write(
+            format!(
+                "Type a number from 1 to {}, or ENTER for the default: ",
+                SCRYPT_PRESETS.len()
+            ),
+            OutputType::Error,
+        );
+        let line = io.read_line().map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't read your choice (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            CommandError::Io
+        })?;
+
+        let choice = line.trim();
+        if choice.is_empty() {
+            return Ok(SCRYPT_PARAM_LOG2_N);
+        }
+        match choice.parse::<usize>() {
+            Ok(i) if i >= 1 && i <= SCRYPT_PRESETS.len() => return Ok(SCRYPT_PRESETS[i - 1].1),
+            _ => io.error("I did not get that.", OutputType::Error),
+        }
+    }
+}
+
+/// Below this many characters, a new master password is accepted with a warning.
+const MIN_MASTER_PASSWORD_LEN: usize = 8;
+
+/// How many times to ask for a new master password when it is refused.
+const MASTER_PASSWORD_ATTEMPTS: usize = 3;
+
+/// Refuses a blank master password, unless `allow_empty`, and warns about a short one. This
+/// looks at the `SafeString` in place, so the master password is never copied.
+pub fn check_new_master_password(
+    master_password: &SafeString,
+    allow_empty: bool,
+    io: &mut impl CliInputOutput,
+) -> bool {
+    if master_password.chars().all(char::is_whitespace) {
+        if !allow_empty {
+            io.error(
+                "Woops, your master password cannot be empty or only made of spaces.",
+                OutputType::Error,
+            );
+        }
+        return allow_empty;
+    }
+
+    let len = master_password.chars().count();
+    if len < MIN_MASTER_PASSWORD_LEN {
+        io.warning(
+            format!(
+                "Heads up, your master password is only {} characters long. Anyone who gets \
+                 your password file can try short passwords much faster.",
+                len
+            ),
+            OutputType::Error,
+        );
+    }
+    true
+}
+
+/// Asks for a new master password until it is accepted and, with a `confirmation_prompt`,
+/// typed the same twice. Scripts don't get to try again, as the next lines of stdin are not
+/// for us, and people get `MASTER_PASSWORD_ATTEMPTS` tries.
+pub fn ask_new_master_password(
+    prompt: &str,
+    confirmation_prompt: Option<&str>,
+    allow_empty: bool,
+    io: &mut impl CliInputOutput,
+) -> Result<SafeString, CommandError> {
+    for attempt in 1..=MASTER_PASSWORD_ATTEMPTS {
+        let master_password = io.prompt_password(prompt).map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't read the master passwords ({:?}).", err),
+                OutputType::Error,
+            );
+            CommandError::Io
+        })?;
+        if !check_new_master_password(&master_password, allow_empty, io) {
+            if confirmation_prompt.is_none() {
+                return Err(CommandError::InvalidInput);
+            }
+            continue;
+        }
+
+        let confirmation_prompt = match confirmation_prompt {
+            Some(confirmation_prompt) => confirmation_prompt,
+            None => return Ok(master_password),
+        };
+        // Both are SafeStrings, so the one we don't keep is scrubbed when dropped.
+        let confirmation = io.prompt_password(confirmation_prompt).map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't read the master passwords ({:?}).", err),
+                OutputType::Error,
+            );
+            CommandError::Io
+        })?;
+        if password::constant_time_eq(&master_password, &confirmation) {
+            return Ok(master_password);
+        }
+        if attempt < MASTER_PASSWORD_ATTEMPTS {
+            io.error(
+                "The master passwords did not match. Let's try again.",
+                OutputType::Error,
+            );
+        } else {
+            io.error(
+                "The master passwords did not match. Aborting.",
+                OutputType::Error,
+            );
+        }
+    }
+    Err(CommandError::InvalidInput)
+}
+
+/// Imports passwords from another manager right away, if the user wants to. Failing to import
+/// does not stop the setup, since it can be done later with `rooster import`.
+fn offer_import(
+    store: &mut PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    io.nl(OutputType::Error);
+    io.title("Importing your passwords", OutputType::Error);
+    io.nl(OutputType::Error);
+    io.info(
+        format!(
+            "Do you want to import passwords from another password manager? Type one of {}, \
+             or ENTER to skip.",
+            import::SOURCES.join(", ")
+        ),
+        OutputType::Error,
+    );
+    let source = io.read_line().map_err(|err| {
+        io.error(
+            format!("Woops, I couldn't read your choice (reason: {:?}).", err),
+            OutputType::Error,
+        );
+        CommandError::Io
+    })?;
+    let source = source.trim().to_lowercase();
+    if source.is_empty() {
+        return Ok(());
+    }
+    if !import::SOURCES.contains(&source.as_str()) {
+        io.warning(
+            format!(
+                "I can't import from \"{}\". You can still import later with `rooster import`.",
+                source
+            ),
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    io.write("Where is the file to import? ", OutputType::Error);
+    let path = io.read_line().map_err(|err| {
+        io.error(
+            format!("Woops, I couldn't read the path (reason: {:?}).", err),
+            OutputType::Error,
+        );
+        CommandError::Io
+    })?;
+
+    if import::import_from(&source, path.trim(), import::OnConflict::Skip, store, io).is_err() {
+        io.warning(
+            "I couldn't import your passwords. You can try again later with `rooster import`.",
+            OutputType::Error,
+        );
+    }
+    Ok(())
+}
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
     io: &mut impl CliInputOutput,
     rooster_file_path: &PathBuf,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
     let filename_as_string = rooster_file_path.to_string_lossy().into_owned();
     if rooster_file_path.exists() && !matches.get_flag("force-for-tests") {
         io.error(
@@ -20,87 +246,126 @@ pub fn callback_exec(
             "Type `rooster --help` to see what Rooster can do for you.",
             OutputType::Error,
         );
-        return Err(1);
+        return Err(CommandError::Duplicate);
     }
 
-    io.title("Welcome to Rooster", OutputType::Standard);
-    io.nl(OutputType::Standard);
-    io.info("Rooster is a simple password manager. Let's get started! Type ENTER to continue.", OutputType::Standard);
+    // Provisioning scripts can pass the master password like for any other command.
+    let provided_master_password =
+        read_master_password_non_interactive(matches.get_flag("password-stdin"), io);
 
-    if let Err(err) = io.read_line() {
-        io.error(
-            format!("Woops, I didn't see the ENTER key (reason: {:?}).", err),
+    // Scripts get the defaults, and a single password prompt unless they passed it already.
+    let interactive = !matches.get_flag("non-interactive") && provided_master_password.is_none();
+    // Tests get the settings of scripts too, after the ENTER key of the welcome screen.
+    let wizard = interactive && !matches.get_flag("force-for-tests");
+    let allow_empty = matches.get_flag("allow-empty");
+
+    if interactive {
+        io.title("Welcome to Rooster", OutputType::Error);
+        io.nl(OutputType::Error);
+        io.info(
+            "Rooster is a simple password manager. Your passwords will be saved in:",
+            OutputType::Error,
+        );
+        io.info(format!("    {}", filename_as_string), OutputType::Error);
+        io.nl(OutputType::Error);
+        io.info(
+            "To use another location, run `rooster init --file <path>`, or set the ROOSTER_FILE \
+             environment variable and run `rooster init` again. Otherwise, type ENTER to continue.",
             OutputType::Error,
         );
-        return Err(1);
-    }
 
-    io.title("The master password", OutputType::Standard);
-    io.nl(OutputType::Standard);
-    io.info(
-        "With Rooster, you only need to remember one password: \
-    the master password. It keeps all of you other passwords safe. The stronger it is, the better your passwords are \
-                      protected."
-    , OutputType::Standard);
-    io.nl(OutputType::Standard);
-
-    let master_password = io
-        .prompt_password("Choose your master password: ")
-        .map_err(|err| {
+        if let Err(err) = io.read_line() {
             io.error(
-                format!("Woops, I couldn't read the master passwords ({:?}).", err),
+                format!("Woops, I didn't see the ENTER key (reason: {:?}).", err),
                 OutputType::Error,
             );
-            1
-        })?;
+            return Err(CommandError::Io);
+        }
+        io.nl(OutputType::Error);
+    }
+
+    let scrypt_log2_n = if wizard {
+        choose_scrypt_log2_n(io)?
+    } else {
+        SCRYPT_PARAM_LOG2_N
+    };
 
-    if master_password.len() == 0 {
-        io.error("Your master password cannot be empty.", OutputType::Error);
-        return Err(1);
+    if wizard {
+        io.nl(OutputType::Error);
+        io.title("The master password", OutputType::Error);
+        io.nl(OutputType::Error);
+        io.info(
+            "With Rooster, you only need to remember one password: the master password. It \
+             keeps all of you other passwords safe. The stronger it is, the better your \
+             passwords are protected.",
+            OutputType::Error,
+        );
+        io.nl(OutputType::Error);
     }
 
-    let store = match crate::password::v2::PasswordStore::new(master_password) {
-        Ok(store) => store,
-        Err(err) => {
+    let master_password = match provided_master_password {
+        Some(Ok(master_password)) => {
+            if !check_new_master_password(&master_password, allow_empty, io) {
+                return Err(CommandError::InvalidInput);
+            }
+            master_password
+        }
+        Some(Err(err)) => {
             io.error(
                 format!(
-                    "Woops, I couldn't use the random number generator on your machine \
-                     (reason: {:?}). Without it, I can't create a secure password file.",
+                    "Woops, I could not read your master password (reason: {}).",
                     err
                 ),
                 OutputType::Error,
             );
-            return Err(1);
+            return Err(CommandError::Io);
         }
+        None => ask_new_master_password(
+            "Choose your master password: ",
+            if wizard {
+                Some("Type it again to make sure there is no typo: ")
+            } else {
+                None
+            },
+            allow_empty,
+            io,
+        )?,
     };
 
-    let mut file = match crate::create_password_file(filename_as_string.as_str()).map_err(|_| 1) {
-        Ok(file) => file,
+    let mut store = match PasswordStore::new(master_password) {
+        Ok(store) => store,
         Err(err) => {
             io.error(
                 format!(
-                    "Woops, I couldn't create a new password file (reason: {:?})",
+                    "Woops, I couldn't use the random number generator on your machine \
+                     (reason: {:?}). Without it, I can't create a secure password file.",
                     err
                 ),
                 OutputType::Error,
             );
-            return Err(1);
+            return Err(CommandError::Store);
         }
     };
-
-    if let Err(err) = store.sync(&mut file) {
-        if let Err(err) = ::std::fs::remove_file(rooster_file_path) {
+    if scrypt_log2_n != SCRYPT_PARAM_LOG2_N {
+        if let Err(err) = store.change_scrypt_params(scrypt_log2_n, SCRYPT_PARAM_R, SCRYPT_PARAM_P)
+        {
             io.error(
                 format!(
-                    "Woops, I was able to create a new password file but couldn't save \
-                     it (reason: {:?}). You may want to remove this dangling file:",
+                    "Woops, I couldn't use these scrypt parameters (reason: {:?}).",
                     err
                 ),
                 OutputType::Error,
             );
-            io.error(format!("    {}", filename_as_string), OutputType::Error);
-            return Err(1);
+            return Err(CommandError::Store);
         }
+    }
+
+    if wizard {
+        offer_import(&mut store, io)?;
+    }
+
+    // Nothing is left behind if this fails, see `PasswordStore::sync`.
+    if let Err(err) = store.sync(rooster_file_path) {
         io.error(
             format!(
                 "Woops, I couldn't create a new password file (reason: {:?}).",
@@ -108,29 +373,29 @@ pub fn callback_exec(
             ),
             OutputType::Error,
         );
-        return Err(1);
+        return Err(CommandError::Io);
     }
 
-    io.nl(OutputType::Standard);
-    io.title("All done and ready to rock", OutputType::Standard);
-    io.nl(OutputType::Standard);
-    io.success("You passwords will be saved in:", OutputType::Standard);
-    io.success(format!("    {}", filename_as_string), OutputType::Standard);
-    io.nl(OutputType::Standard);
+    io.nl(OutputType::Error);
+    io.title("All done and ready to rock", OutputType::Error);
+    io.nl(OutputType::Error);
+    io.success("You passwords will be saved in:", OutputType::Error);
+    io.success(format!("    {}", filename_as_string), OutputType::Error);
+    io.nl(OutputType::Error);
     io.info(
         "If you wish to change the location of your password file, you can set it in the \
         ROOSTER_FILE environment variable. For instance:",
-        OutputType::Standard,
+        OutputType::Error,
     );
     io.info(
         format!("    {}", example_environment_variable_configuration()),
-        OutputType::Standard,
+        OutputType::Error,
     );
-    io.nl(OutputType::Standard);
+    io.nl(OutputType::Error);
     io.info(
         "Type `rooster --help` to see what Rooster can do for you.",
-        OutputType::Standard,
+        OutputType::Error,
     );
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,22 +1,93 @@
+use crate::commands::CommandError;
+use crate::ffi;
 use crate::list;
 use crate::password;
+use crate::select;
 use rclio::CliInputOutput;
 use rclio::OutputType;
 
 pub fn callback_exec(
-    _matches: &clap::ArgMatches,
+    matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
-    let passwords = store.get_all_passwords();
+) -> Result<(), CommandError> {
+    let now = ffi::time();
+    let show_all = matches.get_flag("show-all");
+    let json = list::wants_json(matches);
+    let with_icons = !matches.get_flag("no-icons");
+    let all_passwords = store.get_all_passwords();
 
-    if passwords.len() == 0 {
+    if all_passwords.len() == 0 {
         io.info(
             "No passwords on record yet. Add one with `rooster add <app> <username>`.",
-            OutputType::Standard,
+            OutputType::Error,
         );
+        if matches.get_flag("count") {
+            io.write("0\n", OutputType::Standard);
+        } else if json {
+            list::print_list_of_passwords_as_json(&all_passwords, io);
+        }
+        return Ok(());
+    }
+
+    // The full dump is meant for reviews, so it keeps the expired passwords.
+    let (expired, mut passwords): (Vec<_>, Vec<_>) = all_passwords
+        .into_iter()
+        .partition(|p| !show_all && p.is_expired(now));
+
+    if let Some(tag) = matches.get_one::<String>("tag") {
+        passwords = list::filter_by_tag(passwords, tag);
+        if passwords.len() == 0 {
+            io.info(
+                format!("No passwords are tagged \"{}\".", tag),
+                OutputType::Error,
+            );
+        }
+    }
+
+    if let Some(pattern) = matches.get_one::<String>("regex") {
+        let field = matches
+            .get_one::<String>("field")
+            .map_or("app", |f| f.as_str());
+        let selector = select::regex_selector(pattern, field).map_err(|message| {
+            io.error(message, OutputType::Error);
+            CommandError::InvalidInput
+        })?;
+        passwords.retain(|p| selector.matches(p));
+    }
+
+    list::apply_sort(&mut passwords, matches);
+
+    // The count is for scripts, it ignores --limit.
+    if matches.get_flag("count") {
+        io.write(format!("{}\n", passwords.len()), OutputType::Standard);
+        return Ok(());
+    }
+
+    list::apply_limit(&mut passwords, matches, io);
+
+    if json && show_all {
+        list::print_details_of_passwords_as_json(&passwords, io);
+    } else if json {
+        list::print_list_of_passwords_as_json(&passwords, io);
+    } else if matches.get_flag("plain") {
+        list::print_plain_list_of_passwords(&passwords, io);
+    } else if show_all {
+        list::print_details_of_passwords(&passwords, now, io);
+    } else if matches.get_flag("long") {
+        list::print_long_list_of_passwords(&passwords, with_icons, io);
     } else {
-        list::print_list_of_passwords(&passwords, list::WITHOUT_NUMBERS, io);
+        list::print_list_of_passwords_with_rotation(&passwords, now, with_icons, io);
+    }
+
+    if expired.len() > 0 {
+        io.info(
+            format!(
+                "{} expired password(s) hidden. Restore one with `rooster expire <app> never`.",
+                expired.len()
+            ),
+            OutputType::Error,
+        );
     }
 
     Ok(())
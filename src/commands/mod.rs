@@ -1,14 +1,73 @@
 pub mod add;
+pub mod attach;
+pub mod audit;
+pub mod backup;
 pub mod change;
+pub mod codes;
+pub mod config;
 pub mod delete;
+pub mod expire;
 pub mod export;
+pub mod fingerprint;
 pub mod generate;
 pub mod get;
+pub mod history;
+pub mod icon;
 pub mod import;
+pub mod info;
 pub mod init;
 pub mod list;
+pub mod note;
+pub mod otp;
+pub mod protect;
 pub mod regenerate;
 pub mod rename;
+pub mod rpc;
+pub mod search;
 pub mod set_master_password;
 pub mod set_scrypt_params;
+pub mod strength;
+pub mod tag;
+pub mod touch;
 pub mod transfer;
+pub mod trash;
+pub mod undo;
+pub mod verify_password;
+
+/// Why a command failed. The command already told the user, so this is for its callers:
+/// `main_with_args` turns it into the exit code with `exit_code`, and `run_with_args` returns
+/// it as is, for tests and programs that use Rooster as a library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandError {
+    /// There is no password, or no file, by that name.
+    NotFound,
+    /// There is already a password by that name.
+    Duplicate,
+    /// The arguments or what the user typed can't be used, like an invalid regular expression.
+    InvalidInput,
+    /// Reading or writing stdin, a file or the clipboard failed.
+    Io,
+    /// Something encrypted, like the password file or an export, didn't open with the password
+    /// the user gave.
+    Decryption,
+    /// The user answered no, or stopped answering.
+    UserAbort,
+    /// The password store refused the change, or couldn't be encrypted.
+    Store,
+    /// The command worked, and found problems, like reused passwords for `audit`.
+    CheckFailed,
+    /// What the command needs isn't there right now, like network access in this build, or
+    /// the password file while another Rooster instance uses it.
+    Unavailable,
+    /// Some passwords were changed and others failed, like the bad lines of `add --batch`. The
+    /// changes are saved anyway, then Rooster exits with an error.
+    Partial,
+}
+
+impl CommandError {
+    /// Every failure exits with 1, which is what scripts have relied on so far. Use
+    /// `run_with_args` to tell failures apart.
+    pub fn exit_code(&self) -> i32 {
+        1
+    }
+}
@@ -0,0 +1,75 @@
+use crate::commands::CommandError;
+use crate::ffi;
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use rtoolbox::safe_string::SafeString;
+use std::ops::Deref;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which note would you like?",
+        io,
+    )
+    .ok_or(CommandError::NotFound)?
+    .clone();
+
+    match matches.get_one::<String>("set") {
+        Some(note) => change_note(&password, SafeString::from_string(note.clone()), store, io),
+        None => {
+            if password.notes.is_empty() {
+                io.info(
+                    format!("There is no note for {}.", password.name),
+                    OutputType::Error,
+                );
+            } else {
+                io.success(format!("{}", password.notes.deref()), OutputType::Standard);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Replaces the note of a password, used by both `note --set` and `change --note`.
+pub fn change_note(
+    password: &password::v2::Password,
+    note: SafeString,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let change_result =
+        store.change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                notes: note.clone(),
+                updated_at: ffi::time(),
+                ..old_password
+            }
+        });
+
+    match change_result {
+        Ok(_) => {
+            io.success(
+                format!("Done! I've saved the note for {}.", password.name),
+                OutputType::Error,
+            );
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I couldn't save the note (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            Err(CommandError::Store)
+        }
+    }
+}
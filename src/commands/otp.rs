@@ -0,0 +1,164 @@
+use crate::commands::CommandError;
+use crate::ffi;
+use crate::list;
+use crate::otp;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use rtoolbox::safe_string::SafeString;
+use std::ops::Deref;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    match matches.subcommand() {
+        Some(("set", set_matches)) => return set_otp_secret(set_matches, store, io),
+        Some(("uri", uri_matches)) => return show_otpauth_uri(uri_matches, store, io),
+        _ => {}
+    }
+
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which one-time password would you like to see?",
+        io,
+    )
+    .ok_or(CommandError::NotFound)?;
+
+    let secret = otp_secret_of(password, io)?;
+
+    let code = otp::totp_now(secret, otp::DEFAULT_PERIOD, otp::DEFAULT_DIGITS).ok_or_else(|| {
+        io.error(
+            format!("Woops, the TOTP secret for {} is not valid base32.", password.name),
+            OutputType::Error,
+        );
+        CommandError::InvalidInput
+    })?;
+
+    io.write(format!("{}\n", code), OutputType::Standard);
+    io.info(
+        format!(
+            "This code is valid for {} more seconds.",
+            otp::seconds_remaining(otp::DEFAULT_PERIOD)
+        ),
+        OutputType::Error,
+    );
+
+    Ok(())
+}
+
+fn otp_secret_of<'a>(
+    password: &'a password::v2::Password,
+    io: &mut impl CliInputOutput,
+) -> Result<&'a SafeString, CommandError> {
+    match password.otp_secret {
+        Some(ref secret) => Ok(secret),
+        None => {
+            io.error(
+                format!(
+                    "Woops, there is no TOTP secret for {}. You can add one with \
+                     `rooster otp set '{}' <secret>`.",
+                    password.name, password.name
+                ),
+                OutputType::Error,
+            );
+            Err(CommandError::NotFound)
+        }
+    }
+}
+
+/// Prints the `otpauth://` URI of the secret, to import it in an authenticator app. The
+/// printed line is scrubbed from memory, the terminal keeps its own copy though.
+fn show_otpauth_uri(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which TOTP secret would you like to export?",
+        io,
+    )
+    .ok_or(CommandError::NotFound)?;
+    let secret = otp_secret_of(password, io)?;
+
+    let uri = otp::otpauth_uri(&password.name, &password.username, secret).ok_or_else(|| {
+        io.error(
+            format!(
+                "Woops, the TOTP secret for {} is not valid base32.",
+                password.name
+            ),
+            OutputType::Error,
+        );
+        CommandError::InvalidInput
+    })?;
+
+    let line = SafeString::from_string(format!("{}\n", uri.deref()));
+    io.write(line.deref().as_str(), OutputType::Standard);
+    Ok(())
+}
+
+fn set_otp_secret(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let query = matches.get_one::<String>("app").unwrap();
+    let secret = SafeString::from_string(matches.get_one::<String>("secret").unwrap().clone());
+
+    if !otp::is_valid_secret(&secret) {
+        io.error(
+            "Woops, this TOTP secret is not valid base32.",
+            OutputType::Error,
+        );
+        return Err(CommandError::InvalidInput);
+    }
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which password would you like to add a TOTP secret to?",
+        io,
+    )
+    .ok_or(CommandError::NotFound)?
+    .clone();
+
+    let change_result =
+        store.change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                otp_secret: Some(secret.clone()),
+                updated_at: ffi::time(),
+                ..old_password
+            }
+        });
+
+    match change_result {
+        Ok(_) => {
+            io.success(
+                format!("Done! I've saved the TOTP secret for {}.", password.name),
+                OutputType::Error,
+            );
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!(
+                    "Woops, I couldn't save the TOTP secret (reason: {:?}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            Err(CommandError::Store)
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::commands::CommandError;
+use crate::ffi;
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let query = matches.get_one::<String>("app").unwrap();
+    let no_clipboard = !matches.get_flag("off");
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which password would you like to protect?",
+        io,
+    )
+    .ok_or(CommandError::NotFound)?
+    .clone();
+
+    let change_result =
+        store.change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                no_clipboard,
+                updated_at: ffi::time(),
+                ..old_password
+            }
+        });
+
+    match change_result {
+        Ok(_) => {
+            if no_clipboard {
+                io.success(
+                    format!(
+                        "Done! The password for {} will only be shown, never copied to your clipboard.",
+                        password.name
+                    ),
+                    OutputType::Error,
+                );
+            } else {
+                io.success(
+                    format!(
+                        "Done! The password for {} can be copied to your clipboard again.",
+                        password.name
+                    ),
+                    OutputType::Error,
+                );
+            }
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I couldn't save the change (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            Err(CommandError::Store)
+        }
+    }
+}
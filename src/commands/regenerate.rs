@@ -1,16 +1,30 @@
 use crate::clip;
+use crate::commands::import::confirm;
+use crate::commands::CommandError;
 use crate::ffi;
 use crate::generate::{check_password_len, PasswordSpec};
 use crate::list;
 use crate::password;
+use crate::select;
+use csv::Writer;
 use rclio::CliInputOutput;
 use rclio::OutputType;
+use std::io::Cursor;
+use std::ops::Deref;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
+    let filter = select::Filter::from_matches(matches).map_err(|err| {
+        io.error(err, OutputType::Error);
+        CommandError::InvalidInput
+    })?;
+    if !filter.is_empty() {
+        return regenerate_selection(matches, &filter, store, io);
+    }
+
     let query = matches.get_one::<String>("app").unwrap();
 
     let password = list::search_and_choose_password(
@@ -20,7 +34,7 @@ pub fn callback_exec(
         "Which password would you like to regenerate?",
         io,
     )
-    .ok_or(1)?
+    .ok_or(CommandError::NotFound)?
     .clone();
 
     let pwspec = PasswordSpec::new(
@@ -38,25 +52,36 @@ pub fn callback_exec(
                 ),
                 OutputType::Error,
             );
-            return Err(1);
+            return Err(CommandError::Io);
         }
     };
 
     let change_result =
         store.change_password(&password.name, &|old_password: password::v2::Password| {
             password::v2::Password {
-                name: old_password.name.clone(),
-                username: old_password.username.clone(),
                 password: password_as_string.clone(),
-                created_at: old_password.created_at,
                 updated_at: ffi::time(),
+                ..old_password
             }
         });
 
     match change_result {
+        Ok(password) if matches.get_flag("dry-run") => {
+            io.info(
+                format!(
+                    "I would have regenerated the password for {}.",
+                    password.name
+                ),
+                OutputType::Error,
+            );
+            Ok(())
+        }
+        Ok(password) if matches.get_one::<String>("output").is_some() => {
+            write_csv(&[password], !matches.get_flag("no-header"), io)
+        }
         Ok(password) => {
-            let show = matches.get_flag("show");
-            clip::confirm_password_retrieved(show, &password, io);
+            let show = clip::must_show(matches.get_flag("show"), &password, io);
+            clip::confirm_password_retrieved(show, None, false, &password, io);
             Ok(())
         }
         Err(err) => {
@@ -67,7 +92,149 @@ pub fn callback_exec(
                 ),
                 OutputType::Error,
             );
-            Err(1)
+            Err(CommandError::Store)
         }
     }
 }
+
+/// Regenerates every password picked with --contains, --regex, --tag or --exact. Nothing is
+/// copied to the clipboard, since it can only hold one password.
+fn regenerate_selection(
+    matches: &clap::ArgMatches,
+    filter: &select::Filter,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let names: Vec<String> = select::select_entries(store, filter)
+        .into_iter()
+        .map(|p| p.name.clone())
+        .collect();
+    if names.is_empty() {
+        io.error(
+            "Woops, no password matches, so there is nothing to regenerate.",
+            OutputType::Error,
+        );
+        return Err(CommandError::NotFound);
+    }
+
+    io.info(
+        format!("{} password(s) match:", names.len()),
+        OutputType::Error,
+    );
+    for name in names.iter() {
+        io.info(format!("  {}", name), OutputType::Error);
+    }
+    if !matches.get_flag("yes")
+        && !confirm(
+            format!("Regenerate these {} password(s)? [y/N]", names.len()),
+            io,
+        )
+    {
+        io.info("Alright, I didn't regenerate anything.", OutputType::Error);
+        return Err(CommandError::UserAbort);
+    }
+
+    let pwspec = PasswordSpec::new(
+        matches.get_flag("alnum"),
+        check_password_len(*matches.get_one::<usize>("length").unwrap(), io),
+    );
+
+    let mut regenerated = Vec::new();
+    for name in names.iter() {
+        let password_as_string = pwspec.generate_hard_password().map_err(|io_err| {
+            io.error(
+                format!(
+                    "Woops, I could not generate the password (reason: {:?}).",
+                    io_err
+                ),
+                OutputType::Error,
+            );
+            CommandError::Io
+        })?;
+
+        // Nothing is saved when we return an error, so all passwords are kept as they were.
+        let password = store
+            .change_password(name, &|old_password: password::v2::Password| {
+                password::v2::Password {
+                    password: password_as_string.clone(),
+                    updated_at: ffi::time(),
+                    ..old_password
+                }
+            })
+            .map_err(|err| {
+                io.error(
+                    format!(
+                        "Woops, I couldn't save the new password for {} (reason: {:?}).",
+                        name, err
+                    ),
+                    OutputType::Error,
+                );
+                CommandError::Store
+            })?;
+        regenerated.push(password);
+    }
+
+    if matches.get_flag("dry-run") {
+        io.info(
+            format!(
+                "I would have regenerated {} password(s).",
+                regenerated.len()
+            ),
+            OutputType::Error,
+        );
+        return Ok(());
+    }
+
+    io.success(
+        format!("Done! I've regenerated {} password(s).", regenerated.len()),
+        OutputType::Error,
+    );
+    if matches.get_one::<String>("output").is_some() {
+        write_csv(&regenerated, !matches.get_flag("no-header"), io)?;
+    } else if matches.get_flag("show") {
+        for password in regenerated.iter() {
+            io.success(
+                format!("{}: {}", password.name, password.password.deref()),
+                OutputType::Standard,
+            );
+        }
+    } else {
+        io.info(
+            "See them with `rooster get --show`, or use --show next time.",
+            OutputType::Error,
+        );
+    }
+    Ok(())
+}
+
+/// Prints `app,username,password` rows to stdout, for `--output csv`.
+fn write_csv(
+    passwords: &[password::v2::Password],
+    header: bool,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let mut csv_writer = Writer::from_writer(Cursor::new(Vec::new()));
+    if header {
+        if csv_writer
+            .write_record(&["app", "username", "password"])
+            .is_err()
+        {
+            return Err(CommandError::Io);
+        }
+    }
+    for password in passwords {
+        match csv_writer.write_record(&[
+            &password.name,
+            &password.username,
+            password.password.deref().as_str(),
+        ]) {
+            Ok(_) => {}
+            Err(_) => return Err(CommandError::Io),
+        }
+    }
+    io.write(
+        String::from_utf8(csv_writer.into_inner().unwrap().into_inner()).unwrap(),
+        OutputType::Standard,
+    );
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use crate::ffi;
+use crate::commands::CommandError;
 use crate::list;
 use crate::password;
 use rclio::CliInputOutput;
@@ -8,7 +8,7 @@ pub fn callback_exec(
     matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
     let query = matches.get_one::<String>("app").unwrap();
     let new_name = matches.get_one::<String>("new_name").unwrap();
 
@@ -19,28 +19,35 @@ pub fn callback_exec(
         "Which password would you like to rename?",
         io,
     )
-    .ok_or(1)?
+    .ok_or(CommandError::NotFound)?
     .clone();
 
-    let change_result =
-        store.change_password(&password.name, &|old_password: password::v2::Password| {
-            password::v2::Password {
-                name: new_name.clone(),
-                username: old_password.username.clone(),
-                password: old_password.password.clone(),
-                created_at: old_password.created_at,
-                updated_at: ffi::time(),
-            }
-        });
-
-    match change_result {
+    match store.rename_password(&password.name, new_name) {
+        Ok(_) if matches.get_flag("dry-run") => {
+            io.info(
+                format!("I would have renamed {} to {}.", password.name, new_name),
+                OutputType::Error,
+            );
+            Ok(())
+        }
         Ok(_) => {
             io.success(
                 format!("Done! I've renamed {} to {}", password.name, new_name),
-                OutputType::Standard,
+                OutputType::Error,
             );
             Ok(())
         }
+        Err(password::PasswordError::AppExistsError) => {
+            io.error(
+                format!(
+                    "Woops, there is already an app named {}. Pick another name, or delete it \
+                     first with `rooster delete '{}'`.",
+                    new_name, new_name
+                ),
+                OutputType::Error,
+            );
+            Err(CommandError::Duplicate)
+        }
         Err(err) => {
             io.error(
                 format!(
@@ -49,7 +56,7 @@ pub fn callback_exec(
                 ),
                 OutputType::Error,
             );
-            Err(1)
+            Err(CommandError::Store)
         }
     }
 }
@@ -0,0 +1,368 @@
+use crate::commands::CommandError;
+use crate::ffi;
+use crate::password;
+use crate::password::v2::{Password, PasswordStore};
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use rtoolbox::safe_string::SafeString;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::ops::Deref;
+
+/// Error codes from the JSON-RPC 2.0 specification.
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+/// For errors from the password store itself, in the range reserved for implementations.
+const STORE_ERROR: i32 = -32000;
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct RpcError {
+    code: i32,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct RpcResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+/// A password in `list` results, without any secret.
+#[derive(Serialize)]
+struct RpcPasswordSummary<'a> {
+    app: &'a str,
+    username: &'a str,
+}
+
+/// What an `undo` call reverted.
+#[derive(Serialize)]
+struct RpcUndoStep {
+    method: String,
+    apps: Vec<String>,
+}
+
+/// A password in `get` results, the only method that returns secrets.
+#[derive(Serialize)]
+struct RpcPassword<'a> {
+    app: &'a str,
+    username: &'a str,
+    password: &'a str,
+    notes: &'a str,
+}
+
+fn invalid_params(message: impl Into<String>) -> RpcError {
+    RpcError {
+        code: INVALID_PARAMS,
+        message: message.into(),
+    }
+}
+
+fn store_error(err: password::PasswordError) -> RpcError {
+    RpcError {
+        code: STORE_ERROR,
+        message: format!("{:?}", err),
+    }
+}
+
+/// The store checks changes the same way for `rooster add` and `rooster delete`, what it
+/// refuses is reported as invalid params.
+fn change_error(app: &str, err: password::PasswordError) -> RpcError {
+    match err {
+        password::PasswordError::AppExistsError => {
+            invalid_params(format!("There is already a password for \"{}\"", app))
+        }
+        password::PasswordError::NoSuchAppError => {
+            invalid_params(format!("No password for \"{}\"", app))
+        }
+        password::PasswordError::EmptyAppNameError => invalid_params("The app name is empty"),
+        password::PasswordError::EmptyPasswordError => invalid_params("The password is empty"),
+        err => store_error(err),
+    }
+}
+
+fn string_param(params: &Value, name: &str) -> Result<String, RpcError> {
+    params
+        .get(name)
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_owned())
+        .ok_or_else(|| invalid_params(format!("Missing string parameter \"{}\"", name)))
+}
+
+fn handle_request(
+    request: &RpcRequest,
+    store: &mut PasswordStore,
+    save: &mut dyn FnMut(&mut PasswordStore) -> Result<(), String>,
+) -> Result<Value, RpcError> {
+    match request.method.as_str() {
+        "list" => {
+            let passwords = match request.params.get("query").and_then(|q| q.as_str()) {
+                Some(query) => store.search_passwords(query),
+                None => store.get_all_passwords(),
+            };
+            let summaries: Vec<RpcPasswordSummary> = passwords
+                .iter()
+                .map(|p| RpcPasswordSummary {
+                    app: p.name.as_str(),
+                    username: p.username.as_str(),
+                })
+                .collect();
+            // This should never fail. The structs are all encodable.
+            Ok(serde_json::to_value(&summaries).unwrap())
+        }
+        "get" => {
+            let app = string_param(&request.params, "app")?;
+            let password = store
+                .get_password(&app)
+                .ok_or_else(|| invalid_params(format!("No password for \"{}\"", app)))?;
+            Ok(serde_json::to_value(&RpcPassword {
+                app: password.name.as_str(),
+                username: password.username.as_str(),
+                password: password.password.deref(),
+                notes: password.notes.deref(),
+            })
+            .unwrap())
+        }
+        "add" => {
+            let app = string_param(&request.params, "app")?;
+            let username = string_param(&request.params, "username")?;
+            let password = SafeString::from_string(string_param(&request.params, "password")?);
+            let before = store.snapshot();
+            store
+                .add_password(Password::new(app.clone(), username, password))
+                .map_err(|err| change_error(&app, err))?;
+            store.record_undo("add", before, ffi::time());
+            Ok(Value::Null)
+        }
+        "delete" => {
+            let app = string_param(&request.params, "app")?;
+            let before = store.snapshot();
+            store
+                .trash_password(&app)
+                .map_err(|err| change_error(&app, err))?;
+            store.record_undo("delete", before, ffi::time());
+            Ok(Value::Null)
+        }
+        // The undo log only lives as long as the session, it is never saved.
+        "undo" => {
+            let step = store.undo().map_err(|err| match err {
+                password::PasswordError::NothingToUndoError => {
+                    invalid_params("There is nothing to undo")
+                }
+                err => store_error(err),
+            })?;
+            Ok(serde_json::to_value(&RpcUndoStep {
+                apps: step.affected_names(),
+                method: step.command,
+            })
+            .unwrap())
+        }
+        "save" => save(store)
+            .map(|_| Value::Null)
+            .map_err(|message| RpcError {
+                code: STORE_ERROR,
+                message,
+            }),
+        method => Err(RpcError {
+            code: METHOD_NOT_FOUND,
+            message: format!("Unknown method \"{}\"", method),
+        }),
+    }
+}
+
+fn handle_line(
+    line: &str,
+    store: &mut PasswordStore,
+    save: &mut dyn FnMut(&mut PasswordStore) -> Result<(), String>,
+) -> RpcResponse {
+    let (id, outcome) = match serde_json::from_str::<RpcRequest>(line) {
+        Ok(request) => (request.id.clone(), handle_request(&request, store, save)),
+        Err(err) => (
+            Value::Null,
+            Err(RpcError {
+                code: PARSE_ERROR,
+                message: err.to_string(),
+            }),
+        ),
+    };
+
+    let (result, error) = match outcome {
+        Ok(result) => (Some(result), None),
+        Err(error) => (None, Some(error)),
+    };
+    RpcResponse {
+        jsonrpc: "2.0",
+        id,
+        result,
+        error,
+    }
+}
+
+/// Answers JSON-RPC 2.0 requests, one per line on stdin, with one response per line on
+/// stdout. The methods are `list`, `get`, `add`, `delete`, `undo` and `save`; `undo` reverts
+/// the last `add` or `delete` of the session. The session ends on an empty line or at the end
+/// of stdin, and the password file is then saved.
+pub fn callback_exec(
+    _matches: &clap::ArgMatches,
+    store: &mut PasswordStore,
+    save: &mut dyn FnMut(&mut PasswordStore) -> Result<(), String>,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    loop {
+        let line = match io.read_line() {
+            Ok(line) => line,
+            Err(_) => return Ok(()),
+        };
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+
+        let response = handle_line(line.trim(), store, save);
+        // Responses to `get` hold a password, so don't leave copies around.
+        let response_json = SafeString::from_string(serde_json::to_string(&response).unwrap());
+        io.write(format!("{}\n", response_json.deref()), OutputType::Standard);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::handle_line;
+    use crate::password::v2::{Password, PasswordStore};
+    use rtoolbox::safe_string::SafeString;
+
+    fn call(line: &str, store: &mut PasswordStore, saves: &mut usize) -> String {
+        let mut save = |_: &mut PasswordStore| -> Result<(), String> {
+            *saves += 1;
+            Ok(())
+        };
+        serde_json::to_string(&handle_line(line, store, &mut save)).unwrap()
+    }
+
+    #[test]
+    fn test_handle_line() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        store
+            .add_password(Password::new("Youtube", "yt@example.com", "abcd"))
+            .unwrap();
+        let mut saves = 0;
+
+        assert_eq!(
+            call(
+                r#"{"jsonrpc":"2.0","id":1,"method":"list"}"#,
+                &mut store,
+                &mut saves
+            ),
+            r#"{"jsonrpc":"2.0","id":1,"result":[{"app":"Youtube","username":"yt@example.com"}]}"#
+        );
+        assert_eq!(
+            call(
+                r#"{"jsonrpc":"2.0","id":2,"method":"get","params":{"app":"youtube"}}"#,
+                &mut store,
+                &mut saves
+            ),
+            r#"{"jsonrpc":"2.0","id":2,"result":{"app":"Youtube","username":"yt@example.com","password":"abcd","notes":""}}"#
+        );
+        assert_eq!(
+            call(
+                r#"{"jsonrpc":"2.0","id":3,"method":"add","params":{"app":"Google","username":"me","password":"efgh"}}"#,
+                &mut store,
+                &mut saves
+            ),
+            r#"{"jsonrpc":"2.0","id":3,"result":null}"#
+        );
+        assert!(store.has_password("Google"));
+        assert_eq!(
+            call(
+                r#"{"jsonrpc":"2.0","id":4,"method":"delete","params":{"app":"google"}}"#,
+                &mut store,
+                &mut saves
+            ),
+            r#"{"jsonrpc":"2.0","id":4,"result":null}"#
+        );
+        assert!(!store.has_password("Google"));
+        assert_eq!(
+            call(
+                r#"{"jsonrpc":"2.0","id":5,"method":"save"}"#,
+                &mut store,
+                &mut saves
+            ),
+            r#"{"jsonrpc":"2.0","id":5,"result":null}"#
+        );
+        assert_eq!(saves, 1);
+
+        // The delete, then the add, then nothing
+        assert_eq!(
+            call(
+                r#"{"jsonrpc":"2.0","id":6,"method":"undo"}"#,
+                &mut store,
+                &mut saves
+            ),
+            r#"{"jsonrpc":"2.0","id":6,"result":{"method":"delete","apps":["Google"]}}"#
+        );
+        assert!(store.has_password("Google"));
+        assert_eq!(
+            call(
+                r#"{"jsonrpc":"2.0","id":7,"method":"undo"}"#,
+                &mut store,
+                &mut saves
+            ),
+            r#"{"jsonrpc":"2.0","id":7,"result":{"method":"add","apps":["Google"]}}"#
+        );
+        assert!(!store.has_password("Google"));
+        assert!(call(
+            r#"{"jsonrpc":"2.0","id":8,"method":"undo"}"#,
+            &mut store,
+            &mut saves
+        )
+        .contains(r#""code":-32602"#));
+    }
+
+    #[test]
+    fn test_handle_line_errors() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        let mut saves = 0;
+
+        assert!(call("not json", &mut store, &mut saves).contains(r#""code":-32700"#));
+        assert!(call(r#"{"id":1,"method":"nope"}"#, &mut store, &mut saves)
+            .contains(r#""code":-32601"#));
+        assert!(
+            call(r#"{"id":1,"method":"get"}"#, &mut store, &mut saves).contains(r#""code":-32602"#)
+        );
+        assert!(call(
+            r#"{"id":1,"method":"get","params":{"app":"nope"}}"#,
+            &mut store,
+            &mut saves
+        )
+        .contains(r#""code":-32602"#));
+
+        // Same checks as `rooster add` and `rooster delete`
+        let add = r#"{"id":1,"method":"add","params":{"app":"Youtube","username":"me","password":"abcd"}}"#;
+        assert!(call(add, &mut store, &mut saves).contains(r#""result":null"#));
+        assert!(call(add, &mut store, &mut saves).contains(r#""code":-32602"#));
+        assert!(call(
+            r#"{"id":1,"method":"add","params":{"app":" ","username":"me","password":"abcd"}}"#,
+            &mut store,
+            &mut saves
+        )
+        .contains(r#""code":-32602"#));
+        assert!(call(
+            r#"{"id":1,"method":"delete","params":{"app":"nope"}}"#,
+            &mut store,
+            &mut saves
+        )
+        .contains(r#""code":-32602"#));
+    }
+}
@@ -0,0 +1,75 @@
+use crate::commands::CommandError;
+use crate::list;
+use crate::password;
+use crate::password::v2::Password;
+use crate::select;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+/// Case-insensitive substring search over app names and usernames.
+fn search_exact<'a>(store: &'a password::v2::PasswordStore, query: &str) -> Vec<&'a Password> {
+    let query = query.to_lowercase();
+    let mut passwords: Vec<&Password> = store
+        .get_all_passwords()
+        .into_iter()
+        .filter(|p| {
+            p.name.to_lowercase().contains(query.as_str())
+                || p.username.to_lowercase().contains(query.as_str())
+        })
+        .collect();
+
+    passwords.sort_by_key(|p| p.name.to_lowercase());
+    passwords
+}
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    // Either a query or a pattern is required, see `run_with_args`.
+    let pattern = matches.get_one::<String>("regex");
+    let query = matches.get_one::<String>("query").or(pattern).unwrap();
+
+    let mut passwords = if let Some(pattern) = pattern {
+        let field = matches
+            .get_one::<String>("field")
+            .map_or("app", |f| f.as_str());
+        let selector = select::regex_selector(pattern, field).map_err(|message| {
+            io.error(message, OutputType::Error);
+            CommandError::InvalidInput
+        })?;
+        store
+            .get_all_passwords()
+            .into_iter()
+            .filter(|p| selector.matches(p))
+            .collect()
+    } else if matches.get_flag("exact") {
+        search_exact(store, query)
+    } else {
+        store.search_passwords(query)
+    };
+
+    let json = list::wants_json(matches);
+
+    if passwords.len() == 0 {
+        io.error(
+            format!("Woops, I can't find any passwords for \"{}\".", query),
+            OutputType::Error,
+        );
+        if json {
+            list::print_list_of_passwords_as_json(&passwords, io);
+        }
+        return Err(CommandError::NotFound);
+    }
+
+    list::apply_limit(&mut passwords, matches, io);
+
+    if json {
+        list::print_list_of_passwords_as_json(&passwords, io);
+    } else {
+        list::print_list_of_passwords(&passwords, list::WITHOUT_NUMBERS, io);
+    }
+
+    Ok(())
+}
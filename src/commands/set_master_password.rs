@@ -1,54 +1,34 @@
+use crate::commands::init::ask_new_master_password;
+use crate::commands::CommandError;
 use crate::password;
 use rclio::CliInputOutput;
 use rclio::OutputType;
 use std::ops::Deref;
 
 pub fn callback_exec(
-    _matches: &clap::ArgMatches,
+    matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
-    match io.prompt_password("Type your new master password: ") {
-        Ok(master_password) => {
-            let master_password_confirmation =
-                match io.prompt_password("Type your new master password once more: ") {
-                    Ok(master_password_confirmation) => master_password_confirmation,
-                    Err(err) => {
-                        io.error(
-                            format!(
-                                "I could not read your new master password (reason: {:?}).",
-                                err
-                            ),
-                            OutputType::Error,
-                        );
-                        return Err(1);
-                    }
-                };
+) -> Result<(), CommandError> {
+    let master_password = ask_new_master_password(
+        "Type your new master password: ",
+        Some("Type your new master password once more: "),
+        matches.get_flag("allow-empty"),
+        io,
+    )?;
 
-            if master_password != master_password_confirmation {
-                io.error(
-                    "The master password confirmation did not match. Aborting.",
-                    OutputType::Error,
-                );
-                return Err(1);
-            }
-
-            store.change_master_password(master_password.deref());
-        }
-        Err(err) => {
-            io.error(
-                format!(
-                    "I could not read your new master password (reason: {:?}).",
-                    err
-                ),
-                OutputType::Error,
-            );
-            return Err(1);
-        }
+    if let Err(err) = store.change_master_password(master_password.deref()) {
+        io.error(
+            format!(
+                "I could not re-encrypt your passwords with the new master password \
+                 (reason: {:?}). Your master password has not been changed.",
+                err
+            ),
+            OutputType::Error,
+        );
+        return Err(CommandError::Store);
     }
-    io.success(
-        "Your master password has been changed.",
-        OutputType::Standard,
-    );
+
+    io.success("Your master password has been changed.", OutputType::Error);
     Ok(())
 }
@@ -1,11 +1,101 @@
+use crate::commands::init::format_duration;
+use crate::commands::CommandError;
 use crate::password;
+use crate::password::v2::{KEY_LEN, SCRYPT_PARAM_LOG2_N};
 use rclio::{CliInputOutput, OutputType};
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Environment variables administrators can set to pin the allowed parameter range.
+const MIN_LOG2_N_ENV_VAR: &'static str = "ROOSTER_SCRYPT_MIN_LOG2N";
+const MAX_LOG2_N_ENV_VAR: &'static str = "ROOSTER_SCRYPT_MAX_LOG2N";
+const MIN_R_ENV_VAR: &'static str = "ROOSTER_SCRYPT_MIN_R";
+const MAX_R_ENV_VAR: &'static str = "ROOSTER_SCRYPT_MAX_R";
+const MIN_P_ENV_VAR: &'static str = "ROOSTER_SCRYPT_MIN_P";
+const MAX_P_ENV_VAR: &'static str = "ROOSTER_SCRYPT_MAX_P";
+
+/// Nothing weaker than what `rooster init` uses by default.
+const DEFAULT_MIN_LOG2_N: u8 = SCRYPT_PARAM_LOG2_N;
+const DEFAULT_MAX_LOG2_N: u8 = 20;
+const DEFAULT_MIN_R: u32 = 1;
+const DEFAULT_MAX_R: u32 = 8;
+const DEFAULT_MIN_P: u32 = 1;
+const DEFAULT_MAX_P: u32 = 1;
+
+/// scrypt needs this much memory or more to unlock, and `--force` can't go above it: the
+/// password file could then only be opened on a computer with more memory than this one.
+const MAX_MEMORY_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Warn when unlocking takes longer than this with the new parameters.
+const SLOW_UNLOCK_WARNING: Duration = Duration::from_secs(3);
+
+/// Each environment variable with its default value, for `rooster config show`.
+pub const THRESHOLD_SETTINGS: [(&'static str, u32); 6] = [
+    (MIN_LOG2_N_ENV_VAR, DEFAULT_MIN_LOG2_N as u32),
+    (MAX_LOG2_N_ENV_VAR, DEFAULT_MAX_LOG2_N as u32),
+    (MIN_R_ENV_VAR, DEFAULT_MIN_R),
+    (MAX_R_ENV_VAR, DEFAULT_MAX_R),
+    (MIN_P_ENV_VAR, DEFAULT_MIN_P),
+    (MAX_P_ENV_VAR, DEFAULT_MAX_P),
+];
+
+struct Thresholds {
+    min_log2_n: u8,
+    max_log2_n: u8,
+    min_r: u32,
+    max_r: u32,
+    min_p: u32,
+    max_p: u32,
+}
+
+impl Thresholds {
+    fn from_env() -> Result<Thresholds, String> {
+        Ok(Thresholds {
+            min_log2_n: read_threshold(MIN_LOG2_N_ENV_VAR, DEFAULT_MIN_LOG2_N)?,
+            max_log2_n: read_threshold(MAX_LOG2_N_ENV_VAR, DEFAULT_MAX_LOG2_N)?,
+            min_r: read_threshold(MIN_R_ENV_VAR, DEFAULT_MIN_R)?,
+            max_r: read_threshold(MAX_R_ENV_VAR, DEFAULT_MAX_R)?,
+            min_p: read_threshold(MIN_P_ENV_VAR, DEFAULT_MIN_P)?,
+            max_p: read_threshold(MAX_P_ENV_VAR, DEFAULT_MAX_P)?,
+        })
+    }
+}
+
+fn read_threshold<T: FromStr>(name: &str, default: T) -> Result<T, String> {
+    match env::var(name) {
+        Ok(value) => value.parse::<T>().map_err(|_| {
+            format!(
+                "The {} environment variable must be a number (got \"{}\").",
+                name, value
+            )
+        }),
+        Err(_) => Ok(default),
+    }
+}
+
+/// How much memory scrypt needs with these parameters: 128 * r * 2^log2n bytes. `None` if that
+/// doesn't even fit in a u64.
+fn memory_bytes(log2_n: u8, r: u32) -> Option<u64> {
+    1u64.checked_shl(log2_n as u32)
+        .and_then(|n| n.checked_mul(128))
+        .and_then(|bytes| bytes.checked_mul(r as u64))
+}
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
+    if matches.get_flag("show") {
+        let (log2_n, r, p) = store.scrypt_params();
+        io.write(
+            format!("log2n: {}\nr: {}\np: {}\n", log2_n, r, p),
+            OutputType::Standard,
+        );
+        return Ok(());
+    }
+
     let log2_n = *matches.get_one::<u8>("log2n").unwrap();
     let r = *matches.get_one::<u32>("r").unwrap();
     let p = *matches.get_one::<u32>("p").unwrap();
@@ -15,19 +105,109 @@ pub fn callback_exec(
             format!("The parameters must be > 0 ({}, {}, {})", log2_n, r, p),
             OutputType::Error,
         );
-        return Err(1);
+        return Err(CommandError::InvalidInput);
+    }
+
+    // Not even --force gets past these, scrypt itself refuses them.
+    if scrypt::Params::new(log2_n, r, p, KEY_LEN).is_err() {
+        io.error(
+            format!(
+                "These parameters are not valid for scrypt ({}, {}, {}): log2n must be less \
+                 than 16 * r, and r * p less than 2^30. Aborting.",
+                log2_n, r, p
+            ),
+            OutputType::Error,
+        );
+        return Err(CommandError::InvalidInput);
+    }
+
+    match memory_bytes(log2_n, r) {
+        Some(bytes) if bytes <= MAX_MEMORY_BYTES => {}
+        _ => {
+            io.error(
+                format!(
+                    "With these parameters, unlocking would need more than {} GiB of memory, \
+                     so Rooster could crash every time you open your password file. Aborting.",
+                    MAX_MEMORY_BYTES / 1024 / 1024 / 1024
+                ),
+                OutputType::Error,
+            );
+            io.error("Lower log2n or r.", OutputType::Error);
+            return Err(CommandError::InvalidInput);
+        }
+    }
+
+    let thresholds = Thresholds::from_env().map_err(|err| {
+        io.error(err, OutputType::Error);
+        CommandError::InvalidInput
+    })?;
+
+    if !matches.get_flag("force")
+        && (log2_n < thresholds.min_log2_n || r < thresholds.min_r || p < thresholds.min_p)
+    {
+        io.error(
+            format!(
+                "These parameters are below the minimum allowed ({}, {}, {}). Aborting.",
+                thresholds.min_log2_n, thresholds.min_r, thresholds.min_p
+            ),
+            OutputType::Error,
+        );
+        io.error(
+            "Weaker parameters make your master password faster to guess for anyone who gets \
+             a copy of your password file. Run with --force to use them anyway.",
+            OutputType::Error,
+        );
+        return Err(CommandError::InvalidInput);
     }
 
-    if !matches.get_flag("force") && (log2_n > 20 || r > 8 || p > 1) {
+    if !matches.get_flag("force")
+        && (log2_n > thresholds.max_log2_n || r > thresholds.max_r || p > thresholds.max_p)
+    {
         io.error("These parameters seem very high. You might be unable to open your password file ever again. Aborting.", OutputType::Error);
         io.error(
             "Run with --force to force, but make a backup of your password file first.",
             OutputType::Error,
         );
-        return Err(1);
+        return Err(CommandError::InvalidInput);
     }
 
-    store.change_scrypt_params(log2_n, r, p);
+    let unlock_duration = store.change_scrypt_params(log2_n, r, p).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I couldn't use these parameters (reason: {:?}).",
+                err
+            ),
+            OutputType::Error,
+        );
+        CommandError::InvalidInput
+    })?;
+    io.info(
+        format!(
+            "With these parameters, unlocking takes about {} on this computer.",
+            format_duration(unlock_duration)
+        ),
+        OutputType::Error,
+    );
+    if unlock_duration > SLOW_UNLOCK_WARNING {
+        io.warning(
+            "That's slow. If you don't want to wait this long, set lower parameters.",
+            OutputType::Error,
+        );
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::{memory_bytes, MAX_MEMORY_BYTES};
+
+    #[test]
+    fn test_memory_bytes() {
+        assert_eq!(memory_bytes(12, 8), Some(4 * 1024 * 1024));
+        assert_eq!(memory_bytes(21, 8), Some(MAX_MEMORY_BYTES));
+        assert!(memory_bytes(22, 8).unwrap() > MAX_MEMORY_BYTES);
+        assert_eq!(memory_bytes(63, 8), None);
+        assert_eq!(memory_bytes(64, 1), None);
+    }
+}
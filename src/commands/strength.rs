@@ -0,0 +1,46 @@
+use crate::commands::CommandError;
+use crate::list;
+use crate::password;
+use crate::strength::{estimate_entropy_bits, rate, Rating};
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which password would you like to score?",
+        io,
+    )
+    .ok_or(CommandError::NotFound)?;
+
+    let entropy_bits = estimate_entropy_bits(&password.password);
+    let rating = rate(entropy_bits);
+
+    io.success(
+        format!(
+            "The password for {} has about {:.0} bits of entropy ({}).",
+            password.name, entropy_bits, rating
+        ),
+        OutputType::Standard,
+    );
+
+    if rating == Rating::Weak {
+        io.warning(
+            format!(
+                "This password is weak. You can replace it with `rooster regenerate '{}'`.",
+                password.name
+            ),
+            OutputType::Error,
+        );
+    }
+
+    Ok(())
+}
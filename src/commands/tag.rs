@@ -0,0 +1,84 @@
+use crate::commands::CommandError;
+use crate::password;
+use crate::select;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let subcommand_name = matches.subcommand_name().unwrap();
+    let subcommand_matches = matches.subcommand_matches(subcommand_name).unwrap();
+
+    if subcommand_name == "rename" {
+        rename_tag(subcommand_matches, store, io)
+    } else {
+        unimplemented!("Invalid tag subcommand")
+    }
+}
+
+fn rename_tag(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let old_tag = matches.get_one::<String>("old").unwrap();
+    let new_tag = matches.get_one::<String>("new").unwrap();
+
+    if new_tag.trim().is_empty() {
+        io.error("Woops, the new tag can't be empty.", OutputType::Error);
+        return Err(CommandError::InvalidInput);
+    }
+
+    // Every password, unless some are picked with --contains, --regex, --tag or --exact.
+    let filter = select::Filter::from_matches(matches).map_err(|err| {
+        io.error(err, OutputType::Error);
+        CommandError::InvalidInput
+    })?;
+    let names: Vec<String> = select::select_entries(store, &filter)
+        .into_iter()
+        .filter(|p| p.has_tag(old_tag))
+        .map(|p| p.name.clone())
+        .collect();
+
+    if names.is_empty() {
+        io.error(
+            format!("Woops, no password is tagged \"{}\".", old_tag),
+            OutputType::Error,
+        );
+        return Err(CommandError::NotFound);
+    }
+
+    for name in names.iter() {
+        // Tags are only labels, so `updated_at` is left alone. It is used for rotation
+        // reminders, which a rename shouldn't reset.
+        let change_result =
+            store.change_password(name, &|mut old_password: password::v2::Password| {
+                old_password.rename_tag(old_tag, new_tag);
+                old_password
+            });
+        if let Err(err) = change_result {
+            io.error(
+                format!(
+                    "Woops, I couldn't rename the tag of {} (reason: {:?}).",
+                    name, err
+                ),
+                OutputType::Error,
+            );
+            return Err(CommandError::Store);
+        }
+    }
+
+    io.success(
+        format!(
+            "Done! I've renamed \"{}\" to \"{}\" on {} password(s).",
+            old_tag,
+            new_tag,
+            names.len()
+        ),
+        OutputType::Error,
+    );
+    Ok(())
+}
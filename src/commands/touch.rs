@@ -0,0 +1,59 @@
+use crate::commands::CommandError;
+use crate::date;
+use crate::ffi;
+use crate::list;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+/// Marks a password as reviewed, or as changed outside of Rooster, which restarts its rotation
+/// period. Nothing else about the password changes.
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let query = matches.get_one::<String>("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which password would you like to mark as up to date?",
+        io,
+    )
+    .ok_or(CommandError::NotFound)?
+    .clone();
+
+    let now = ffi::time();
+    let change_result =
+        store.change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                updated_at: now,
+                ..old_password
+            }
+        });
+
+    match change_result {
+        Ok(password) => {
+            io.success(
+                format!("Done! I've marked {} as up to date.", password.name),
+                OutputType::Error,
+            );
+            if let Some(due_at) = password.rotation_due_at() {
+                io.info(
+                    format!("Next rotation: {}.", date::format_date(due_at)),
+                    OutputType::Error,
+                );
+            }
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I couldn't save the change (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            Err(CommandError::Store)
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::commands::CommandError;
 use crate::ffi;
 use crate::list;
 use crate::password;
@@ -8,7 +9,7 @@ pub fn callback_exec(
     matches: &clap::ArgMatches,
     store: &mut password::v2::PasswordStore,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
+) -> Result<(), CommandError> {
     let query = matches.get_one::<String>("app").unwrap();
     let new_username = matches.get_one::<String>("new_username").unwrap();
 
@@ -19,7 +20,7 @@ pub fn callback_exec(
         "Which password would you like to transfer?",
         io,
     )
-    .ok_or(1)?
+    .ok_or(CommandError::NotFound)?
     .clone();
 
     let old_username = password.username;
@@ -27,19 +28,27 @@ pub fn callback_exec(
     let change_result =
         store.change_password(&password.name, &|old_password: password::v2::Password| {
             password::v2::Password {
-                name: old_password.name.clone(),
                 username: new_username.clone(),
-                password: old_password.password.clone(),
-                created_at: old_password.created_at,
                 updated_at: ffi::time(),
+                ..old_password
             }
         });
 
     match change_result {
+        Ok(_) if matches.get_flag("dry-run") => {
+            io.info(
+                format!(
+                    "I would have transfered {} to {}.",
+                    old_username, new_username
+                ),
+                OutputType::Error,
+            );
+            Ok(())
+        }
         Ok(_) => {
             io.success(
                 format!("Done! I've transfered {} to {}", old_username, new_username),
-                OutputType::Standard,
+                OutputType::Error,
             );
             Ok(())
         }
@@ -51,7 +60,7 @@ pub fn callback_exec(
                 ),
                 OutputType::Error,
             );
-            Err(1)
+            Err(CommandError::Store)
         }
     }
 }
@@ -0,0 +1,138 @@
+use crate::commands::CommandError;
+use crate::date;
+use crate::ffi;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use std::env;
+
+/// Environment variable to change how long deleted passwords are kept.
+pub const RETENTION_DAYS_ENV_VAR: &'static str = "ROOSTER_TRASH_RETENTION_DAYS";
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+/// How many days deleted passwords are kept before being purged for good.
+pub fn retention_days() -> Result<u32, String> {
+    match env::var(RETENTION_DAYS_ENV_VAR) {
+        Ok(value) => value.parse::<u32>().map_err(|_| {
+            format!(
+                "The {} environment variable must be a number (got \"{}\").",
+                RETENTION_DAYS_ENV_VAR, value
+            )
+        }),
+        Err(_) => Ok(DEFAULT_RETENTION_DAYS),
+    }
+}
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let subcommand_name = matches.subcommand_name().unwrap();
+    let subcommand_matches = matches.subcommand_matches(subcommand_name).unwrap();
+
+    if subcommand_name == "list" {
+        list_trash(store, io)
+    } else if subcommand_name == "restore" {
+        restore_from_trash(subcommand_matches, store, io)
+    } else {
+        unimplemented!("Invalid trash subcommand")
+    }
+}
+
+fn list_trash(
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let retention_days = retention_days().map_err(|err| {
+        io.error(err, OutputType::Error);
+        CommandError::InvalidInput
+    })?;
+
+    let trash = store.get_trashed_passwords();
+    if trash.is_empty() {
+        io.info("The trash is empty.", OutputType::Error);
+        return Ok(());
+    }
+
+    let now = ffi::time();
+    let longest_app_name = trash
+        .iter()
+        .map(|t| t.password.name.len())
+        .max()
+        .unwrap_or(0);
+    let longest_username = trash
+        .iter()
+        .map(|t| t.password.username.len())
+        .max()
+        .unwrap_or(0);
+
+    for t in trash {
+        let days_left = retention_days as i64 - date::days_between(t.deleted_at, now);
+        io.info(
+            format!(
+                "{:app_name_width$} {:username_width$} deleted on {}, purged in {} day(s)",
+                t.password.name,
+                t.password.username,
+                date::format_date(t.deleted_at),
+                days_left.max(0),
+                app_name_width = longest_app_name,
+                username_width = longest_username,
+            ),
+            OutputType::Standard,
+        );
+    }
+
+    Ok(())
+}
+
+fn restore_from_trash(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let app_name = matches.get_one::<String>("app").unwrap();
+
+    match store.restore_password(app_name) {
+        Ok(password) => {
+            io.success(
+                format!(
+                    "Done! I've restored the password for \"{}\".",
+                    password.name
+                ),
+                OutputType::Error,
+            );
+            Ok(())
+        }
+        Err(password::PasswordError::NoSuchAppError) => {
+            io.error(
+                format!(
+                    "Woops, there is no \"{}\" in the trash. See `rooster trash list`.",
+                    app_name
+                ),
+                OutputType::Error,
+            );
+            Err(CommandError::NotFound)
+        }
+        Err(password::PasswordError::AppExistsError) => {
+            io.error(
+                format!(
+                    "Woops, there is already an app named \"{}\". Rename it first.",
+                    app_name
+                ),
+                OutputType::Error,
+            );
+            Err(CommandError::Duplicate)
+        }
+        Err(err) => {
+            io.error(
+                format!(
+                    "Woops, I couldn't restore this password (reason: {:?}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            Err(CommandError::Store)
+        }
+    }
+}
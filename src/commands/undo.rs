@@ -0,0 +1,103 @@
+use crate::commands::import::confirm;
+use crate::commands::CommandError;
+use crate::password;
+use crate::password::v2::PasswordStore;
+use crate::password::PasswordError;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+use std::path::Path;
+
+/// What undoing does to each password that differs between the password file and its backup.
+fn list_changes(store: &PasswordStore, backup: &PasswordStore) -> Vec<String> {
+    let mut changes = Vec::new();
+    for password in store.get_all_passwords() {
+        match backup.get_password(&password.name) {
+            None => changes.push(format!("{} (added, undoing removes it)", password.name)),
+            Some(ref previous) if previous != password => changes.push(format!(
+                "{} (changed, undoing puts back the previous version)",
+                password.name
+            )),
+            Some(_) => {}
+        }
+    }
+    for previous in backup.get_all_passwords() {
+        if !store.has_password(&previous.name) {
+            changes.push(format!(
+                "{} (deleted, undoing brings it back)",
+                previous.name
+            ));
+        }
+    }
+    changes
+}
+
+/// Reverts the last save, by putting back the passwords from the backup that saving keeps
+/// next to the password file. Undoing saves too, so undoing twice puts the change back.
+/// A new master password can't be undone, the backup only opens with the old one.
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    rooster_file_path: &Path,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let backup = match store.open_backup(rooster_file_path) {
+        Ok(backup) => backup,
+        Err(PasswordError::NothingToUndoError) => {
+            io.error(
+                "Woops, there is nothing to undo, your password file was never changed.",
+                OutputType::Error,
+            );
+            return Err(CommandError::NotFound);
+        }
+        Err(PasswordError::DecryptionError) => {
+            io.error(
+                format!(
+                    "Woops, the backup doesn't open with your master password, so the last \
+                     change was probably a new master password, which can't be undone. You \
+                     can still open the backup with `rooster list --file '{}'`.",
+                    password::v2::backup_path(rooster_file_path).to_string_lossy()
+                ),
+                OutputType::Error,
+            );
+            return Err(CommandError::Decryption);
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I couldn't read the backup (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            return Err(CommandError::Store);
+        }
+    };
+
+    let changes = list_changes(store, &backup);
+    if changes.is_empty() {
+        io.error(
+            "Woops, there is nothing to undo, the last change didn't touch any password.",
+            OutputType::Error,
+        );
+        return Err(CommandError::NotFound);
+    }
+
+    io.info("Undoing the last change affects:", OutputType::Error);
+    for change in changes.iter() {
+        io.info(format!("  {}", change), OutputType::Error);
+    }
+    if !matches.get_flag("yes") && !confirm("Undo it? [y/N]".to_owned(), io) {
+        io.info("Alright, I didn't undo anything.", OutputType::Error);
+        return Err(CommandError::UserAbort);
+    }
+
+    store.revert_to(backup);
+
+    if matches.get_flag("dry-run") {
+        io.info("I would have undone the last change.", OutputType::Error);
+        return Ok(());
+    }
+
+    io.success(
+        "Done! I've undone the last change. Run `rooster undo` again to redo it.",
+        OutputType::Error,
+    );
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use crate::commands::CommandError;
+use crate::password;
+use rclio::CliInputOutput;
+use rclio::OutputType;
+
+/// Exits with 0 when the password read from stdin is the one stored for the app, 1 otherwise.
+/// The stored password is never printed, so scripts can check it without seeing it.
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), CommandError> {
+    let app_name = matches.get_one::<String>("app").unwrap();
+
+    // No fuzzy matching, a script should never check the wrong app.
+    let password = match store.get_password(app_name) {
+        Some(password) => password,
+        None => {
+            io.error(
+                format!("Woops, there is no password for \"{}\".", app_name),
+                OutputType::Error,
+            );
+            return Err(CommandError::NotFound);
+        }
+    };
+
+    let candidate = io
+        .prompt_password(format!(
+            "What password do you want to check for \"{}\"? ",
+            password.name
+        ))
+        .map_err(|err| {
+            io.error(
+                format!("\nI couldn't read the password (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            CommandError::Io
+        })?;
+
+    if password::constant_time_eq(&candidate, &password.password) {
+        io.success(
+            format!("The password for {} matches.", password.name),
+            OutputType::Error,
+        );
+        Ok(())
+    } else {
+        io.error(
+            format!("The password for {} does not match.", password.name),
+            OutputType::Error,
+        );
+        Err(CommandError::CheckFailed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::callback_exec;
+    use crate::commands::CommandError;
+    use crate::password::v2::{Password, PasswordStore};
+    use clap::{Arg, Command};
+    use rclio::CursorInputOutput;
+    use rtoolbox::safe_string::SafeString;
+
+    fn verify(store: &mut PasswordStore, app: &str, input: &str) -> Result<(), CommandError> {
+        let matches = Command::new("verify-password")
+            .arg(Arg::new("app").required(true))
+            .get_matches_from(vec!["verify-password", app]);
+        callback_exec(&matches, store, &mut CursorInputOutput::new("", input))
+    }
+
+    #[test]
+    fn test_callback_exec_errors() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        store
+            .add_password(Password::new("Youtube", "me", "abcd"))
+            .unwrap();
+
+        assert_eq!(verify(&mut store, "Youtube", "abcd\n"), Ok(()));
+        assert_eq!(
+            verify(&mut store, "Youtube", "efgh\n"),
+            Err(CommandError::CheckFailed)
+        );
+        assert_eq!(
+            verify(&mut store, "Google", "abcd\n"),
+            Err(CommandError::NotFound)
+        );
+    }
+}
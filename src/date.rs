@@ -0,0 +1,149 @@
+//! Conversions between Unix timestamps and calendar dates (UTC).
+//!
+//! The algorithms come from Howard Hinnant's "chrono-Compatible Low-Level Date Algorithms".
+
+use crate::ffi;
+
+const SECONDS_PER_DAY: i64 = 86400;
+
+/// Number of days between 1970-01-01 and the given date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = (year - era * 400) as u64;
+    let month_from_march = ((month + 9) % 12) as u64;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as u64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era as i64 - 719468
+}
+
+/// The date that is the given number of days after 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = (if days >= 0 { days } else { days - 146096 }) / 146097;
+    let day_of_era = (days - era * 146097) as u64;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era as i64 + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+/// Parses a `YYYY-MM-DD` date into the timestamp of its first second (UTC).
+pub fn parse_date(s: &str) -> Option<ffi::time_t> {
+    let mut parts = s.trim().splitn(3, '-');
+    let year = parts.next()?.parse::<i64>().ok()?;
+    let month = parts.next()?.parse::<u32>().ok()?;
+    let day = parts.next()?.parse::<u32>().ok()?;
+
+    if month < 1 || month > 12 || day < 1 || day > 31 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    // Reject dates like February 30th, which would roll over to the next month.
+    if civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+
+    let timestamp = days * SECONDS_PER_DAY;
+    if timestamp < 0 || timestamp > ffi::time_t::MAX as i64 {
+        return None;
+    }
+
+    Some(timestamp as ffi::time_t)
+}
+
+/// Formats a timestamp as a `YYYY-MM-DD` date (UTC).
+pub fn format_date(timestamp: ffi::time_t) -> String {
+    let (year, month, day) = civil_from_days(timestamp as i64 / SECONDS_PER_DAY);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Formats a timestamp as a `YYYY-MM-DD HH:MM:SS UTC` date and time.
+pub fn format_datetime(timestamp: ffi::time_t) -> String {
+    let seconds_of_day = timestamp as i64 % SECONDS_PER_DAY;
+    format!(
+        "{} {:02}:{:02}:{:02} UTC",
+        format_date(timestamp),
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Formats a timestamp in the ISO 8601 basic format, like `20240229T134500Z`. It has no
+/// colons, so it can be used in file names.
+pub fn format_iso8601_basic(timestamp: ffi::time_t) -> String {
+    let (year, month, day) = civil_from_days(timestamp as i64 / SECONDS_PER_DAY);
+    let seconds_of_day = timestamp as i64 % SECONDS_PER_DAY;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Number of whole days from `from` until `to`, negative if `to` is in the past.
+pub fn days_between(from: ffi::time_t, to: ffi::time_t) -> i64 {
+    (to as i64 - from as i64).div_euclid(SECONDS_PER_DAY)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::date::{
+        days_between, format_date, format_datetime, format_iso8601_basic, parse_date,
+    };
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2000-03-01"), Some(951868800));
+        assert_eq!(parse_date("2024-02-29"), Some(1709164800));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2023-13-01"), None);
+        assert_eq!(parse_date("1969-12-31"), None);
+        assert_eq!(parse_date("not a date"), None);
+        assert_eq!(parse_date("2023-01"), None);
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951868800), "2000-03-01");
+        assert_eq!(format_date(1709164800 + 86399), "2024-02-29");
+    }
+
+    #[test]
+    fn test_format_datetime() {
+        assert_eq!(format_datetime(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(
+            format_datetime(1709164800 + 3723),
+            "2024-02-29 01:02:03 UTC"
+        );
+    }
+
+    #[test]
+    fn test_days_between() {
+        assert_eq!(days_between(0, 86400 * 3), 3);
+        assert_eq!(days_between(0, 86400 * 3 - 1), 2);
+        assert_eq!(days_between(86400 * 3, 0), -3);
+    }
+
+    #[test]
+    fn test_format_iso8601_basic() {
+        assert_eq!(format_iso8601_basic(0), "19700101T000000Z");
+        assert_eq!(format_iso8601_basic(1709164800 + 49500), "20240229T134500Z");
+    }
+}
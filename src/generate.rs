@@ -1,14 +1,26 @@
+use fancy_regex::Regex;
 use rand::{rngs::OsRng, Rng};
 use rclio::CliInputOutput;
 use rclio::OutputType;
 use rtoolbox::safe_string::SafeString;
 use std::io::Result as IoResult;
 
-fn generate_password(alnum: bool, len: usize) -> IoResult<SafeString> {
+/// The length of generated passwords, unless `--length` says otherwise.
+pub const DEFAULT_PASSWORD_LEN: usize = 32;
+
+/// How many passwords to try before giving up on a regex that can't be matched.
+const MAX_REGEX_ATTEMPTS: usize = 100000;
+
+/// Below this many distinct characters, a custom charset makes for weak passwords.
+const MIN_CHARSET_LEN: usize = 10;
+
+fn generate_password(alnum: bool, charset: &Option<Vec<char>>, len: usize) -> IoResult<SafeString> {
     let mut password_as_string = String::new();
     let mut rng = OsRng::default();
     for _ in 0..len {
-        if alnum {
+        if let Some(charset) = charset {
+            password_as_string.push(charset[rng.gen_range(0..charset.len())]);
+        } else if alnum {
             match rng.gen_range(0..3) {
                 // Numbers 0-9
                 0 => password_as_string.push(rng.gen_range(48..58) as u8 as char),
@@ -38,6 +50,8 @@ fn password_is_hard(password: &str, alnum: bool) -> bool {
 
 pub struct PasswordSpec {
     pub alnum: bool,
+    /// Only use these characters, `alnum` is then ignored.
+    pub charset: Option<Vec<char>>,
     pub len: usize,
 }
 
@@ -45,18 +59,39 @@ impl PasswordSpec {
     pub fn new(alnum: bool, password_len: Option<usize>) -> PasswordSpec {
         PasswordSpec {
             alnum,
-            len: password_len.unwrap_or(32),
+            charset: None,
+            len: password_len.unwrap_or(DEFAULT_PASSWORD_LEN),
         }
     }
 
+    pub fn with_charset(self, charset: Option<Vec<char>>) -> PasswordSpec {
+        PasswordSpec { charset, ..self }
+    }
+
+    /// With a custom charset, there are no requirements on character classes, since the
+    /// charset may not have all of them.
     pub fn generate_hard_password(&self) -> IoResult<SafeString> {
         loop {
-            let password = generate_password(self.alnum, self.len)?;
-            if password_is_hard(password.as_ref(), self.alnum) {
+            let password = generate_password(self.alnum, &self.charset, self.len)?;
+            if self.charset.is_some() || password_is_hard(password.as_ref(), self.alnum) {
                 return Ok(password);
             }
         }
     }
+
+    /// Generates passwords until one matches the regex. The regex replaces the usual
+    /// requirements on character classes. Returns `None` if no match was found in a
+    /// reasonable number of attempts, which usually means the regex can't be matched.
+    pub fn generate_password_matching(&self, regex: &Regex) -> IoResult<Option<SafeString>> {
+        for _ in 0..MAX_REGEX_ATTEMPTS {
+            let password = generate_password(self.alnum, &self.charset, self.len)?;
+            // Errors only happen when the regex engine gives up, so treat them like no match.
+            if regex.is_match(password.as_ref()).unwrap_or(false) {
+                return Ok(Some(password));
+            }
+        }
+        Ok(None)
+    }
 }
 
 pub fn check_password_len(len: usize, io: &mut impl CliInputOutput) -> Option<usize> {
@@ -73,9 +108,40 @@ pub fn check_password_len(len: usize, io: &mut impl CliInputOutput) -> Option<us
     }
 }
 
+/// Deduplicates the characters of `--charset`, keeping their order. Warns when there are
+/// few of them, since each character then adds little entropy.
+pub fn check_charset(charset: &str, io: &mut impl CliInputOutput) -> Option<Vec<char>> {
+    let mut chars: Vec<char> = Vec::new();
+    for c in charset.chars() {
+        if !chars.contains(&c) {
+            chars.push(c);
+        }
+    }
+
+    if chars.is_empty() {
+        io.error(
+            "Woops! The charset must have at least one character.",
+            OutputType::Error,
+        );
+        return None;
+    }
+    if chars.len() < MIN_CHARSET_LEN {
+        io.warning(
+            format!(
+                "Heads up, this charset only has {} different characters, so the password \
+                 is easier to guess. Consider making it longer with --length.",
+                chars.len()
+            ),
+            OutputType::Error,
+        );
+    }
+    Some(chars)
+}
+
 #[cfg(test)]
 mod test {
     use crate::generate::PasswordSpec;
+    use fancy_regex::Regex;
     use std::ops::Deref;
 
     #[test]
@@ -120,4 +186,29 @@ mod test {
         }
         assert!(ok);
     }
+
+    #[test]
+    fn test_generate_password_with_charset() {
+        let ps = PasswordSpec::new(true, Some(64)).with_charset(Some(vec!['a', 'b', '!']));
+        let pw = ps.generate_hard_password().unwrap();
+        assert_eq!(pw.len(), 64);
+        assert!(pw.deref().chars().all(|c| c == 'a' || c == 'b' || c == '!'));
+    }
+
+    #[test]
+    fn test_generate_password_matching() {
+        let regex = Regex::new(r"^(?=.*\d)(?=.*[A-Z]).{12,}$").unwrap();
+        let pw = PasswordSpec::new(false, Some(12))
+            .generate_password_matching(&regex)
+            .unwrap()
+            .unwrap();
+        assert!(regex.is_match(pw.deref()).unwrap());
+
+        // Impossible patterns give up instead of looping forever
+        let regex = Regex::new(r"^$").unwrap();
+        assert!(PasswordSpec::new(true, Some(32))
+            .generate_password_matching(&regex)
+            .unwrap()
+            .is_none());
+    }
 }
@@ -0,0 +1,129 @@
+use rtoolbox::safe_string::SafeString;
+use sha1::{Digest, Sha1};
+use std::ops::Deref;
+
+/// Where the k-anonymity range API lives, the 5 characters prefix goes at the end.
+#[cfg(feature = "hibp")]
+const RANGE_API_URL: &'static str = "https://api.pwnedpasswords.com/range/";
+
+/// How many characters of the SHA-1 hash are sent to the API.
+const PREFIX_LEN: usize = 5;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum HibpError {
+    /// The API asked us to slow down.
+    RateLimited,
+    /// The API could not be reached, or answered with an error.
+    Unavailable(String),
+    /// The API answered with something we don't understand.
+    InvalidResponse,
+    /// Rooster was built without the `hibp` feature, so without network access.
+    Disabled,
+}
+
+fn sha1_hex(password: &SafeString) -> SafeString {
+    let hash = Sha1::digest(password.deref().as_bytes());
+    SafeString::from_string(hash.iter().map(|b| format!("{:02X}", b)).collect())
+}
+
+/// How many times the password appears in known breaches, 0 if it doesn't.
+///
+/// Only the first 5 characters of the SHA-1 hash are given to `fetch`, which returns the
+/// matching range as "SUFFIX:COUNT" lines. The comparison with the rest of the hash happens
+/// here, so neither the password nor its full hash ever leave the machine.
+pub fn pwned_count(
+    password: &SafeString,
+    fetch: impl Fn(&str) -> Result<String, HibpError>,
+) -> Result<u64, HibpError> {
+    let hash = sha1_hex(password);
+    let (prefix, suffix) = hash.deref().split_at(PREFIX_LEN);
+
+    let range = fetch(prefix)?;
+    for line in range.lines() {
+        let mut parts = line.trim().splitn(2, ':');
+        let (line_suffix, count) = match (parts.next(), parts.next()) {
+            (Some(line_suffix), Some(count)) => (line_suffix, count),
+            _ => return Err(HibpError::InvalidResponse),
+        };
+        if line_suffix.eq_ignore_ascii_case(suffix) {
+            return count.parse::<u64>().map_err(|_| HibpError::InvalidResponse);
+        }
+    }
+    Ok(0)
+}
+
+/// Fetches a range of hashes from the Have I Been Pwned API. Responses are padded with
+/// fake entries, so that their size doesn't tell which range was requested.
+#[cfg(feature = "hibp")]
+pub fn fetch_range(prefix: &str) -> Result<String, HibpError> {
+    let response = ureq::get(format!("{}{}", RANGE_API_URL, prefix).as_str())
+        .set("Add-Padding", "true")
+        .set("User-Agent", "rooster")
+        .call();
+
+    match response {
+        Ok(response) => response
+            .into_string()
+            .map_err(|err| HibpError::Unavailable(err.to_string())),
+        Err(ureq::Error::Status(429, _)) => Err(HibpError::RateLimited),
+        Err(err) => Err(HibpError::Unavailable(err.to_string())),
+    }
+}
+
+#[cfg(not(feature = "hibp"))]
+pub fn fetch_range(_prefix: &str) -> Result<String, HibpError> {
+    Err(HibpError::Disabled)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{pwned_count, sha1_hex, HibpError};
+    use rtoolbox::safe_string::SafeString;
+    use std::cell::RefCell;
+    use std::ops::Deref;
+
+    fn password(s: &str) -> SafeString {
+        SafeString::from_string(s.to_owned())
+    }
+
+    #[test]
+    fn test_sha1_hex() {
+        assert_eq!(
+            sha1_hex(&password("password")).deref(),
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8"
+        );
+    }
+
+    #[test]
+    fn test_pwned_count() {
+        let requested = RefCell::new(Vec::new());
+        let fetch = |prefix: &str| -> Result<String, HibpError> {
+            requested.borrow_mut().push(prefix.to_owned());
+            Ok("003D68EB55068C33ACE09247EE4C639306B:3\r\n\
+                1E4C9B93F3F0682250B6CF8331B7EE68FD8:3861493\r\n\
+                1E4C9B93F3F0682250B6CF8331B7EE68FD9:0"
+                .to_owned())
+        };
+
+        assert_eq!(pwned_count(&password("password"), &fetch), Ok(3861493));
+        assert_eq!(requested.borrow().as_slice(), &["5BAA6".to_owned()]);
+
+        // Same prefix, different suffix
+        let fetch = |_: &str| -> Result<String, HibpError> {
+            Ok("003D68EB55068C33ACE09247EE4C639306B:3".to_owned())
+        };
+        assert_eq!(pwned_count(&password("password"), fetch), Ok(0));
+    }
+
+    #[test]
+    fn test_pwned_count_errors() {
+        assert_eq!(
+            pwned_count(&password("password"), |_| Err(HibpError::RateLimited)),
+            Err(HibpError::RateLimited)
+        );
+        assert_eq!(
+            pwned_count(&password("password"), |_| Ok("not a range".to_owned())),
+            Err(HibpError::InvalidResponse)
+        );
+    }
+}
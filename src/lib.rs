@@ -12,18 +12,30 @@ use std::io::Read;
 use std::io::Result as IoResult;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 mod aes;
+mod armor;
 mod clip;
 mod commands;
+mod date;
 mod ffi;
 mod generate;
+mod hibp;
 mod list;
-mod password;
+mod lock;
+mod otp;
+/// The password file format and the password store, usable without the command line, see
+/// `password::v2::PasswordStore`.
+pub mod password;
+mod qr;
 #[cfg(unix)]
 mod quale;
-#[cfg(unix)]
-mod shell_escape;
+mod select;
+mod strength;
+
+/// Why a command failed, see `run_with_args`.
+pub use crate::commands::CommandError;
 
 #[cfg(windows)]
 fn example_environment_variable_configuration() -> &'static str {
@@ -35,32 +47,56 @@ fn example_environment_variable_configuration() -> &'static str {
     return "export ROOSTER_FILE=$HOME/path/to/rooster.file"
 }
 
-fn only_digits(s: &str) -> bool {
-    s.chars()
-        .map(|c| char::is_ascii_digit(&c))
-        .collect::<Vec<bool>>()
-        .contains(&false)
+fn is_only_digits(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
 }
 
-fn validate_arg_usize(v: &str) -> Result<usize, String> {
-    if only_digits(v) {
+fn parse_digits<T: std::str::FromStr>(v: &str) -> Result<T, String> {
+    if !is_only_digits(v) {
         return Err(String::from("The value must be made of digits"));
     }
-    Ok(v.parse::<usize>().unwrap())
+    // Only digits, so the only way to fail is to not fit in the integer type.
+    v.parse::<T>()
+        .map_err(|_| String::from("The value is too large for this option"))
+}
+
+fn validate_arg_usize(v: &str) -> Result<usize, String> {
+    parse_digits::<usize>(v)
 }
 
 fn validate_arg_u8(v: &str) -> Result<u8, String> {
-    if only_digits(v) {
-        return Err(String::from("The value must be made of digits"));
-    }
-    Ok(v.parse::<u8>().unwrap())
+    parse_digits::<u8>(v)
 }
 
 fn validate_arg_u32(v: &str) -> Result<u32, String> {
-    if only_digits(v) {
-        return Err(String::from("The value must be made of digits"));
-    }
-    Ok(v.parse::<u32>().unwrap())
+    parse_digits::<u32>(v)
+}
+
+fn validate_arg_u64(v: &str) -> Result<u64, String> {
+    parse_digits::<u64>(v)
+}
+
+/// Shared by the exports that are encrypted unless asked otherwise.
+fn plaintext_arg() -> Arg {
+    Arg::new("plaintext")
+        .action(ArgAction::SetTrue)
+        .long("plaintext")
+        .help("Export in clear text, instead of encrypted with your master password")
+}
+
+/// Shared by the exports that go to stdout unless asked otherwise.
+fn export_output_arg() -> Arg {
+    Arg::new("output")
+        .long("output")
+        .value_name("PATH")
+        .help("Write the export to this file, readable by you only, instead of stdout")
+}
+
+/// Shared by the commands that pick one password, for apps with several accounts.
+fn username_filter_arg() -> Arg {
+    Arg::new("username")
+        .long("username")
+        .help("Only consider the passwords with this username (case-insensitive)")
 }
 
 fn open_password_file(filename: &str) -> IoResult<File> {
@@ -71,39 +107,199 @@ fn open_password_file(filename: &str) -> IoResult<File> {
     options.open(&Path::new(filename))
 }
 
-fn create_password_file(filename: &str) -> IoResult<File> {
-    let mut options = std::fs::OpenOptions::new();
-    options.read(true);
-    options.write(true);
-    options.create(true);
-    options.open(&Path::new(filename))
+/// Warns when other users may read the password file. Rooster saves it readable by its
+/// owner only, so looser permissions come from elsewhere, like a copy or a sync tool.
+#[cfg(unix)]
+fn warn_about_file_permissions(file: &File, path: &str, io: &mut impl CliInputOutput) {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = match file.metadata() {
+        Ok(metadata) => metadata.permissions().mode(),
+        Err(_) => return,
+    };
+    if mode & 0o077 != 0 {
+        io.warning(
+            format!(
+                "Heads up, other users of this computer can read your password file {} (mode \
+                 {:o}). It is private again the next time Rooster saves it, or right away with \
+                 `chmod 600 '{}'`.",
+                path,
+                mode & 0o777,
+                path
+            ),
+            OutputType::Error,
+        );
+    }
+}
+
+#[cfg(not(unix))]
+fn warn_about_file_permissions(_file: &File, _path: &str, _io: &mut impl CliInputOutput) {}
+
+/// Environment variable to read the master password from, see
+/// `read_master_password_non_interactive`.
+const MASTER_PASSWORD_ENV_VAR: &'static str = "ROOSTER_MASTER_PASSWORD";
+
+/// Where the password store is read from and saved to.
+enum PasswordStoreSource {
+    /// The path of the password file, which saving replaces, see `PasswordStore::sync`, and
+    /// the lock that keeps other Rooster instances away from it until we exit.
+    File(PathBuf, lock::FileLock),
+    /// Used by `--memory`: the store starts empty and is discarded on exit. Also used by
+    /// `--dry-run`, which discards the changes made to the store read from the file.
+    Memory,
+}
+
+/// What we check to tell whether the password file was changed by another program, like a
+/// file sync tool replacing it, since we opened it.
+#[derive(PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+const DISK_FULL_ERROR: &'static str =
+    "the disk is full, so I left the password file as it was, without your changes";
+
+const FILE_CHANGED_ERROR: &'static str =
+    "the password file was changed by another program since I opened it, so I won't overwrite it";
+
+impl FileStamp {
+    fn of(path: &Path) -> IoResult<FileStamp> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(FileStamp {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
 }
 
 fn sync_password_store(
     store: &mut PasswordStore,
-    file: &mut File,
+    source: &mut PasswordStoreSource,
     io: &mut impl CliInputOutput,
-) -> Result<(), i32> {
-    if let Err(err) = store.sync(file) {
-        io.error(
-            format!("I could not save the password file (reason: {:?}).", err),
-            OutputType::Error,
-        );
-        return Err(1);
+) -> Result<(), CommandError> {
+    let path = match source {
+        PasswordStoreSource::File(path, _) => path,
+        PasswordStoreSource::Memory => return Ok(()),
+    };
+    // Saving also replaces the backup, which must keep the version from before the last
+    // change, not from before the last `get`.
+    if !store.has_changes() {
+        return Ok(());
+    }
+
+    match store.sync(path) {
+        Ok(()) => Ok(()),
+        Err(password::PasswordError::DiskFullError) => {
+            io.error(
+                format!(
+                    "I could not save the password file (reason: {}).",
+                    DISK_FULL_ERROR
+                ),
+                OutputType::Error,
+            );
+            Err(CommandError::Io)
+        }
+        Err(err) => {
+            io.error(
+                format!("I could not save the password file (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            Err(CommandError::Io)
+        }
     }
+}
 
-    return Ok(());
+fn report_dry_run(io: &mut impl CliInputOutput) {
+    io.info(
+        "This was a dry run, so I didn't save anything to the password file.",
+        OutputType::Error,
+    );
 }
 
 fn get_password_store(
     file: &mut File,
+    password_stdin: bool,
     io: &mut impl CliInputOutput,
-) -> Result<password::v2::PasswordStore, i32> {
+) -> Result<password::v2::PasswordStore, password::PasswordError> {
     // Read the Rooster file contents.
     let mut input: SafeVec = SafeVec::new(Vec::new());
-    file.read_to_end(input.inner_mut()).map_err(|_| 1)?;
+    file.read_to_end(input.inner_mut())?;
+
+    match read_master_password_non_interactive(password_stdin, io) {
+        Some(Ok(master_password)) => {
+            get_password_store_non_interactive(&input, &master_password, io)
+        }
+        Some(Err(err)) => {
+            io.error(
+                format!(
+                    "Woops, I could not read your master password (reason: {}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            Err(password::PasswordError::Io(err))
+        }
+        None => get_password_store_from_input_interactive(&input, 3, false, false, io),
+    }
+}
 
-    return get_password_store_from_input_interactive(&input, 3, false, false, io).map_err(|_| 1);
+/// Points to the copy of the password file from before the last save, for when the file
+/// can't be opened anymore because it was damaged.
+fn suggest_backup(rooster_file_path: &Path, io: &mut impl CliInputOutput) {
+    let backup = password::v2::backup_path(rooster_file_path);
+    if !backup.exists() {
+        return;
+    }
+    io.error(
+        format!(
+            "Heads up, the version of your password file from before the last save is at {}. \
+             Check that it opens with `rooster list --file {}`, then put it in place of {}.",
+            backup.to_string_lossy(),
+            backup.to_string_lossy(),
+            rooster_file_path.to_string_lossy()
+        ),
+        OutputType::Error,
+    );
+}
+
+/// Opens the store with a master password that wasn't typed in, so a wrong password fails
+/// right away instead of asking again, and upgrades from v1 are left to interactive use.
+fn get_password_store_non_interactive(
+    input: &SafeVec,
+    master_password: &SafeString,
+    io: &mut impl CliInputOutput,
+) -> Result<password::v2::PasswordStore, password::PasswordError> {
+    get_password_store_from_input(input, master_password, false).map_err(|err| {
+        match err {
+            password::PasswordError::CorruptionError
+            | password::PasswordError::InvalidScryptParamsError => {
+                io.error("Your Rooster file is corrupted.", OutputType::Error);
+            }
+            password::PasswordError::OutdatedRoosterBinaryError => {
+                io.error(
+                    "I could not open the Rooster file because your version of Rooster is outdated.",
+                    OutputType::Error,
+                );
+            }
+            password::PasswordError::NeedUpgradeErrorFromV1 => {
+                io.error(
+                    "Your Rooster file has version 1. Run Rooster interactively to upgrade it.",
+                    OutputType::Error,
+                );
+            }
+            password::PasswordError::Io(ref err) => {
+                io.error(
+                    format!("I couldn't open your Rooster file (reason: {:?})", err),
+                    OutputType::Error,
+                );
+            }
+            _ => {
+                io.error("Woops, that's not the right password.", OutputType::Error);
+            }
+        }
+        err
+    })
 }
 
 fn get_password_store_from_input_interactive(
@@ -147,7 +343,9 @@ fn get_password_store_from_input_interactive(
         Ok(store) => {
             return Ok(store);
         }
-        Err(password::PasswordError::CorruptionError) => {
+        // The scrypt parameters come from the file header, so it is damaged.
+        Err(password::PasswordError::CorruptionError)
+        | Err(password::PasswordError::InvalidScryptParamsError) => {
             io.error("Your Rooster file is corrupted.", OutputType::Error);
             return Err(password::PasswordError::CorruptionError);
         }
@@ -249,11 +447,161 @@ fn ask_master_password(io: &mut impl CliInputOutput) -> IoResult<SafeString> {
     io.prompt_password("Type your master password: ")
 }
 
-pub fn main_with_args(
+/// Reads the master password without prompting, for scripts and cron jobs. Returns `None`
+/// when the master password should be typed in as usual.
+///
+/// With `--password-stdin`, the first line of stdin is the master password. Otherwise, the
+/// `ROOSTER_MASTER_PASSWORD` environment variable is used if set. Both are convenient but
+/// weaker than typing the password: environment variables can be read by other processes
+/// running as the same user (`/proc/<pid>/environ`) and tend to leak into shell history,
+/// crontabs and logs. Prefer `--password-stdin`, fed from a secret manager or a file only
+/// you can read.
+pub(crate) fn read_master_password_non_interactive(
+    password_stdin: bool,
+    io: &mut impl CliInputOutput,
+) -> Option<IoResult<SafeString>> {
+    if password_stdin {
+        return Some(io.read_line().map(|line| {
+            let line = SafeString::from_string(line);
+            SafeString::from_string(line.trim_end_matches(&['\r', '\n'][..]).to_owned())
+        }));
+    }
+
+    env::var(MASTER_PASSWORD_ENV_VAR)
+        .ok()
+        .map(|master_password| Ok(SafeString::from_string(master_password)))
+}
+
+fn open_file_password_store(
+    rooster_file_path: &PathBuf,
+    password_stdin: bool,
+    io: &mut impl CliInputOutput,
+) -> Result<(PasswordStore, PasswordStoreSource), CommandError> {
+    let password_file_path_as_string = rooster_file_path.to_string_lossy().into_owned();
+
+    if !rooster_file_path.exists() {
+        io.error(
+            format!(
+                "Woops, there is no password file at {}.",
+                password_file_path_as_string
+            ),
+            OutputType::Error,
+        );
+        io.nl(OutputType::Error);
+        io.title("First time user", OutputType::Error);
+        io.nl(OutputType::Error);
+        io.info("Try `rooster init`.", OutputType::Error);
+        io.nl(OutputType::Error);
+        io.title("Long time user", OutputType::Error);
+        io.nl(OutputType::Error);
+        io.info(
+            "Set the ROOSTER_FILE environment variable, or use --file. For instance:",
+            OutputType::Error,
+        );
+        io.info(
+            format!("    {}", example_environment_variable_configuration()),
+            OutputType::Error,
+        );
+        return Err(CommandError::NotFound);
+    }
+
+    let lock = match lock::try_lock(rooster_file_path) {
+        Ok(Some(lock)) => lock,
+        Ok(None) => {
+            io.error(
+                format!(
+                    "Woops, another Rooster instance is using this file ({}). Try again once \
+                     it is done.",
+                    password_file_path_as_string
+                ),
+                OutputType::Error,
+            );
+            return Err(CommandError::Unavailable);
+        }
+        Err(err) => {
+            io.error(
+                format!(
+                    "Woops, I couldn't lock your password file ({} for \"{}\").",
+                    err,
+                    lock::lock_path(rooster_file_path).to_string_lossy()
+                ),
+                OutputType::Error,
+            );
+            return Err(CommandError::Io);
+        }
+    };
+
+    let mut file = match open_password_file(password_file_path_as_string.deref()) {
+        Ok(file) => file,
+        Err(err) => {
+            return Err(match err.kind() {
+                std::io::ErrorKind::NotFound => {
+                    io.error(
+                        "Woops, I can't find your password file. Run `rooster init` to create one.",
+                        OutputType::Error,
+                    );
+                    CommandError::NotFound
+                }
+                _ => {
+                    io.error(
+                        format!(
+                            "Woops, I couldn't read your password file ({} for \"{}\").",
+                            err, password_file_path_as_string
+                        ),
+                        OutputType::Error,
+                    );
+                    CommandError::Io
+                }
+            });
+        }
+    };
+
+    warn_about_file_permissions(&file, password_file_path_as_string.deref(), io);
+
+    let store = get_password_store(&mut file, password_stdin, io).map_err(|err| match err {
+        password::PasswordError::CorruptionError
+        | password::PasswordError::CorruptionLikelyError
+        | password::PasswordError::InvalidScryptParamsError => {
+            suggest_backup(rooster_file_path, io);
+            CommandError::Store
+        }
+        password::PasswordError::DecryptionError => CommandError::Decryption,
+        password::PasswordError::NoUpgradeError => CommandError::UserAbort,
+        password::PasswordError::Io(_) => CommandError::Io,
+        _ => CommandError::Store,
+    })?;
+
+    Ok((
+        store,
+        PasswordStoreSource::File(rooster_file_path.clone(), lock),
+    ))
+}
+
+fn open_memory_password_store(
+    io: &mut impl CliInputOutput,
+) -> Result<(PasswordStore, PasswordStoreSource), CommandError> {
+    // Nothing is ever written, so there is no need for a master password.
+    match PasswordStore::new(SafeString::from_string(String::new())) {
+        Ok(store) => Ok((store, PasswordStoreSource::Memory)),
+        Err(err) => {
+            io.error(
+                format!(
+                    "Woops, I couldn't create the password store (reason: {:?}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            Err(CommandError::Store)
+        }
+    }
+}
+
+/// Runs Rooster like `main_with_args`, and tells why it failed instead of an exit code.
+pub fn run_with_args(
     args: &[&str],
     io: &mut impl CliInputOutput,
     rooster_file_path: &PathBuf,
-) -> i32 {
+) -> Result<(), CommandError> {
     let matches = Command::new("rooster")
         .help_expected(true)
         .disable_help_subcommand(true)
@@ -261,6 +609,44 @@ pub fn main_with_args(
         .arg_required_else_help(true)
         .about("Welcome to Rooster, a simple password manager")
         .version(env!("CARGO_PKG_VERSION"))
+        .arg(
+            Arg::new("memory")
+                .action(ArgAction::SetTrue)
+                .long("memory")
+                .global(true)
+                .help("Use an empty in-memory password store that is discarded on exit"),
+        )
+        .arg(
+            Arg::new("password-stdin")
+                .action(ArgAction::SetTrue)
+                .long("password-stdin")
+                .global(true)
+                .help("Read the master password from the first line of stdin, for scripts"),
+        )
+        .arg(
+            // Not "file", which `attach add` uses for the file to attach: clap copies the
+            // values of global args up to the top-level matches by id.
+            Arg::new("rooster-file")
+                .long("file")
+                .value_name("PATH")
+                .global(true)
+                .help("Use this password file instead of $ROOSTER_FILE or ~/.passwords.rooster"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .action(ArgAction::SetTrue)
+                .long("dry-run")
+                .global(true)
+                .help("Show what would change, without saving, writing files or touching the clipboard"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .global(true)
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .help("Output format of get, list and search, json is meant for scripts"),
+        )
         .subcommand(
             Command::new("init")
                 .about("Create a new password file")
@@ -270,6 +656,18 @@ pub fn main_with_args(
                         .long("force-for-tests")
                         .hide(true)
                         .help("Forces initializing the file, used in integration tests only"),
+                )
+                .arg(
+                    Arg::new("non-interactive")
+                        .action(ArgAction::SetTrue)
+                        .long("non-interactive")
+                        .help("Skip the setup wizard and use the default settings, for scripts"),
+                )
+                .arg(
+                    Arg::new("allow-empty")
+                        .action(ArgAction::SetTrue)
+                        .long("allow-empty")
+                        .help("Accept an empty master password, which protects nothing, for tests only"),
                 ),
         )
         .subcommand(
@@ -277,20 +675,65 @@ pub fn main_with_args(
                 .about("Add a new password manually")
                 .arg(
                     Arg::new("app")
-                        .required(true)
+                        .required_unless_present("batch")
                         .help("The name of the app (fuzzy-matched)"),
                 )
                 .arg(
                     Arg::new("username")
-                        .required(true)
+                        .required_unless_present("batch")
                         .help("Your username for this account"),
                 )
+                .arg(
+                    Arg::new("batch")
+                        .action(ArgAction::SetTrue)
+                        .long("batch")
+                        .conflicts_with_all(["app", "username", "show", "otp", "clear-after"])
+                        .help("Add one password per \"app<TAB>username<TAB>password\" line of stdin, after the master password"),
+                )
                 .arg(
                     Arg::new("show")
                         .action(ArgAction::SetTrue)
                         .short('s')
                         .long("show")
                         .help("Show the password instead of copying it to the clipboard"),
+                )
+                .arg(
+                    Arg::new("note")
+                        .long("note")
+                        .help("A note to save along with the password"),
+                )
+                .arg(
+                    Arg::new("tag")
+                        .long("tag")
+                        .action(ArgAction::Append)
+                        .help("A tag to group the password with, can be repeated"),
+                )
+                .arg(
+                    Arg::new("otp")
+                        .long("otp")
+                        .value_name("SECRET")
+                        .help("A TOTP secret (base32) to generate one-time passwords with"),
+                )
+                .arg(
+                    Arg::new("expire-days")
+                        .long("expire-days")
+                        .value_name("DAYS")
+                        .help("Remind to rotate the password this many days after it was last changed")
+                        .value_parser(validate_arg_u32),
+                )
+                .arg(
+                    Arg::new("clear-after")
+                        .long("clear-after")
+                        .value_name("SECONDS")
+                        .help("Restore the previous clipboard contents after this many seconds")
+                        .value_parser(validate_arg_u64),
+                )
+                .arg(
+                    Arg::new("notify")
+                        .action(ArgAction::SetTrue)
+                        .long("notify")
+                        .requires("clear-after")
+                        .help("Ring the terminal bell, or show a desktop notification, when the clipboard is restored"),
                 ),
         )
         .subcommand(
@@ -307,14 +750,51 @@ pub fn main_with_args(
                         .short('s')
                         .long("show")
                         .help("Show the password instead of copying it to the clipboard"),
-                ),
+                )
+                .arg(
+                    Arg::new("note")
+                        .long("note")
+                        .help("Change the note instead of the password"),
+                )
+                .arg(username_filter_arg()),
         )
         .subcommand(
-            Command::new("delete").about("Delete a password").arg(
-                Arg::new("app")
-                    .required(true)
-                    .help("The name of the app (fuzzy-matched)"),
-            ),
+            Command::new("delete")
+                .about("Delete a password")
+                .arg(
+                    Arg::new("app")
+                        .required_unless_present_any(select::SELECTOR_IDS)
+                        .conflicts_with_all(select::SELECTOR_IDS)
+                        .help("The name of the app (fuzzy-matched)"),
+                )
+                .args(select::selector_args())
+                .arg(
+                    Arg::new("yes")
+                        .action(ArgAction::SetTrue)
+                        .short('y')
+                        .long("yes")
+                        .help("Don't ask for confirmation"),
+                )
+                .arg(username_filter_arg().conflicts_with_all(select::SELECTOR_IDS)),
+        )
+        .subcommand(
+            Command::new("trash")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("See and restore deleted passwords")
+                .subcommand(
+                    Command::new("list")
+                        .about("List deleted passwords and when they will be purged"),
+                )
+                .subcommand(
+                    Command::new("restore")
+                        .about("Restore a deleted password")
+                        .arg(
+                            Arg::new("app")
+                                .required(true)
+                                .help("The name of the app (case-insensitive)"),
+                        ),
+                ),
         )
         .subcommand(
             Command::new("generate")
@@ -336,6 +816,20 @@ pub fn main_with_args(
                         .long("show")
                         .help("Show the password instead of copying it to the clipboard"),
                 )
+                .arg(
+                    Arg::new("clear-after")
+                        .long("clear-after")
+                        .value_name("SECONDS")
+                        .help("Restore the previous clipboard contents after this many seconds")
+                        .value_parser(validate_arg_u64),
+                )
+                .arg(
+                    Arg::new("notify")
+                        .action(ArgAction::SetTrue)
+                        .long("notify")
+                        .requires("clear-after")
+                        .help("Ring the terminal bell, or show a desktop notification, when the clipboard is restored"),
+                )
                 .arg(
                     Arg::new("alnum")
                         .action(ArgAction::SetTrue)
@@ -343,6 +837,18 @@ pub fn main_with_args(
                         .long("alnum")
                         .help("Only use alpha numeric (a-z, A-Z, 0-9) in generated passwords"),
                 )
+                .arg(
+                    Arg::new("charset")
+                        .long("charset")
+                        .value_name("CHARACTERS")
+                        .help("Only use these characters in the generated password, instead of the usual ones or --alnum"),
+                )
+                .arg(
+                    Arg::new("match-regex")
+                        .long("match-regex")
+                        .value_name("REGEX")
+                        .help("Keep generating until the password matches this regex, instead of requiring all character classes"),
+                )
                 .arg(
                     Arg::new("length")
                         .short('l')
@@ -357,9 +863,18 @@ pub fn main_with_args(
                 .about("Regenerate a previously existing password")
                 .arg(
                     Arg::new("app")
-                        .required(true)
+                        .required_unless_present_any(select::SELECTOR_IDS)
+                        .conflicts_with_all(select::SELECTOR_IDS)
                         .help("The name of the app (fuzzy-matched)"),
                 )
+                .args(select::selector_args())
+                .arg(
+                    Arg::new("yes")
+                        .action(ArgAction::SetTrue)
+                        .short('y')
+                        .long("yes")
+                        .help("Don't ask for confirmation when regenerating several passwords"),
+                )
                 .arg(
                     Arg::new("show")
                         .action(ArgAction::SetTrue)
@@ -367,6 +882,20 @@ pub fn main_with_args(
                         .long("show")
                         .help("Show the password instead of copying it to the clipboard"),
                 )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .value_parser(["csv"])
+                        .conflicts_with("show")
+                        .help("Print the new passwords as app,username,password rows, e.g. for a spreadsheet"),
+                )
+                .arg(
+                    Arg::new("no-header")
+                        .action(ArgAction::SetTrue)
+                        .long("no-header")
+                        .requires("output")
+                        .help("Leave out the header row of --output csv"),
+                )
                 .arg(
                     Arg::new("alnum")
                         .action(ArgAction::SetTrue)
@@ -391,12 +920,54 @@ pub fn main_with_args(
                         .required(true)
                         .help("The name of the app (fuzzy-matched)"),
                 )
+                .arg(username_filter_arg())
                 .arg(
                     Arg::new("show")
                         .action(ArgAction::SetTrue)
                         .short('s')
                         .long("show")
                         .help("Show the password instead of copying it to the clipboard"),
+                )
+                .arg(
+                    Arg::new("copy-username")
+                        .action(ArgAction::SetTrue)
+                        .short('u')
+                        .long("copy-username")
+                        .help("Copy the username instead of the password, or show it with --show"),
+                )
+                .arg(
+                    Arg::new("verbose")
+                        .action(ArgAction::SetTrue)
+                        .long("verbose")
+                        .help("Say which clipboard backend was used"),
+                )
+                .arg(
+                    Arg::new("totp")
+                        .action(ArgAction::SetTrue)
+                        .long("totp")
+                        .conflicts_with("copy-username")
+                        .help("Also get the TOTP code, copied once you have pasted the password"),
+                )
+                .arg(
+                    Arg::new("show-qr")
+                        .action(ArgAction::SetTrue)
+                        .long("show-qr")
+                        .conflicts_with_all(["show", "copy-username", "clear-after"])
+                        .help("Show the password as a QR code for your phone, or the TOTP secret with --totp"),
+                )
+                .arg(
+                    Arg::new("clear-after")
+                        .long("clear-after")
+                        .value_name("SECONDS")
+                        .help("Restore the previous clipboard contents after this many seconds")
+                        .value_parser(validate_arg_u64),
+                )
+                .arg(
+                    Arg::new("notify")
+                        .action(ArgAction::SetTrue)
+                        .long("notify")
+                        .requires("clear-after")
+                        .help("Ring the terminal bell, or show a desktop notification, when the clipboard is restored"),
                 ),
         )
         .subcommand(
@@ -427,12 +998,426 @@ pub fn main_with_args(
                         .help("Your new username for this account"),
                 ),
         )
-        .subcommand(Command::new("list").about("List all apps and usernames"))
         .subcommand(
-            Command::new("import")
-                .subcommand_required(true)
-                .arg_required_else_help(true)
-                .about("Import all your existing passwords from elsewhere")
+            Command::new("otp")
+                .about("Show the current one-time password (TOTP) for an app")
+                .args_conflicts_with_subcommands(true)
+                .subcommand_negates_reqs(true)
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                )
+                .subcommand(
+                    Command::new("set")
+                        .about("Save the TOTP secret for an app")
+                        .arg(
+                            Arg::new("app")
+                                .required(true)
+                                .help("The name of the app (fuzzy-matched)"),
+                        )
+                        .arg(
+                            Arg::new("secret")
+                                .required(true)
+                                .help("The TOTP secret, encoded in base32"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("uri")
+                        .about("Print the otpauth:// URI of the TOTP secret, for authenticator apps")
+                        .arg(
+                            Arg::new("app")
+                                .required(true)
+                                .help("The name of the app (fuzzy-matched)"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("note")
+                .about("Show or change the note saved with a password")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("set")
+                        .long("set")
+                        .value_name("NOTE")
+                        .help("Replace the note with this text"),
+                ),
+        )
+        .subcommand(
+            Command::new("codes")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("Save and use one-time backup codes")
+                .subcommand(
+                    Command::new("set")
+                        .about("Replace the backup codes, read one per line")
+                        .arg(
+                            Arg::new("app")
+                                .required(true)
+                                .help("The name of the app (fuzzy-matched)"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("use")
+                        .about("Show the next unused backup code and mark it as used")
+                        .arg(
+                            Arg::new("app")
+                                .required(true)
+                                .help("The name of the app (fuzzy-matched)"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("attach")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("Keep small files, like key files or recovery sheets, with a password")
+                .subcommand(
+                    Command::new("add")
+                        .about("Attach a file of at most 64 KiB")
+                        .arg(
+                            Arg::new("app")
+                                .required(true)
+                                .help("The name of the app (fuzzy-matched)"),
+                        )
+                        .arg(
+                            Arg::new("file")
+                                .required(true)
+                                .help("The path to the file to attach"),
+                        )
+                        .arg(
+                            Arg::new("name")
+                                .long("name")
+                                .help("The name of the attachment (default: the file name)"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("get")
+                        .about("Write an attachment to a new file")
+                        .arg(
+                            Arg::new("app")
+                                .required(true)
+                                .help("The name of the app (fuzzy-matched)"),
+                        )
+                        .arg(
+                            Arg::new("name")
+                                .required(true)
+                                .help("The name of the attachment (case-insensitive)"),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .required(true)
+                                .help("Where to write the attachment, which must not exist yet"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("List the attachments of a password")
+                        .arg(
+                            Arg::new("app")
+                                .required(true)
+                                .help("The name of the app (fuzzy-matched)"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove an attachment")
+                        .arg(
+                            Arg::new("app")
+                                .required(true)
+                                .help("The name of the app (fuzzy-matched)"),
+                        )
+                        .arg(
+                            Arg::new("name")
+                                .required(true)
+                                .help("The name of the attachment (case-insensitive)"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("Show the previous values of a password")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                ),
+        )
+        .subcommand(
+            Command::new("expire")
+                .about("Set the date after which a password is hidden from get and list")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("date")
+                        .required(true)
+                        .help("The expiry date, as YYYY-MM-DD, or \"never\" to remove it"),
+                ),
+        )
+        .subcommand(
+            Command::new("audit")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("Check your passwords for problems")
+                .subcommand(
+                    Command::new("expired")
+                        .about("List expired passwords and the ones due for rotation"),
+                )
+                .subcommand(
+                    Command::new("duplicates")
+                        .about("List passwords used for more than one app, exits with 1 if any"),
+                )
+                .subcommand(
+                    Command::new("empty")
+                        .about("List passwords that are empty or placeholders, exits with 1 if any"),
+                )
+                .subcommand(
+                    Command::new("pwned")
+                        .about("Check your passwords against Have I Been Pwned, exits with 1 if any was breached"),
+                ),
+        )
+        .subcommand(
+            Command::new("protect")
+                .about("Never copy a password to the clipboard, only show it")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("off")
+                        .action(ArgAction::SetTrue)
+                        .long("off")
+                        .help("Allow copying the password to the clipboard again"),
+                ),
+        )
+        .subcommand(
+            Command::new("undo")
+                .about("Revert the last change to your passwords, from the backup next to the password file")
+                .arg(
+                    Arg::new("yes")
+                        .action(ArgAction::SetTrue)
+                        .short('y')
+                        .long("yes")
+                        .help("Don't ask for confirmation"),
+                ),
+        )
+        .subcommand(
+            Command::new("touch")
+                .about("Mark a password as up to date, e.g. after changing it elsewhere, without changing it")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                ),
+        )
+        .subcommand(
+            Command::new("icon")
+                .about("Show an emoji before an app's name in `rooster list`")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("icon")
+                        .required_unless_present("clear")
+                        .help("The emoji, like 🔑"),
+                )
+                .arg(
+                    Arg::new("clear")
+                        .action(ArgAction::SetTrue)
+                        .long("clear")
+                        .conflicts_with("icon")
+                        .help("Remove the icon"),
+                ),
+        )
+        .subcommand(
+            Command::new("tag")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("Manage tags across all passwords")
+                .subcommand(
+                    Command::new("rename")
+                        .about("Rename a tag on every password that has it")
+                        .arg(
+                            Arg::new("old")
+                                .required(true)
+                                .help("The current name of the tag (case-insensitive)"),
+                        )
+                        .arg(
+                            Arg::new("new")
+                                .required(true)
+                                .help("The new name of the tag"),
+                        )
+                        .args(select::selector_args()),
+                ),
+        )
+        .subcommand(
+            Command::new("fingerprint")
+                .about("Print a hash of your passwords, to check that two password files match")
+                .after_help("Use --file to get the fingerprint of another password file, like a backup."),
+        )
+        .subcommand(
+            Command::new("info")
+                .about("Print the format version and scrypt parameters of your password file")
+                .after_help(
+                    "These are readable without your master password. With --unlock, Rooster \
+                     then asks for it and also counts your passwords.",
+                )
+                .arg(
+                    Arg::new("unlock")
+                        .long("unlock")
+                        .action(ArgAction::SetTrue)
+                        .help("Also count the passwords, which needs your master password"),
+                ),
+        )
+        .subcommand(
+            Command::new("rpc")
+                .about("Answer JSON-RPC requests on stdin, one per line, until an empty line"),
+        )
+        .subcommand(
+            Command::new("strength")
+                .about("Estimate the strength of a password")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                ),
+        )
+        .subcommand(
+            Command::new("verify-password")
+                .about("Check a password read from stdin against the stored one, without showing it")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The exact name of the app"),
+                ),
+        )
+        .subcommand(
+            Command::new("search")
+                .about("Search for apps by name")
+                .arg(
+                    Arg::new("query")
+                        .required_unless_present("regex")
+                        .help("The text to search for (fuzzy-matched against app names)"),
+                )
+                .arg(
+                    Arg::new("exact")
+                        .action(ArgAction::SetTrue)
+                        .long("exact")
+                        .help("Search for the exact text in app names and usernames, ignoring case"),
+                )
+                .arg(
+                    Arg::new("regex")
+                        .long("regex")
+                        .value_name("PATTERN")
+                        .conflicts_with_all(["query", "exact"])
+                        .help("Search for the apps whose name matches this regular expression (add (?i) to ignore case)"),
+                )
+                .arg(
+                    Arg::new("field")
+                        .long("field")
+                        .value_parser(["app", "username"])
+                        .requires("regex")
+                        .help("Match --regex against app names (the default) or usernames"),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .value_name("N")
+                        .default_value("0")
+                        .value_parser(validate_arg_usize)
+                        .help("Only show the first N passwords (0 means no limit)"),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List all apps and usernames")
+                .arg(
+                    Arg::new("tag")
+                        .long("tag")
+                        .help("Only list the passwords with this tag (case-insensitive)"),
+                )
+                .arg(
+                    Arg::new("regex")
+                        .long("regex")
+                        .value_name("PATTERN")
+                        .help("Only list the apps whose name matches this regular expression"),
+                )
+                .arg(
+                    Arg::new("field")
+                        .long("field")
+                        .value_parser(["app", "username"])
+                        .requires("regex")
+                        .help("Match --regex against app names (the default) or usernames"),
+                )
+                .arg(
+                    Arg::new("show-all")
+                        .action(ArgAction::SetTrue)
+                        .long("show-all")
+                        .help("Show every field of every password, including the password itself"),
+                )
+                .arg(
+                    Arg::new("long")
+                        .action(ArgAction::SetTrue)
+                        .long("long")
+                        .help("Also show when each password was created and last updated, and which ones have a note"),
+                )
+                .arg(
+                    Arg::new("sort")
+                        .long("sort")
+                        .value_parser(["app", "username", "updated"])
+                        .help("Sort by app name (the default), username, or last update, most recent first"),
+                )
+                .arg(
+                    Arg::new("count")
+                        .action(ArgAction::SetTrue)
+                        .long("count")
+                        .conflicts_with_all(["show-all", "long", "plain"])
+                        .help("Only print how many passwords there are"),
+                )
+                .arg(
+                    Arg::new("plain")
+                        .action(ArgAction::SetTrue)
+                        .long("plain")
+                        .conflicts_with_all(["show-all", "long"])
+                        .help("Print one \"app<TAB>username\" line per password, for scripts"),
+                )
+                .arg(
+                    Arg::new("no-icons")
+                        .action(ArgAction::SetTrue)
+                        .long("no-icons")
+                        .help("Don't show icons, for terminals that can't display emoji"),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .value_name("N")
+                        .default_value("0")
+                        .value_parser(validate_arg_usize)
+                        .help("Only show the first N passwords (0 means no limit)"),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("Import all your existing passwords from elsewhere")
+                .arg(
+                    Arg::new("on-conflict")
+                        .long("on-conflict")
+                        .global(true)
+                        .value_parser(["skip", "update", "ask"])
+                        .help("What to do with apps that are already in the password file (default: skip, or ask for KeePass)"),
+                )
                 .subcommand(
                     Command::new("json")
                         .about("Import a file generated with `rooster export json`")
@@ -445,6 +1430,7 @@ pub fn main_with_args(
                 .subcommand(
                     Command::new("csv")
                         .about("Import a file generated with `rooster export csv`")
+                        .after_help("Columns are app, username, password and, optionally, notes.")
                         .arg(
                             Arg::new("path")
                                 .required(true)
@@ -459,6 +1445,42 @@ pub fn main_with_args(
                                 .required(true)
                                 .help("The path to the file you want to import"),
                         ),
+                )
+                .subcommand(
+                    Command::new("bitwarden")
+                        .about("Import an unencrypted JSON export from Bitwarden")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the file you want to import"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("keepass")
+                        .about("Import a CSV export from KeePass or KeePassXC")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the file you want to import"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("encrypted")
+                        .about("Import a file generated with `rooster export encrypted`, or an export without --plaintext")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the file you want to import"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("pass")
+                        .about("Import a pass store, decrypting each entry with gpg")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the store, usually ~/.password-store"),
+                        ),
                 ),
         )
         .subcommand(
@@ -466,35 +1488,136 @@ pub fn main_with_args(
                 .subcommand_required(true)
                 .arg_required_else_help(true)
                 .about("Export raw password data")
-                .subcommand(Command::new("json").about("Export raw password data in JSON format"))
-                .subcommand(Command::new("csv").about("Export raw password data in CSV format"))
+                .after_help(
+                    "Unless --plaintext is given, the json, csv and 1password exports are \
+                     encrypted with your master password. Import them back with \
+                     `rooster import encrypted`.",
+                )
+                .subcommand(
+                    Command::new("json")
+                        .about("Export raw password data in JSON format")
+                        .arg(plaintext_arg())
+                        .arg(export_output_arg())
+                        .args(select::selector_args()),
+                )
+                .subcommand(
+                    Command::new("csv")
+                        .about("Export raw password data in CSV format")
+                        .arg(plaintext_arg())
+                        .arg(export_output_arg())
+                        .args(select::selector_args()),
+                )
                 .subcommand(
                     Command::new("1password")
-                        .about("Export raw password data in 1Password compatible CSV format"),
+                        .about("Export raw password data in 1Password compatible CSV format")
+                        .arg(plaintext_arg())
+                        .arg(export_output_arg())
+                        .args(select::selector_args()),
+                )
+                .subcommand(
+                    Command::new("encrypted")
+                        .about("Export all passwords, encrypted with your master password")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The file to write the encrypted export to"),
+                        )
+                        .args(select::selector_args()),
+                )
+                .subcommand(
+                    Command::new("markdown")
+                        .about("Export all passwords as a Markdown document, for a printed copy")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The file to write the Markdown document to"),
+                        )
+                        .arg(
+                            Arg::new("no-secrets")
+                                .action(ArgAction::SetTrue)
+                                .long("no-secrets")
+                                .help("Leave out passwords and notes"),
+                        )
+                        .args(select::selector_args()),
+                ),
+        )
+        .subcommand(
+            Command::new("set-master-password")
+                .visible_alias("change-master-password")
+                .about("Set your master password")
+                .after_help(
+                    "The password file is replaced at once, and only after the new file was \
+                     read back with the new master password, so it never needs both.",
+                )
+                .arg(
+                    Arg::new("allow-empty")
+                        .action(ArgAction::SetTrue)
+                        .long("allow-empty")
+                        .help("Accept an empty master password, which protects nothing, for tests only"),
+                ),
+        )
+        .subcommand(
+            Command::new("config")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .about("Inspect Rooster's settings")
+                .subcommand(
+                    Command::new("show")
+                        .about("Show the settings in effect and where each value comes from"),
+                ),
+        )
+        .subcommand(
+            Command::new("backup")
+                .about("Copy your Rooster file to a timestamped backup file")
+                .arg(
+                    Arg::new("dir")
+                        .help("The directory to put the backup in (default: next to your Rooster file)"),
+                ),
+        )
+        .subcommand(
+            Command::new("clear-clipboard")
+                .hide(true)
+                .about("Restores the clipboard after a delay, used internally by --clear-after")
+                .arg(
+                    Arg::new("seconds")
+                        .required(true)
+                        .help("How long to wait before restoring the clipboard")
+                        .value_parser(validate_arg_u64),
                 ),
         )
-        .subcommand(Command::new("set-master-password").about("Set your master password"))
         .subcommand(
             Command::new("set-scrypt-params")
                 .about("Set the key derivation parameters")
+                .after_help(
+                    "The allowed range can be pinned with the ROOSTER_SCRYPT_MIN_LOG2N, \
+                     ROOSTER_SCRYPT_MAX_LOG2N, ROOSTER_SCRYPT_MIN_R, ROOSTER_SCRYPT_MAX_R, \
+                     ROOSTER_SCRYPT_MIN_P and ROOSTER_SCRYPT_MAX_P environment variables.",
+                )
                 .arg(
                     Arg::new("log2n")
-                        .required(true)
+                        .required_unless_present("show")
                         .help("The log2n parameter")
                         .value_parser(validate_arg_u8),
                 )
                 .arg(
                     Arg::new("r")
-                        .required(true)
+                        .required_unless_present("show")
                         .help("The r parameter")
                         .value_parser(validate_arg_u32),
                 )
                 .arg(
                     Arg::new("p")
-                        .required(true)
+                        .required_unless_present("show")
                         .help("The p parameter")
                         .value_parser(validate_arg_u32),
                 )
+                .arg(
+                    Arg::new("show")
+                        .action(ArgAction::SetTrue)
+                        .long("show")
+                        .conflicts_with_all(["log2n", "r", "p", "force"])
+                        .help("Show the current parameters instead of setting them"),
+                )
                 .arg(
                     Arg::new("force")
                         .action(ArgAction::SetTrue)
@@ -505,90 +1628,249 @@ pub fn main_with_args(
         )
         .get_matches_from(args);
 
+    // `--file` wins over ROOSTER_FILE and the default location, for this invocation only.
+    let file_override = matches.get_one::<String>("rooster-file").map(PathBuf::from);
+    let rooster_file_path = file_override.as_ref().unwrap_or(rooster_file_path);
+
     let subcommand = matches.subcommand_name().unwrap();
 
     let command_matches = matches.subcommand_matches(subcommand).unwrap();
 
-    if subcommand == "init" {
-        match commands::init::callback_exec(command_matches, io, rooster_file_path) {
-            Err(i) => return i,
-            _ => return 0,
+    if subcommand == "clear-clipboard" {
+        let timeout_seconds = *command_matches.get_one::<u64>("seconds").unwrap();
+        return clip::restore_clipboard_after(timeout_seconds, io);
+    }
+
+    if subcommand == "config" {
+        return commands::config::callback_exec(command_matches, io, rooster_file_path);
+    }
+
+    if subcommand == "backup" {
+        if matches.get_flag("memory") {
+            io.error(
+                "With --memory, there is no password file to back up.",
+                OutputType::Error,
+            );
+            return Err(CommandError::InvalidInput);
         }
+        return commands::backup::callback_exec(command_matches, io, rooster_file_path);
     }
 
-    let password_file_path_as_string = rooster_file_path.to_string_lossy().into_owned();
+    if subcommand == "init" {
+        if matches.get_flag("memory") {
+            io.error(
+                "With --memory, there is no password file to create.",
+                OutputType::Error,
+            );
+            return Err(CommandError::InvalidInput);
+        }
+        return commands::init::callback_exec(command_matches, io, rooster_file_path);
+    }
 
-    if !rooster_file_path.exists() {
-        io.title("First time user", OutputType::Standard);
-        io.nl(OutputType::Standard);
-        io.info("Try `rooster init`.", OutputType::Standard);
-        io.nl(OutputType::Standard);
-        io.title("Long time user", OutputType::Standard);
-        io.nl(OutputType::Standard);
-        io.info(
-            "Set the ROOSTER_FILE environment variable. For instance:",
-            OutputType::Standard,
-        );
-        io.info(
-            format!("    {}", example_environment_variable_configuration()),
-            OutputType::Standard,
+    if subcommand == "undo" && matches.get_flag("memory") {
+        io.error(
+            "With --memory, there is no password file to undo changes to.",
+            OutputType::Error,
         );
-        return 1;
+        return Err(CommandError::InvalidInput);
     }
 
-    let mut file = match open_password_file(password_file_path_as_string.deref()) {
-        Ok(file) => file,
+    if subcommand == "info" {
+        if matches.get_flag("memory") {
+            io.error(
+                "With --memory, there is no password file to describe.",
+                OutputType::Error,
+            );
+            return Err(CommandError::InvalidInput);
+        }
+        commands::info::print_file_format(io, rooster_file_path)?;
+        if !command_matches.get_flag("unlock") {
+            return Ok(());
+        }
+    }
+
+    let opened = if matches.get_flag("memory") {
+        open_memory_password_store(io)
+    } else {
+        open_file_password_store(rooster_file_path, matches.get_flag("password-stdin"), io)
+    };
+    let (mut store, mut source) = opened?;
+
+    let dry_run = matches.get_flag("dry-run");
+    // `fingerprint` and `info` change nothing, so checking a backup with them leaves the backup
+    // as it was.
+    if dry_run || subcommand == "fingerprint" || subcommand == "info" {
+        source = PasswordStoreSource::Memory;
+    }
+
+    // Deleted passwords are only kept for a while.
+    match commands::trash::retention_days() {
+        Ok(retention_days) => {
+            store.purge_trash(ffi::time(), retention_days);
+        }
         Err(err) => {
-            match err.kind() {
-                std::io::ErrorKind::NotFound => {
-                    io.error(
-                        "Woops, I can't find your password file. Run `rooster init` to create one.",
-                        OutputType::Error,
-                    );
-                }
-                _ => {
-                    io.error(
-                        format!(
-                            "Woops, I couldn't read your password file ({} for \"{}\").",
-                            err, password_file_path_as_string
-                        ),
-                        OutputType::Error,
-                    );
-                }
+            io.error(err, OutputType::Error);
+            return Err(CommandError::InvalidInput);
+        }
+    }
+
+    // The RPC session can save several times before it ends, so it gets a way to save. It
+    // can also stay open for long, so it checks that nobody else changed the file meanwhile.
+    if subcommand == "rpc" {
+        let mut stamp = FileStamp::of(rooster_file_path).ok();
+        let mut save = |store: &mut PasswordStore| -> Result<(), String> {
+            let path = match source {
+                PasswordStoreSource::File(ref path, _) => path,
+                PasswordStoreSource::Memory => return Ok(()),
+            };
+            if !store.has_changes() {
+                return Ok(());
             }
-            return 1;
+            if FileStamp::of(path).ok() != stamp {
+                return Err(FILE_CHANGED_ERROR.to_owned());
+            }
+            store.sync(path).map_err(|err| match err {
+                password::PasswordError::DiskFullError => DISK_FULL_ERROR.to_owned(),
+                err => format!("{:?}", err),
+            })?;
+            stamp = FileStamp::of(rooster_file_path).ok();
+            Ok(())
+        };
+        commands::rpc::callback_exec(command_matches, &mut store, &mut save, io)?;
+        if let Err(err) = save(&mut store) {
+            io.error(
+                format!("I could not save the password file (reason: {}).", err),
+                OutputType::Error,
+            );
+            return Err(CommandError::Io);
         }
-    };
+        if dry_run {
+            report_dry_run(io);
+        }
+        return Ok(());
+    }
 
-    let mut store = match get_password_store(&mut file, io) {
-        Err(code) => return code,
-        Ok(store) => store,
+    // Partial changes are saved too, before the error is returned. Undo reads the backup
+    // next to the password file, so it gets its path.
+    let outcome = if subcommand == "undo" {
+        commands::undo::callback_exec(command_matches, &mut store, rooster_file_path, io)
+    } else {
+        let callback = match subcommand {
+            "get" => commands::get::callback_exec,
+            "add" => commands::add::callback_exec,
+            "delete" => commands::delete::callback_exec,
+            "generate" => commands::generate::callback_exec,
+            "regenerate" => commands::regenerate::callback_exec,
+            "list" => commands::list::callback_exec,
+            "search" => commands::search::callback_exec,
+            "import" => commands::import::callback_exec,
+            "export" => commands::export::callback_exec,
+            "fingerprint" => commands::fingerprint::callback_exec,
+            "info" => commands::info::callback_exec,
+            "set-master-password" => commands::set_master_password::callback_exec,
+            "set-scrypt-params" => commands::set_scrypt_params::callback_exec,
+            "rename" => commands::rename::callback_exec,
+            "transfer" => commands::transfer::callback_exec,
+            "change" => commands::change::callback_exec,
+            "strength" => commands::strength::callback_exec,
+            "verify-password" => commands::verify_password::callback_exec,
+            "otp" => commands::otp::callback_exec,
+            "note" => commands::note::callback_exec,
+            "codes" => commands::codes::callback_exec,
+            "attach" => commands::attach::callback_exec,
+            "expire" => commands::expire::callback_exec,
+            "protect" => commands::protect::callback_exec,
+            "icon" => commands::icon::callback_exec,
+            "touch" => commands::touch::callback_exec,
+            "history" => commands::history::callback_exec,
+            "trash" => commands::trash::callback_exec,
+            "tag" => commands::tag::callback_exec,
+            "audit" => commands::audit::callback_exec,
+            _ => unreachable!("Validation should have been done by `clap` before"),
+        };
+        callback(command_matches, &mut store, io)
     };
+    if let Err(err) = outcome {
+        if err != CommandError::Partial {
+            return Err(err);
+        }
+    }
+
+    sync_password_store(&mut store, &mut source, io)?;
+
+    if dry_run {
+        report_dry_run(io);
+    }
+
+    return outcome;
+}
 
-    let callback = match subcommand {
-        "get" => commands::get::callback_exec,
-        "add" => commands::add::callback_exec,
-        "delete" => commands::delete::callback_exec,
-        "generate" => commands::generate::callback_exec,
-        "regenerate" => commands::regenerate::callback_exec,
-        "list" => commands::list::callback_exec,
-        "import" => commands::import::callback_exec,
-        "export" => commands::export::callback_exec,
-        "set-master-password" => commands::set_master_password::callback_exec,
-        "set-scrypt-params" => commands::set_scrypt_params::callback_exec,
-        "rename" => commands::rename::callback_exec,
-        "transfer" => commands::transfer::callback_exec,
-        "change" => commands::change::callback_exec,
-        _ => unreachable!("Validation should have been done by `clap` before"),
+/// Runs Rooster with the command line arguments in `args`, and returns the exit code: 0 on
+/// success, and `CommandError::exit_code` on errors.
+pub fn main_with_args(
+    args: &[&str],
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &PathBuf,
+) -> i32 {
+    match run_with_args(args, io, rooster_file_path) {
+        Ok(()) => 0,
+        Err(err) => err.exit_code(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        is_only_digits, validate_arg_u32, validate_arg_u64, validate_arg_u8, validate_arg_usize,
+        FileStamp,
     };
+    use std::io::Write;
+
+    #[test]
+    fn test_file_stamp_changes_with_the_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"before").unwrap();
+        let stamp = FileStamp::of(file.path()).unwrap();
+        assert!(FileStamp::of(file.path()).unwrap() == stamp);
 
-    if let Err(code) = callback(command_matches, &mut store, io) {
-        return code;
+        file.write_all(b" and after").unwrap();
+        assert!(FileStamp::of(file.path()).unwrap() != stamp);
     }
 
-    if let Err(code) = sync_password_store(&mut store, &mut file, io) {
-        return code;
+    #[test]
+    fn test_is_only_digits() {
+        assert!(is_only_digits("32"));
+        assert!(is_only_digits("007"));
+        assert!(!is_only_digits(""));
+        assert!(!is_only_digits("abc"));
+        assert!(!is_only_digits("3a"));
+        assert!(!is_only_digits("-1"));
+        assert!(!is_only_digits(" 1"));
     }
 
-    return 0;
+    #[test]
+    fn test_validate_arg_accepts_digits() {
+        assert_eq!(validate_arg_usize("32"), Ok(32));
+        assert_eq!(validate_arg_usize("007"), Ok(7));
+        assert_eq!(validate_arg_u8("255"), Ok(255));
+        assert_eq!(validate_arg_u32("0"), Ok(0));
+        assert_eq!(validate_arg_u64("30"), Ok(30));
+    }
+
+    #[test]
+    fn test_validate_arg_rejects_non_digits() {
+        assert!(validate_arg_usize("").is_err());
+        assert!(validate_arg_usize("abc").is_err());
+        assert!(validate_arg_u8("1.5").is_err());
+        assert!(validate_arg_u32("+1").is_err());
+        assert!(validate_arg_u64("-1").is_err());
+    }
+
+    #[test]
+    fn test_validate_arg_rejects_overflow() {
+        assert!(validate_arg_u8("256").is_err());
+        assert!(validate_arg_u32("4294967296").is_err());
+        assert!(validate_arg_u64("18446744073709551616").is_err());
+        assert!(validate_arg_usize("99999999999999999999999999").is_err());
+    }
 }
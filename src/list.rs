@@ -1,5 +1,9 @@
-use crate::password::v2::{Password, PasswordStore};
+use crate::date;
+use crate::ffi;
+use crate::password::v2::{Password, PasswordStore, RotationStatus};
 use rclio::{CliInputOutput, OutputType};
+use serde::Serialize;
+use std::ops::Deref;
 
 /// Used to indicate lists should have a number, ie: 23 Google my.account@gmail.com
 pub const WITH_NUMBERS: bool = true;
@@ -26,13 +30,16 @@ fn get_list_of_passwords(passwords: &Vec<&Password>, with_numbers: bool) -> Vec<
         }
     });
 
+    // Only show the tags column when there is something to show
+    let with_tags = passwords.iter().any(|p| !p.tags.is_empty());
+
     // Find the number column length
     let i_width = ((passwords.len() as f64).log10() + 1 as f64).floor() as usize;
 
     let mut list = Vec::new();
 
     for (i, p) in passwords.iter().enumerate() {
-        let s = match with_numbers {
+        let mut s = match with_numbers {
             WITH_NUMBERS => format!(
                 "{:i_width$} {:app_name_width$} {:username_width$}",
                 i + 1,
@@ -51,12 +58,268 @@ fn get_list_of_passwords(passwords: &Vec<&Password>, with_numbers: bool) -> Vec<
             ),
         };
 
+        if with_tags {
+            s.push_str(" ");
+            s.push_str(p.tags.join(", ").as_str());
+        }
+
         list.push(s);
     }
 
     list
 }
 
+/// Formats a creation or update date, which is unknown for very old entries.
+pub fn format_timestamp(timestamp: ffi::time_t) -> String {
+    if timestamp == 0 {
+        "unknown".to_owned()
+    } else {
+        date::format_datetime(timestamp)
+    }
+}
+
+/// Like `get_list_of_passwords`, with when each password was created and last updated, and
+/// which ones have a note.
+fn get_long_list_of_passwords(passwords: &Vec<&Password>) -> Vec<String> {
+    let longest_app_name = passwords.iter().map(|p| p.name.len()).max().unwrap_or(0);
+    let longest_username = passwords
+        .iter()
+        .map(|p| p.username.len())
+        .max()
+        .unwrap_or(0);
+    let with_notes = passwords.iter().any(|p| !p.notes.is_empty());
+    let with_tags = passwords.iter().any(|p| !p.tags.is_empty());
+
+    passwords
+        .iter()
+        .map(|p| {
+            let mut s = format!(
+                "{:app_name_width$} {:username_width$} created {:23} updated {:23}",
+                p.name,
+                p.username,
+                format_timestamp(p.created_at),
+                format_timestamp(p.updated_at),
+                app_name_width = longest_app_name,
+                username_width = longest_username,
+            );
+            if with_notes {
+                s.push_str(if p.notes.is_empty() { "     " } else { " note" });
+            }
+            if with_tags {
+                s.push_str(" ");
+                s.push_str(p.tags.join(", ").as_str());
+            }
+            s.trim_end().to_owned()
+        })
+        .collect()
+}
+
+/// Puts each password's icon in front of its line. Icons are usually emoji, two columns
+/// wide, so passwords without one get two spaces. Nothing changes when there are no icons.
+fn prefix_icons(list: Vec<String>, passwords: &Vec<&Password>, with_icons: bool) -> Vec<String> {
+    if !with_icons || passwords.iter().all(|p| p.icon.is_none()) {
+        return list;
+    }
+
+    list.into_iter()
+        .zip(passwords.iter())
+        .map(|(s, p)| format!("{} {}", p.icon.as_deref().unwrap_or("  "), s))
+        .collect()
+}
+
+pub fn print_long_list_of_passwords(
+    passwords: &Vec<&Password>,
+    with_icons: bool,
+    io: &mut impl CliInputOutput,
+) {
+    for s in prefix_icons(get_long_list_of_passwords(passwords), passwords, with_icons) {
+        io.info(s, OutputType::Standard);
+    }
+}
+
+/// Every field of a password, one per line, for `list --show-all`.
+fn get_details_of_password(p: &Password, now: ffi::time_t) -> Vec<String> {
+    let mut details = vec![
+        format!("App:      {}", p.name),
+        format!("Username: {}", p.username),
+        format!("Password: {}", p.password.deref()),
+    ];
+
+    if !p.tags.is_empty() {
+        details.push(format!("Tags:     {}", p.tags.join(", ")));
+    }
+    if let Some(ref icon) = p.icon {
+        details.push(format!("Icon:     {}", icon));
+    }
+    if !p.notes.is_empty() {
+        details.push(format!("Note:     {}", p.notes.deref()));
+    }
+    if let Some(ref otp_secret) = p.otp_secret {
+        details.push(format!("TOTP:     {}", otp_secret.deref()));
+    }
+    if p.no_clipboard {
+        details.push("Copy:     never (protected)".to_owned());
+    }
+    if let Some(expires_at) = p.expires_at {
+        details.push(format!(
+            "Expires:  {}{}",
+            date::format_date(expires_at),
+            if p.is_expired(now) { " (expired)" } else { "" }
+        ));
+    }
+    if let Some(due_at) = p.rotation_due_at() {
+        details.push(format!(
+            "Rotate:   {}{}",
+            date::format_date(due_at),
+            match rotation_marker(p, now) {
+                Some(RotationStatus::Overdue) => " (now)",
+                Some(RotationStatus::DueSoon) => " (soon)",
+                _ => "",
+            }
+        ));
+    }
+    details.push(format!("Created:  {}", format_timestamp(p.created_at)));
+    details.push(format!("Updated:  {}", format_timestamp(p.updated_at)));
+
+    details
+}
+
+pub fn print_details_of_passwords(
+    passwords: &Vec<&Password>,
+    now: ffi::time_t,
+    io: &mut impl CliInputOutput,
+) {
+    for (i, p) in passwords.iter().enumerate() {
+        if i > 0 {
+            io.nl(OutputType::Standard);
+        }
+        for s in get_details_of_password(p, now) {
+            io.info(s, OutputType::Standard);
+        }
+    }
+}
+
+/// Whether the user asked for machine-readable output with `--format json`.
+pub fn wants_json(matches: &clap::ArgMatches) -> bool {
+    match matches.try_get_one::<String>("format") {
+        Ok(Some(format)) => format == "json",
+        _ => false,
+    }
+}
+
+/// Keeps only the first `--limit` passwords. A limit of 0 means no limit.
+pub fn apply_limit<T>(
+    passwords: &mut Vec<T>,
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+) {
+    let limit = *matches.get_one::<usize>("limit").unwrap();
+    if limit > 0 && passwords.len() > limit {
+        io.info(
+            format!("Showing {} of {} passwords.", limit, passwords.len()),
+            OutputType::Error,
+        );
+        passwords.truncate(limit);
+    }
+}
+
+/// Orders passwords for `list --sort`. Passwords come sorted by app name, and the sorts are
+/// stable, so ties stay in that order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    App,
+    Username,
+    /// Most recently updated first.
+    Updated,
+}
+
+impl SortKey {
+    pub fn from_name(name: &str) -> Option<SortKey> {
+        match name {
+            "app" => Some(SortKey::App),
+            "username" => Some(SortKey::Username),
+            "updated" => Some(SortKey::Updated),
+            _ => None,
+        }
+    }
+}
+
+/// Sorts the passwords, ignoring case.
+pub fn sort_passwords(passwords: &mut Vec<&Password>, key: SortKey) {
+    match key {
+        SortKey::App => passwords.sort_by_key(|p| p.name.to_lowercase()),
+        SortKey::Username => passwords.sort_by_key(|p| p.username.to_lowercase()),
+        SortKey::Updated => passwords.sort_by_key(|p| std::cmp::Reverse(p.updated_at)),
+    }
+}
+
+/// Applies `--sort`, if given.
+pub fn apply_sort(passwords: &mut Vec<&Password>, matches: &clap::ArgMatches) {
+    if let Some(key) = matches
+        .get_one::<String>("sort")
+        .and_then(|name| SortKey::from_name(name))
+    {
+        sort_passwords(passwords, key);
+    }
+}
+
+/// A password in `--format json` lists, without any secret.
+#[derive(Serialize)]
+struct PasswordSummary<'a> {
+    app: &'a str,
+    username: &'a str,
+}
+
+fn get_json_list_of_passwords(passwords: &Vec<&Password>) -> String {
+    let summaries: Vec<PasswordSummary> = passwords
+        .iter()
+        .map(|p| PasswordSummary {
+            app: p.name.as_str(),
+            username: p.username.as_str(),
+        })
+        .collect();
+
+    // This should never fail. The structs are all encodable.
+    serde_json::to_string(&summaries).unwrap()
+}
+
+pub fn print_list_of_passwords_as_json(passwords: &Vec<&Password>, io: &mut impl CliInputOutput) {
+    io.write(
+        format!("{}\n", get_json_list_of_passwords(passwords)),
+        OutputType::Standard,
+    );
+}
+
+/// Every field of every password, in the same format as `rooster export json`.
+pub fn print_details_of_passwords_as_json(
+    passwords: &Vec<&Password>,
+    io: &mut impl CliInputOutput,
+) {
+    io.write(
+        format!("{}\n", serde_json::to_string(passwords).unwrap()),
+        OutputType::Standard,
+    );
+}
+
+/// One `app<TAB>username` line per password, for scripts. No padding, icons or markers.
+fn get_plain_list_of_passwords(passwords: &Vec<&Password>) -> Vec<String> {
+    passwords
+        .iter()
+        .map(|p| format!("{}\t{}", p.name, p.username))
+        .collect()
+}
+
+pub fn print_plain_list_of_passwords(passwords: &Vec<&Password>, io: &mut impl CliInputOutput) {
+    for s in get_plain_list_of_passwords(passwords) {
+        io.write(format!("{}\n", s), OutputType::Standard);
+    }
+}
+
+/// Keeps the passwords that have the given tag, ignoring case.
+pub fn filter_by_tag<'a>(passwords: Vec<&'a Password>, tag: &str) -> Vec<&'a Password> {
+    passwords.into_iter().filter(|p| p.has_tag(tag)).collect()
+}
+
 pub fn print_list_of_passwords(
     passwords: &Vec<&Password>,
     with_numbers: bool,
@@ -69,6 +332,42 @@ pub fn print_list_of_passwords(
     }
 }
 
+/// What `list` appends to a password that is due for rotation.
+fn rotation_marker(p: &Password, now: ffi::time_t) -> Option<RotationStatus> {
+    match p.rotation_status(now) {
+        Some(RotationStatus::UpToDate) | None => None,
+        status => status,
+    }
+}
+
+/// Like `print_list_of_passwords`, with a colored marker on passwords that should be rotated.
+pub fn print_list_of_passwords_with_rotation(
+    passwords: &Vec<&Password>,
+    now: ffi::time_t,
+    with_icons: bool,
+    io: &mut impl CliInputOutput,
+) {
+    let list = prefix_icons(
+        get_list_of_passwords(passwords, WITHOUT_NUMBERS),
+        passwords,
+        with_icons,
+    );
+
+    for (s, p) in list.into_iter().zip(passwords.iter()) {
+        match rotation_marker(p, now) {
+            Some(RotationStatus::Overdue) => io.error(
+                format!("{} (rotate now)", s.trim_end()),
+                OutputType::Standard,
+            ),
+            Some(RotationStatus::DueSoon) => io.warning(
+                format!("{} (rotate soon)", s.trim_end()),
+                OutputType::Standard,
+            ),
+            _ => io.info(s, OutputType::Standard),
+        }
+    }
+}
+
 fn request_password_index_from_stdin(
     passwords: &Vec<&Password>,
     prompt: &str,
@@ -79,15 +378,15 @@ fn request_password_index_from_stdin(
     // Read the index from the command line and convert to a number
     loop {
         if passwords.len() > 1 {
-            io.info(prompt, OutputType::Standard);
+            io.info(prompt, OutputType::Error);
             io.write(
                 format!("Type a number from 1 to {}: ", passwords.len()),
-                OutputType::Standard,
+                OutputType::Error,
             );
         } else if passwords.len() == 1 {
             io.write(
                 "If this is the password you mean, type \"1\" and hit ENTER: ",
-                OutputType::Standard,
+                OutputType::Error,
             );
         }
 
@@ -101,7 +400,7 @@ fn request_password_index_from_stdin(
                                     "I need a number between 1 and {}. Let's try again:",
                                     passwords.len()
                                 ),
-                                OutputType::Standard,
+                                OutputType::Error,
                             );
                             continue;
                         }
@@ -110,7 +409,7 @@ fn request_password_index_from_stdin(
                     }
                     Err(err) => {
                         io.write(
-                            format!("This isn't a valid number (reason: {}). Let's try again (1 to {}): ", err, passwords.len()), OutputType::Standard,
+                            format!("This isn't a valid number (reason: {}). Let's try again (1 to {}): ", err, passwords.len()), OutputType::Error,
                         );
                         continue;
                     }
@@ -123,7 +422,7 @@ fn request_password_index_from_stdin(
                         err,
                         passwords.len()
                     ),
-                    OutputType::Standard,
+                    OutputType::Error,
                 );
             }
         }
@@ -136,8 +435,11 @@ fn choose_password_in_list(
     prompt: &str,
     io: &mut impl CliInputOutput,
 ) -> usize {
-    print_list_of_passwords(passwords, with_numbers, io);
-    io.nl(OutputType::Standard);
+    // The list is only there to help choose, keep it out of stdout.
+    for s in get_list_of_passwords(passwords, with_numbers) {
+        io.info(s, OutputType::Error);
+    }
+    io.nl(OutputType::Error);
     request_password_index_from_stdin(passwords, prompt, io)
 }
 
@@ -148,12 +450,34 @@ pub fn search_and_choose_password<'a>(
     prompt: &str,
     io: &mut impl CliInputOutput,
 ) -> Option<&'a Password> {
-    let passwords = store.search_passwords(query);
+    search_and_choose_password_for_account(store, query, None, with_numbers, prompt, io)
+}
+
+/// Like `search_and_choose_password`, but only among the passwords with the given username,
+/// if any. This lets `--username` pick one account when an app has several.
+pub fn search_and_choose_password_for_account<'a>(
+    store: &'a PasswordStore,
+    query: &str,
+    username: Option<&str>,
+    with_numbers: bool,
+    prompt: &str,
+    io: &mut impl CliInputOutput,
+) -> Option<&'a Password> {
+    let passwords = store.search_passwords_for_account(query, username);
     if passwords.len() == 0 {
-        io.error(
-            format!("Woops, I can't find any passwords for \"{}\".", query),
-            OutputType::Error,
-        );
+        match username {
+            Some(username) => io.error(
+                format!(
+                    "Woops, I can't find any passwords for \"{}\" with username \"{}\".",
+                    query, username
+                ),
+                OutputType::Error,
+            ),
+            None => io.error(
+                format!("Woops, I can't find any passwords for \"{}\".", query),
+                OutputType::Error,
+            ),
+        }
         return None;
     }
 
@@ -170,7 +494,11 @@ pub fn search_and_choose_password<'a>(
 
 #[cfg(test)]
 mod test {
-    use super::get_list_of_passwords;
+    use super::{
+        filter_by_tag, format_timestamp, get_details_of_password, get_json_list_of_passwords,
+        get_list_of_passwords, get_long_list_of_passwords, get_plain_list_of_passwords,
+        prefix_icons, sort_passwords, SortKey,
+    };
     use crate::list::{WITHOUT_NUMBERS, WITH_NUMBERS};
     use crate::password::v2::Password;
     use rtoolbox::safe_string::SafeString;
@@ -248,4 +576,188 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn password_list_shows_icons_when_there_are_some() {
+        let mut passwords = get_passwords(0);
+        let list = get_list_of_passwords(&passwords.iter().collect(), WITHOUT_NUMBERS);
+        assert_eq!(
+            prefix_icons(list.clone(), &passwords.iter().collect(), true),
+            list
+        );
+
+        passwords[1].icon = Some("🔑".to_owned());
+        let list = get_list_of_passwords(&passwords.iter().collect(), WITHOUT_NUMBERS);
+        assert_eq!(
+            prefix_icons(list.clone(), &passwords.iter().collect(), true),
+            &[
+                "   youtube.com that long username",
+                "🔑 google      short un          ",
+            ]
+        );
+        assert_eq!(
+            prefix_icons(list.clone(), &passwords.iter().collect(), false),
+            list
+        );
+    }
+
+    #[test]
+    fn password_list_shows_tags_when_there_are_some() {
+        let mut passwords = get_passwords(0);
+        passwords[1].tags = vec![format!("work"), format!("email")];
+        let list = get_list_of_passwords(&passwords.iter().collect(), WITHOUT_NUMBERS);
+
+        assert_eq!(
+            list,
+            &[
+                "youtube.com that long username ",
+                "google      short un           work, email",
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_by_tag_ignores_case() {
+        let mut passwords = get_passwords(0);
+        passwords[1].tags = vec![format!("Work")];
+
+        let filtered = filter_by_tag(passwords.iter().collect(), "wORK");
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].name, "google");
+
+        assert!(filter_by_tag(passwords.iter().collect(), "home").is_empty());
+    }
+
+    #[test]
+    fn password_details_include_every_field() {
+        let mut password = Password::new("google", "short un", "xxxx");
+        password.created_at = 0;
+        password.updated_at = 86400;
+
+        assert_eq!(
+            get_details_of_password(&password, 0),
+            &[
+                "App:      google",
+                "Username: short un",
+                "Password: xxxx",
+                "Created:  unknown",
+                "Updated:  1970-01-02 00:00:00 UTC",
+            ]
+        );
+
+        password.tags = vec![format!("work")];
+        password.notes = SafeString::from_string(format!("a note"));
+        password.expires_at = Some(86400);
+        password.expires_after_days = Some(1);
+
+        assert_eq!(
+            get_details_of_password(&password, 86400),
+            &[
+                "App:      google",
+                "Username: short un",
+                "Password: xxxx",
+                "Tags:     work",
+                "Note:     a note",
+                "Expires:  1970-01-02 (expired)",
+                "Rotate:   1970-01-03 (soon)",
+                "Created:  unknown",
+                "Updated:  1970-01-02 00:00:00 UTC",
+            ]
+        );
+    }
+
+    #[test]
+    fn password_list_as_json_has_no_secrets() {
+        let passwords = get_passwords(0);
+        assert_eq!(
+            get_json_list_of_passwords(&passwords.iter().collect()),
+            "[{\"app\":\"youtube.com\",\"username\":\"that long username\"},\
+             {\"app\":\"google\",\"username\":\"short un\"}]"
+        );
+    }
+
+    #[test]
+    fn plain_password_list_has_tabs_only() {
+        let mut passwords = get_passwords(0);
+        passwords[0].tags = vec!["work".to_owned()];
+        passwords[0].icon = Some("🎬".to_owned());
+        assert_eq!(
+            get_plain_list_of_passwords(&passwords.iter().collect()),
+            &["youtube.com\tthat long username", "google\tshort un"]
+        );
+    }
+
+    #[test]
+    fn long_password_list_has_dates() {
+        let mut passwords = get_passwords(0);
+        passwords[0].created_at = 0;
+        passwords[0].updated_at = 86400;
+        passwords[1].created_at = 86400;
+        passwords[1].updated_at = 86400 + 3600;
+        let list = get_long_list_of_passwords(&passwords.iter().collect());
+
+        assert_eq!(
+            list,
+            &[
+                "youtube.com that long username created unknown                 updated 1970-01-02 00:00:00 UTC",
+                "google      short un           created 1970-01-02 00:00:00 UTC updated 1970-01-02 01:00:00 UTC",
+            ]
+        );
+    }
+
+    #[test]
+    fn long_password_list_marks_notes() {
+        let mut passwords = get_passwords(0);
+        passwords[0].created_at = 86400;
+        passwords[0].updated_at = 86400;
+        passwords[1].created_at = 86400;
+        passwords[1].updated_at = 86400;
+        passwords[1].notes = SafeString::from_string("a note".to_owned());
+        passwords[0].tags = vec!["work".to_owned()];
+        let list = get_long_list_of_passwords(&passwords.iter().collect());
+
+        assert_eq!(
+            list,
+            &[
+                "youtube.com that long username created 1970-01-02 00:00:00 UTC updated 1970-01-02 00:00:00 UTC      work",
+                "google      short un           created 1970-01-02 00:00:00 UTC updated 1970-01-02 00:00:00 UTC note",
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_passwords_ignores_case_and_is_stable() {
+        let mut passwords = get_passwords(0);
+        passwords[0].name = "Zoom".to_owned();
+        passwords[0].updated_at = 10;
+        passwords[1].updated_at = 20;
+        let mut third = passwords[1].clone();
+        third.name = "amazon".to_owned();
+        third.username = "Short un".to_owned();
+        third.updated_at = 10;
+        passwords.push(third);
+        let names = |passwords: &Vec<&Password>| -> Vec<String> {
+            passwords.iter().map(|p| p.name.clone()).collect()
+        };
+
+        let mut sorted: Vec<&Password> = passwords.iter().collect();
+        sort_passwords(&mut sorted, SortKey::App);
+        assert_eq!(names(&sorted), vec!["amazon", "google", "Zoom"]);
+
+        // "short un" and "Short un" are equal, so they stay sorted by app
+        sort_passwords(&mut sorted, SortKey::Username);
+        assert_eq!(names(&sorted), vec!["amazon", "google", "Zoom"]);
+
+        sort_passwords(&mut sorted, SortKey::Updated);
+        assert_eq!(names(&sorted), vec!["google", "amazon", "Zoom"]);
+
+        assert_eq!(SortKey::from_name("updated"), Some(SortKey::Updated));
+        assert_eq!(SortKey::from_name("size"), None);
+    }
+
+    #[test]
+    fn unknown_timestamps_are_shown_as_such() {
+        assert_eq!(format_timestamp(0), "unknown");
+        assert_eq!(format_timestamp(86400), "1970-01-02 00:00:00 UTC");
+    }
 }
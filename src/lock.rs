@@ -0,0 +1,94 @@
+use std::fs::{File, OpenOptions};
+use std::io::Result as IoResult;
+use std::path::{Path, PathBuf};
+
+/// Held while Rooster uses a password file, so that two instances never save over each other.
+/// The lock is released when this is dropped, or when the process exits.
+pub struct FileLock {
+    _file: File,
+}
+
+/// Saving replaces the password file with a new one, see `PasswordStore::sync`, so locking the
+/// password file itself would not protect the new one. Instead, the lock is on this file next
+/// to it, which stays in place.
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut lock = path.as_os_str().to_owned();
+    lock.push(".lock");
+    PathBuf::from(lock)
+}
+
+/// Takes the advisory lock for the password file at `path`, without waiting. Returns `None`
+/// when another Rooster instance holds it.
+pub fn try_lock(path: &Path) -> IoResult<Option<FileLock>> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(lock_path(path))?;
+    if lock_file(&file)? {
+        Ok(Some(FileLock { _file: file }))
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(unix)]
+fn lock_file(file: &File) -> IoResult<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(windows)]
+fn lock_file(file: &File) -> IoResult<bool> {
+    use std::os::windows::io::AsRawHandle;
+    use windows::Win32::Foundation::{ERROR_LOCK_VIOLATION, HANDLE};
+    use windows::Win32::Storage::FileSystem::{
+        LockFileEx, LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY,
+    };
+    use windows::Win32::System::IO::OVERLAPPED;
+
+    let mut overlapped = OVERLAPPED::default();
+    let locked = unsafe {
+        LockFileEx(
+            HANDLE(file.as_raw_handle() as isize),
+            LOCKFILE_EXCLUSIVE_LOCK | LOCKFILE_FAIL_IMMEDIATELY,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    };
+    match locked {
+        Ok(()) => Ok(true),
+        Err(err) if err.code() == ERROR_LOCK_VIOLATION.to_hresult() => Ok(false),
+        Err(_) => Err(std::io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{lock_path, try_lock};
+
+    #[test]
+    fn test_try_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rooster.file");
+
+        let lock = try_lock(&path).unwrap();
+        assert!(lock.is_some());
+        assert!(lock_path(&path).exists());
+        assert!(try_lock(&path).unwrap().is_none());
+
+        drop(lock);
+        assert!(try_lock(&path).unwrap().is_some());
+    }
+}
@@ -0,0 +1,213 @@
+use crate::ffi;
+use hmac::{Hmac, Mac};
+use rtoolbox::safe_string::SafeString;
+use rtoolbox::safe_vec::SafeVec;
+use sha1::Sha1;
+use std::ops::Deref;
+
+type HmacSha1 = Hmac<Sha1>;
+
+/// Most services use 30 seconds long time steps.
+pub const DEFAULT_PERIOD: u64 = 30;
+
+/// Most services use 6 digits codes.
+pub const DEFAULT_DIGITS: u32 = 6;
+
+/// Decodes a base32 string (RFC 4648), ignoring casing, spaces, dashes and padding.
+fn decode_base32(s: &str) -> Option<SafeVec> {
+    let mut bytes = SafeVec::new(Vec::new());
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in s.chars() {
+        let value = match c.to_ascii_uppercase() {
+            c @ 'A'..='Z' => c as u32 - 'A' as u32,
+            c @ '2'..='7' => c as u32 - '2' as u32 + 26,
+            ' ' | '-' | '=' => continue,
+            _ => return None,
+        };
+
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.inner_mut().push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    if bytes.len() == 0 {
+        return None;
+    }
+
+    Some(bytes)
+}
+
+/// Encodes bytes in base32 (RFC 4648), uppercase and without padding like authenticator
+/// apps expect.
+fn encode_base32(bytes: &[u8]) -> SafeString {
+    const ALPHABET: &'static [u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut s = String::with_capacity((bytes.len() * 8 + 4) / 5);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            s.push(ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        s.push(ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+    SafeString::from_string(s)
+}
+
+/// Escapes everything but the unreserved characters of URIs (RFC 3986).
+fn percent_encode(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                escaped.push(byte as char)
+            }
+            _ => escaped.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    escaped
+}
+
+/// The `otpauth://totp/` URI that authenticator apps import, in the "Key Uri Format" of
+/// Google Authenticator. The issuer is the app, the account is the username. The secret is
+/// encoded again, without the spaces and dashes people copy along with it.
+///
+/// Returns `None` if the secret isn't valid base32.
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &SafeString) -> Option<SafeString> {
+    let secret = encode_base32(decode_base32(secret.deref())?.deref());
+    let label = if account.is_empty() {
+        percent_encode(issuer)
+    } else {
+        format!("{}:{}", percent_encode(issuer), percent_encode(account))
+    };
+    Some(SafeString::from_string(format!(
+        "otpauth://totp/{}?secret={}&issuer={}",
+        label,
+        secret.deref(),
+        percent_encode(issuer)
+    )))
+}
+
+/// Returns true if the secret can be used to generate codes.
+pub fn is_valid_secret(secret: &SafeString) -> bool {
+    decode_base32(secret.deref()).is_some()
+}
+
+/// Computes an HOTP code (RFC 4226).
+fn hotp(key: &[u8], counter: u64, digits: u32) -> String {
+    let mut mac = HmacSha1::new_from_slice(key).unwrap();
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // Dynamic truncation, see RFC 4226 section 5.3.
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = ((hash[offset] as u64 & 0x7f) << 24)
+        | ((hash[offset + 1] as u64) << 16)
+        | ((hash[offset + 2] as u64) << 8)
+        | (hash[offset + 3] as u64);
+
+    format!(
+        "{:0width$}",
+        binary % 10u64.pow(digits),
+        width = digits as usize
+    )
+}
+
+/// Computes the TOTP code (RFC 6238) for the given Unix timestamp.
+pub fn totp_at(secret: &SafeString, timestamp: u64, period: u64, digits: u32) -> Option<String> {
+    let key = decode_base32(secret.deref())?;
+    Some(hotp(key.deref(), timestamp / period, digits))
+}
+
+/// Computes the TOTP code (RFC 6238) for the current time.
+///
+/// Returns `None` if the secret isn't valid base32.
+pub fn totp_now(secret: &SafeString, period: u64, digits: u32) -> Option<String> {
+    totp_at(secret, ffi::time() as u64, period, digits)
+}
+
+/// How many seconds the current code remains valid.
+pub fn seconds_remaining(period: u64) -> u64 {
+    period - (ffi::time() as u64 % period)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::otp::{decode_base32, encode_base32, is_valid_secret, otpauth_uri, totp_at};
+    use rtoolbox::safe_string::SafeString;
+    use std::ops::Deref;
+
+    // The RFC 6238 test secret, "12345678901234567890" encoded in base32.
+    fn rfc_secret() -> SafeString {
+        SafeString::from_string("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_owned())
+    }
+
+    #[test]
+    fn test_decode_base32() {
+        assert_eq!(
+            &decode_base32("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").unwrap()[..],
+            b"12345678901234567890"
+        );
+        assert_eq!(&decode_base32("gezd gnbv").unwrap()[..], b"12345");
+        assert!(decode_base32("not base32!").is_none());
+        assert!(decode_base32("").is_none());
+    }
+
+    #[test]
+    fn test_encode_base32() {
+        // Test vectors from RFC 4648, without the padding
+        assert_eq!(encode_base32(b"").deref(), "");
+        assert_eq!(encode_base32(b"f").deref(), "MY");
+        assert_eq!(encode_base32(b"fo").deref(), "MZXQ");
+        assert_eq!(encode_base32(b"foo").deref(), "MZXW6");
+        assert_eq!(encode_base32(b"foobar").deref(), "MZXW6YTBOI");
+        assert_eq!(
+            encode_base32(b"12345678901234567890").deref(),
+            rfc_secret().deref()
+        );
+    }
+
+    #[test]
+    fn test_otpauth_uri() {
+        assert_eq!(
+            otpauth_uri(
+                "ACME Co",
+                "john.doe@email.com",
+                &SafeString::from_string("jbsw y3dp-ehpk 3pxp".to_owned())
+            )
+            .unwrap()
+            .deref(),
+            "otpauth://totp/ACME%20Co:john.doe%40email.com?secret=JBSWY3DPEHPK3PXP&issuer=ACME%20Co"
+        );
+        assert_eq!(
+            otpauth_uri("a:b&c", "", &rfc_secret()).unwrap().deref(),
+            "otpauth://totp/a%3Ab%26c?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=a%3Ab%26c"
+        );
+        assert!(otpauth_uri("app", "me", &SafeString::from_string("0189".to_owned())).is_none());
+    }
+
+    #[test]
+    fn test_is_valid_secret() {
+        assert!(is_valid_secret(&rfc_secret()));
+        assert!(!is_valid_secret(&SafeString::from_string("0189".to_owned())));
+    }
+
+    #[test]
+    fn test_totp_rfc6238_vectors() {
+        assert_eq!(totp_at(&rfc_secret(), 59, 30, 8).unwrap(), "94287082");
+        assert_eq!(totp_at(&rfc_secret(), 1111111109, 30, 8).unwrap(), "07081804");
+        assert_eq!(totp_at(&rfc_secret(), 1234567890, 30, 8).unwrap(), "89005924");
+        assert_eq!(totp_at(&rfc_secret(), 59, 30, 6).unwrap(), "287082");
+    }
+}
@@ -21,6 +21,14 @@ pub enum PasswordError {
     NeedUpgradeErrorFromV1,
     NoUpgradeError,
     EmptyPasswordError,
+    /// The app name is empty, or only whitespace.
+    EmptyAppNameError,
+    /// Saving failed for lack of space, the password file was left as it was.
+    DiskFullError,
+    /// scrypt refuses these parameters, like a log2n of 0, see `scrypt::Params::new`.
+    InvalidScryptParamsError,
+    /// The undo log is empty, or the password file has no backup to go back to.
+    NothingToUndoError,
 }
 
 impl From<IoError> for PasswordError {
@@ -34,13 +42,10 @@ fn upgrade_v1_v2(
     v2_store: &mut v2::PasswordStore,
 ) -> Result<(), PasswordError> {
     for p in v1_passwords.iter() {
-        let v2_password = v2::Password {
-            name: p.name.clone(),
-            username: p.username.clone(),
-            password: p.password.clone(),
-            created_at: p.created_at,
-            updated_at: p.updated_at,
-        };
+        let mut v2_password =
+            v2::Password::new(p.name.clone(), p.username.clone(), p.password.clone());
+        v2_password.created_at = p.created_at;
+        v2_password.updated_at = p.updated_at;
         v2_store.add_password(v2_password)?;
     }
 
@@ -61,3 +66,35 @@ pub fn upgrade(
 
     Ok(v2_store)
 }
+
+/// Compares two secrets in constant time, so that the time it takes doesn't tell how many
+/// leading characters match. Only the length leaks, which says little about a password.
+pub fn constant_time_eq(a: &SafeString, b: &SafeString) -> bool {
+    let (a, b) = (a.deref().as_bytes(), b.deref().as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+        == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::constant_time_eq;
+    use rtoolbox::safe_string::SafeString;
+
+    fn s(s: &str) -> SafeString {
+        SafeString::from_string(s.to_owned())
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(&s("abcd"), &s("abcd")));
+        assert!(constant_time_eq(&s(""), &s("")));
+        assert!(!constant_time_eq(&s("abcd"), &s("abce")));
+        assert!(!constant_time_eq(&s("abcd"), &s("abc")));
+        assert!(!constant_time_eq(&s("abcd"), &s("ABCD")));
+    }
+}
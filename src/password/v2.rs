@@ -1,4 +1,5 @@
 use crate::aes;
+use crate::date;
 use crate::ffi;
 use crate::password::PasswordError;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
@@ -10,13 +11,14 @@ use serde_json;
 use serde_json::Error;
 use std::fs::File;
 use std::io::{
-    Cursor, Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult, Seek, SeekFrom,
-    Write,
+    Cursor, Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult, Write,
 };
 use scrypt::{scrypt, Params};
 use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use hmac::{Hmac, Mac};
-use sha2::Sha512;
+use sha2::{Digest, Sha256, Sha512};
 
 type HmacSha512 = Hmac<Sha512>;
 
@@ -30,6 +32,17 @@ type HmacSha512 = Hmac<Sha512>;
 ///         "password": "xxxxxxxx",
 ///         "created_at": 23145436,
 ///         "updated_at": 23145546,
+///         "otp_secret": "JBSWY3DPEHPK3PXP", // optional
+///         "notes": "Recovery codes: ...", // optional
+///         "expires_at": 23155546, // optional
+///         "tags": ["work", "email"], // optional
+///         "attachments": [["recovery.txt", "aGVsbG8="]], // optional, base64
+///         "history": [ // optional, most recent first
+///             {"username": "conradk", "password": "yyyyyyyy", "changed_at": 23145546}
+///         ],
+///     ],
+///     "trash": [ // optional
+///         {"password": {"name": "Facebook", ...}, "deleted_at": 23145546}
 ///     ]
 /// }
 /// ```
@@ -43,7 +56,7 @@ type HmacSha512 = Hmac<Sha512>;
 const IV_LEN: usize = 16;
 
 /// Length of the key derived from the user password, in bytes
-const KEY_LEN: usize = 32;
+pub const KEY_LEN: usize = 32;
 
 /// Length of the salt passed to the key derivation function, in bytes
 const SALT_LEN: usize = 32;
@@ -52,9 +65,9 @@ const SALT_LEN: usize = 32;
 const SIGNATURE_LEN: usize = 64;
 
 /// Scrypt parameters
-const SCRYPT_PARAM_LOG2_N: u8 = 12;
-const SCRYPT_PARAM_R: u32 = 8;
-const SCRYPT_PARAM_P: u32 = 1;
+pub const SCRYPT_PARAM_LOG2_N: u8 = 12;
+pub const SCRYPT_PARAM_R: u32 = 8;
+pub const SCRYPT_PARAM_P: u32 = 1;
 
 /// The version of this lib
 const VERSION: u32 = 2;
@@ -82,27 +95,54 @@ fn generate_encryption_key(
     scrypt_log2_n: u8,
     scrypt_r: u32,
     scrypt_p: u32,
-) -> SafeVec {
+) -> Result<SafeVec, PasswordError> {
     let mut vec = Vec::<u8>::with_capacity(KEY_LEN);
     for _ in 0..KEY_LEN {
         vec.push(0u8);
     }
     let mut output = SafeVec::new(vec);
 
-    let result = scrypt(
+    // The parameters may come from the header of a damaged file.
+    let params = Params::new(scrypt_log2_n, scrypt_r, scrypt_p, KEY_LEN)
+        .map_err(|_| PasswordError::InvalidScryptParamsError)?;
+    scrypt(
         master_password.as_bytes(),
         salt.as_slice(),
-        &Params::new(scrypt_log2_n, scrypt_r, scrypt_p, KEY_LEN).unwrap(),
+        &params,
         output.as_mut(),
-    );
-
-    if result.is_err() {
-        panic!("Deriving scrypt key failed: {:?}", result);
-    }
+    )
+    .map_err(|_| PasswordError::InvalidScryptParamsError)?;
 
     assert_eq!(output.len(), KEY_LEN);
 
-    output
+    Ok(output)
+}
+
+/// Times a key derivation with these parameters, which is about how long unlocking takes.
+pub fn benchmark_scrypt_params(
+    scrypt_log2_n: u8,
+    scrypt_r: u32,
+    scrypt_p: u32,
+) -> Result<Duration, PasswordError> {
+    let start = Instant::now();
+    generate_encryption_key(
+        "benchmark",
+        [0; SALT_LEN],
+        scrypt_log2_n,
+        scrypt_r,
+        scrypt_p,
+    )?;
+    Ok(start.elapsed())
+}
+
+/// Zeroes a key as soon as it is not needed anymore, rather than when it is dropped.
+fn scrub_key(key: &mut SafeVec) {
+    for byte in key.inner_mut().iter_mut() {
+        // Volatile writes, so that the compiler can't skip zeroing memory that is about to be
+        // freed.
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+    std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
 }
 
 /// Creates a HMAC signature
@@ -147,27 +187,291 @@ fn digest_blob_with_metadata(
     Ok(blob_with_metadata)
 }
 
+/// Whether a write failed because the disk is full.
+fn is_disk_full(err: &IoError) -> bool {
+    #[cfg(unix)]
+    let disk_full_codes = [libc::ENOSPC, libc::EDQUOT];
+    // ERROR_HANDLE_DISK_FULL and ERROR_DISK_FULL
+    #[cfg(windows)]
+    let disk_full_codes = [39, 112];
+    match err.raw_os_error() {
+        Some(code) => disk_full_codes.contains(&code),
+        None => false,
+    }
+}
+
+/// Where `replace_file` keeps the previous version of the file at `path`: the same path,
+/// with `.bak` at the end.
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Copies the file at `path` to `backup_path(path)`, through a temporary file as well, so an
+/// interrupted copy never damages the last backup. Does nothing if there is no file yet.
+fn back_up_file(path: &Path, dir: &Path) -> IoResult<()> {
+    let mut previous = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == IoErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    let mut temp_file = tempfile::NamedTempFile::new_in(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        temp_file
+            .as_file()
+            .set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    std::io::copy(&mut previous, temp_file.as_file_mut())?;
+    temp_file.as_file().sync_all()?;
+    temp_file
+        .persist(backup_path(path))
+        .map(|_| ())
+        .map_err(|err| err.error)
+}
+
+/// Writes `bytes` to a temporary file next to `path`, then renames it over `path`. A rename
+/// within a directory is atomic, so the file at `path` always has either all of the old bytes
+/// or all of the new ones, even when the disk fills up or the process dies halfway through.
+///
+/// `verify` gets the path of the complete temporary file, and can still cancel everything.
+/// Right before the rename, the file at `path` is copied to `backup_path(path)`, so the
+/// previous version can be recovered if the new one turns out to be damaged.
+fn replace_file(
+    path: &Path,
+    bytes: &[u8],
+    write: impl FnOnce(&mut File, &[u8]) -> IoResult<()>,
+    verify: impl FnOnce(&Path) -> Result<(), PasswordError>,
+) -> Result<(), PasswordError> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    // The temporary file is removed when dropped, so nothing is left behind on errors.
+    let mut temp_file = tempfile::NamedTempFile::new_in(dir)?;
+    // It becomes the password file, which only its owner may read. tempfile already creates
+    // files like this, but this must not depend on it.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        temp_file
+            .as_file()
+            .set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    write(temp_file.as_file_mut(), bytes)
+        .and_then(|_| temp_file.as_file().sync_all())
+        .map_err(|err| {
+            if is_disk_full(&err) {
+                PasswordError::DiskFullError
+            } else {
+                PasswordError::Io(err)
+            }
+        })?;
+    verify(temp_file.path())?;
+    back_up_file(path, dir)?;
+    temp_file
+        .persist(path)
+        .map_err(|err| PasswordError::Io(err.error))?;
+
+    // Make the rename itself durable. Not all platforms can open directories, and the new
+    // file is already complete, so this is best effort.
+    #[cfg(unix)]
+    let _ = File::open(dir).and_then(|dir| dir.sync_all());
+    Ok(())
+}
+
 /// The format of the encrypted JSON content in the password file v1.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Schema {
     passwords: Vec<Password>,
+    /// Deleted passwords, until they are purged, see `PasswordStore::purge_trash`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    trash: Vec<TrashedPassword>,
 }
 
 impl Schema {
     fn new() -> Schema {
         Schema {
             passwords: Vec::new(),
+            trash: Vec::new(),
         }
     }
 }
 
+/// How many changes `PasswordStore::undo` can revert, one at a time.
+pub const UNDO_LEN: usize = 10;
+
+/// What one command changed, so that it can be reverted. Previous values hold secrets, so the
+/// undo log is only kept in memory, in `SafeString`s, and dropped with the `PasswordStore`. It
+/// is never saved: a deleted password must not outlive the trash in the password file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UndoStep {
+    /// The command that made the changes, like "delete".
+    pub command: String,
+    pub done_at: ffi::time_t,
+    /// The passwords that were changed or removed, as they were before.
+    removed: Vec<Password>,
+    /// The names of the passwords that were added, or changed, as they are after.
+    added: Vec<String>,
+    /// The passwords that were moved to the trash, and out of it.
+    trashed: Vec<TrashedPassword>,
+    untrashed: Vec<TrashedPassword>,
+}
+
+impl UndoStep {
+    /// What undoing the step changes, for confirmations, sorted by app name.
+    pub fn affected_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .removed
+            .iter()
+            .map(|p| p.name.clone())
+            .chain(self.added.iter().cloned())
+            .collect();
+        names.sort_by_key(|name| name.to_lowercase());
+        names.dedup_by_key(|name| name.to_lowercase());
+        names
+    }
+}
+
+/// The passwords and the trash before a command, see `PasswordStore::record_undo`.
+pub struct Snapshot {
+    passwords: Vec<Password>,
+    trash: Vec<TrashedPassword>,
+}
+
+/// A deleted password that can still be restored.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrashedPassword {
+    pub password: Password,
+    pub deleted_at: ffi::time_t,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Password {
     pub name: String,
     pub username: String,
     pub password: SafeString,
+    /// Entries from files that predate these fields get 0, shown as "unknown".
+    #[serde(default)]
     pub created_at: ffi::time_t,
+    #[serde(default)]
     pub updated_at: ffi::time_t,
+    /// Base32 encoded TOTP secret, see `crate::otp`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub otp_secret: Option<SafeString>,
+    /// Free-form text, like recovery codes or security question answers.
+    #[serde(default = "empty_safe_string", skip_serializing_if = "is_empty_safe_string")]
+    pub notes: SafeString,
+    /// After this timestamp, the password is hidden from `get` and `list`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<ffi::time_t>,
+    /// Reminds to rotate the password this many days after it was last updated. Unlike
+    /// `expires_at`, the password stays visible.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_after_days: Option<u32>,
+    /// Never copy this password to the clipboard, only show it, see `rooster protect`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_clipboard: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// An emoji shown before the app name in `list`, see `rooster icon`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// One-time backup codes, in order, with whether each one was used, see `rooster codes`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backup_codes: Vec<(SafeString, bool)>,
+    /// Previous values, most recent first, see `PasswordStore::change_password`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<PreviousPassword>,
+    /// Small files, by name, see `rooster attach`.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        with = "base64_attachments"
+    )]
+    pub attachments: Vec<(String, SafeVec)>,
+}
+
+/// The largest file `rooster attach` accepts.
+pub const MAX_ATTACHMENT_BYTES: usize = 64 * 1024;
+
+/// The most attached bytes in a password file. Every unlock decrypts and parses them all,
+/// so this keeps the password file quick to open.
+pub const MAX_TOTAL_ATTACHMENT_BYTES: usize = 1024 * 1024;
+
+/// Attachments are stored in base64, a lot more compact in JSON than arrays of numbers.
+mod base64_attachments {
+    use crate::armor;
+    use rtoolbox::safe_string::SafeString;
+    use rtoolbox::safe_vec::SafeVec;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::ops::Deref;
+
+    pub fn serialize<S: Serializer>(
+        attachments: &Vec<(String, SafeVec)>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let encoded: Vec<(&str, SafeString)> = attachments
+            .iter()
+            .map(|(name, content)| {
+                (
+                    name.as_str(),
+                    SafeString::from_string(armor::encode_base64(content.deref())),
+                )
+            })
+            .collect();
+        encoded.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(String, SafeVec)>, D::Error> {
+        let encoded: Vec<(String, SafeString)> = Deserialize::deserialize(deserializer)?;
+        encoded
+            .into_iter()
+            .map(|(name, content)| {
+                armor::decode_base64(content.deref())
+                    .map(|bytes| (name, SafeVec::new(bytes)))
+                    .ok_or_else(|| D::Error::custom("invalid base64 in attachment"))
+            })
+            .collect()
+    }
+}
+
+/// Start reminding to rotate a password this many days before it is due.
+pub const ROTATION_WARNING_DAYS: i64 = 14;
+
+/// Where a password is in its rotation schedule, see `Password::rotation_status`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RotationStatus {
+    UpToDate,
+    DueSoon,
+    Overdue,
+}
+
+/// How many previous values are kept for each password.
+pub const HISTORY_LEN: usize = 5;
+
+/// A value a password had before it was changed.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PreviousPassword {
+    pub username: String,
+    pub password: SafeString,
+    /// When this value was replaced.
+    pub changed_at: ffi::time_t,
+}
+
+fn empty_safe_string() -> SafeString {
+    SafeString::from_string(String::new())
+}
+
+fn is_empty_safe_string(s: &SafeString) -> bool {
+    s.is_empty()
 }
 
 impl Password {
@@ -183,10 +487,154 @@ impl Password {
             password: password.into(),
             created_at: timestamp,
             updated_at: timestamp,
+            otp_secret: None,
+            notes: empty_safe_string(),
+            expires_at: None,
+            expires_after_days: None,
+            no_clipboard: false,
+            tags: Vec::new(),
+            icon: None,
+            backup_codes: Vec::new(),
+            history: Vec::new(),
+            attachments: Vec::new(),
         }
     }
+
+    /// Tags are compared case-insensitively.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.to_lowercase();
+        self.tags.iter().any(|t| t.to_lowercase() == tag)
+    }
+
+    /// Replaces the tag `old` with `new`, without adding `new` twice. Returns false if the
+    /// password doesn't have the tag `old`.
+    pub fn rename_tag(&mut self, old: &str, new: &str) -> bool {
+        if !self.has_tag(old) {
+            return false;
+        }
+
+        let old = old.to_lowercase();
+        let mut has_new = self
+            .tags
+            .iter()
+            .any(|t| t.to_lowercase() == new.to_lowercase() && t.to_lowercase() != old);
+        let tags = std::mem::take(&mut self.tags);
+        for tag in tags {
+            if tag.to_lowercase() != old {
+                self.tags.push(tag);
+            } else if !has_new {
+                self.tags.push(new.to_owned());
+                has_new = true;
+            }
+        }
+        true
+    }
+
+    pub fn is_expired(&self, now: ffi::time_t) -> bool {
+        match self.expires_at {
+            Some(expires_at) => now >= expires_at,
+            None => false,
+        }
+    }
+
+    /// Attachment names are compared case-insensitively, like app names.
+    pub fn get_attachment(&self, name: &str) -> Option<&SafeVec> {
+        let name = name.to_lowercase();
+        self.attachments
+            .iter()
+            .find(|(n, _)| n.to_lowercase() == name)
+            .map(|(_, content)| content)
+    }
+
+    /// The backup codes that weren't used yet.
+    pub fn unused_backup_codes(&self) -> Vec<&SafeString> {
+        self.backup_codes
+            .iter()
+            .filter(|(_, used)| !used)
+            .map(|(code, _)| code)
+            .collect()
+    }
+
+    /// When the password should be rotated, if it has a rotation period.
+    pub fn rotation_due_at(&self) -> Option<ffi::time_t> {
+        self.expires_after_days.map(|days| {
+            self.updated_at
+                .saturating_add((days as ffi::time_t).saturating_mul(86400))
+        })
+    }
+
+    /// `None` when the password has no rotation period.
+    pub fn rotation_status(&self, now: ffi::time_t) -> Option<RotationStatus> {
+        self.rotation_due_at().map(|due_at| {
+            if now >= due_at {
+                RotationStatus::Overdue
+            } else if date::days_between(now, due_at) < ROTATION_WARNING_DAYS {
+                RotationStatus::DueSoon
+            } else {
+                RotationStatus::UpToDate
+            }
+        })
+    }
+}
+
+/// What the header of a password file tells without the master password, see
+/// `read_file_format`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum FileFormat {
+    /// Version 1 files have no header, they need an upgrade, see `password::upgrade`.
+    V1,
+    /// Version 2 files start with their scrypt parameters.
+    V2 {
+        scrypt_log2_n: u8,
+        scrypt_r: u32,
+        scrypt_p: u32,
+    },
+}
+
+/// Reads the version and scrypt parameters of a password file, the same way
+/// `PasswordStore::from_input` tells a v1 file from a v2 file, but without decrypting it.
+pub fn read_file_format(input: &[u8]) -> Result<FileFormat, PasswordError> {
+    let mut reader = Cursor::new(input);
+
+    let version = reader.read_u32::<BigEndian>()?;
+    if version > VERSION {
+        return Err(PasswordError::OutdatedRoosterBinaryError);
+    } else if version < VERSION {
+        return Ok(FileFormat::V1);
+    }
+
+    Ok(FileFormat::V2 {
+        scrypt_log2_n: reader.read_u8()?,
+        scrypt_r: reader.read_u32::<BigEndian>()?,
+        scrypt_p: reader.read_u32::<BigEndian>()?,
+    })
 }
 
+/// The passwords, decrypted in memory, and what it takes to encrypt them again.
+///
+/// This is all there is to a password file, without any of the command line, so other
+/// programs can use it as a library:
+///
+/// ```
+/// use rooster::password::v2::{Password, PasswordStore};
+/// use rtoolbox::safe_string::SafeString;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let path = dir.path().join("passwords.rooster");
+/// let master_password = || SafeString::from_string("correct horse".to_owned());
+///
+/// let mut store = PasswordStore::new(master_password()).unwrap();
+/// store
+///     .add_password(Password::new("YouTube", "me@example.com", "xxxx"))
+///     .unwrap();
+/// store.rename_password("youtube", "YouTube Music").unwrap();
+/// store.sync(&path).unwrap();
+///
+/// let store = PasswordStore::open(&path, master_password()).unwrap();
+/// let password = store.get_password("youtube music").unwrap();
+/// assert_eq!(password.username, "me@example.com");
+/// assert_eq!(store.get_all_passwords().len(), 1);
+/// ```
 pub struct PasswordStore {
     key: SafeVec,
     scrypt_log2_n: u8,
@@ -194,7 +642,14 @@ pub struct PasswordStore {
     scrypt_p: u32,
     salt: [u8; SALT_LEN],
     schema: Schema,
-    master_password: String,
+    master_password: SafeString,
+    /// Set once the master password changed, so that `sync` reads the new file back before
+    /// it replaces the one that opens with the old password.
+    verify_on_sync: bool,
+    /// The last changes of this session, oldest first, see `undo`.
+    undo: Vec<UndoStep>,
+    /// Whether something changed since the store was opened or last saved, see `has_changes`.
+    changed: bool,
 }
 
 /// Read and writes to a Rooster file
@@ -209,7 +664,7 @@ pub struct PasswordStore {
 /// - signature:       512 bits HMAC-SHA512
 /// - encrypted blob:  variable length
 impl PasswordStore {
-    pub fn new(master_password: SafeString) -> IoResult<PasswordStore> {
+    pub fn new(master_password: SafeString) -> Result<PasswordStore, PasswordError> {
         let salt = generate_random_salt()?;
         let key = generate_encryption_key(
             master_password.deref(),
@@ -217,7 +672,7 @@ impl PasswordStore {
             SCRYPT_PARAM_LOG2_N,
             SCRYPT_PARAM_R,
             SCRYPT_PARAM_P,
-        );
+        )?;
 
         Ok(PasswordStore {
             key: key,
@@ -226,10 +681,22 @@ impl PasswordStore {
             scrypt_p: SCRYPT_PARAM_P,
             salt: salt,
             schema: Schema::new(),
-            master_password: master_password.into_inner(),
+            master_password: master_password,
+            verify_on_sync: false,
+            undo: Vec::new(),
+            changed: true,
         })
     }
 
+    /// Reads and decrypts the password file at `path`. Files from Rooster 1 fail with
+    /// `NeedUpgradeErrorFromV1`, see `password::upgrade`.
+    pub fn open(path: &Path, master_password: SafeString) -> Result<PasswordStore, PasswordError> {
+        let mut input = SafeVec::new(Vec::new());
+        File::open(path)?.read_to_end(input.inner_mut())?;
+        PasswordStore::from_input(master_password, input)
+    }
+
+    /// Decrypts the bytes of a password file, as written by `to_bytes`.
     pub fn from_input(
         master_password: SafeString,
         input: SafeVec,
@@ -292,17 +759,17 @@ impl PasswordStore {
             scrypt_log2_n,
             scrypt_r,
             scrypt_p,
-        );
+        )?;
 
         // Decrypt the data.
-        let passwords = match aes::decrypt(blob.deref(), key.as_ref(), iv.as_ref()) {
+        let schema = match aes::decrypt(blob.deref(), key.as_ref(), iv.as_ref()) {
             Ok(decrypted) => {
                 let encoded = SafeString::from_string(
                     String::from_utf8_lossy(decrypted.as_ref()).into_owned(),
                 );
                 let s: Result<Schema, Error> = serde_json::from_str(encoded.deref());
                 match s {
-                    Ok(json) => json.passwords,
+                    Ok(json) => json,
                     Err(_) => {
                         return Err(PasswordError::InvalidJsonError);
                     }
@@ -321,7 +788,7 @@ impl PasswordStore {
             &iv,
             &salt,
             blob.deref(),
-        ).unwrap();
+        )?;
         if !verify_signature(old_signature_mac.as_slice(), blob.deref(), key.deref()) {
             return Err(PasswordError::CorruptionError);
         }
@@ -332,16 +799,67 @@ impl PasswordStore {
             scrypt_r: scrypt_r,
             scrypt_p: scrypt_p,
             salt: salt,
-            schema: Schema {
-                passwords: passwords,
+            schema: schema,
+            master_password: master_password,
+            verify_on_sync: false,
+            undo: Vec::new(),
+            changed: false,
+        })
+    }
+
+    /// Saves the store to the password file at `path`, see `replace_file`. After a change of
+    /// master password, the new file must decrypt with it before it replaces the old one.
+    pub fn sync(&mut self, path: &Path) -> Result<(), PasswordError> {
+        let output = self.to_bytes()?;
+        replace_file(
+            path,
+            &output,
+            |file, bytes| file.write_all(bytes),
+            |written| {
+                if self.verify_on_sync {
+                    self.verify_file(written)
+                } else {
+                    Ok(())
+                }
             },
-            master_password: master_password.deref().into(),
+        )?;
+        self.changed = false;
+        Ok(())
+    }
+
+    /// Whether the store changed since it was opened or last saved. New stores have never
+    /// been saved, so they count as changed.
+    pub fn has_changes(&self) -> bool {
+        self.changed
+    }
+
+    /// Checks that the file at `path` opens with the master password, with our passwords.
+    fn verify_file(&self, path: &Path) -> Result<(), PasswordError> {
+        let mut bytes = SafeVec::new(Vec::new());
+        File::open(path)?.read_to_end(bytes.inner_mut())?;
+        self.verify_bytes(self.master_password.deref(), bytes)
+    }
+
+    /// Encrypts the store into the bytes of a password file, as read by `from_input`.
+    pub fn to_bytes(&self) -> Result<SafeVec, PasswordError> {
+        self.schema_to_bytes(&self.schema)
+    }
+
+    /// Like `to_bytes`, but with only these passwords and an empty trash, for exports of
+    /// some of the passwords.
+    pub fn to_bytes_with_passwords(
+        &self,
+        passwords: Vec<Password>,
+    ) -> Result<SafeVec, PasswordError> {
+        self.schema_to_bytes(&Schema {
+            passwords,
+            trash: Vec::new(),
         })
     }
 
-    pub fn sync(&self, file: &mut File) -> Result<(), PasswordError> {
+    fn schema_to_bytes(&self, schema: &Schema) -> Result<SafeVec, PasswordError> {
         // This should never fail. The structs are all encodable.
-        let json_schema = match serde_json::to_string(&self.schema) {
+        let json_schema = match serde_json::to_string(schema) {
             Ok(json_schema) => json_schema,
             Err(_) => {
                 return Err(PasswordError::InvalidJsonError);
@@ -360,46 +878,50 @@ impl PasswordStore {
             Err(_) => return Err(PasswordError::EncryptionError),
         };
 
-        // Reset the file pointer.
-        file.seek(SeekFrom::Start(0))
-            .and_then(|_| file.set_len(0))?;
+        let mut output = SafeVec::new(Vec::new());
+        let buffer = output.inner_mut();
 
         // Write the file version.
-        file.write_u32::<BigEndian>(VERSION)?;
+        buffer.write_u32::<BigEndian>(VERSION)?;
 
         // Write the scrypt params.
-        file.write_u8(self.scrypt_log2_n)?;
-        file.write_u32::<BigEndian>(self.scrypt_r)?;
-        file.write_u32::<BigEndian>(self.scrypt_p)?;
+        buffer.write_u8(self.scrypt_log2_n)?;
+        buffer.write_u32::<BigEndian>(self.scrypt_r)?;
+        buffer.write_u32::<BigEndian>(self.scrypt_p)?;
 
         // Write the key derivation salt.
-        file.write_all(&self.salt)?;
+        buffer.write_all(&self.salt)?;
 
         // Write the encryption IV.
-        file.write_all(&iv)?;
+        buffer.write_all(&iv)?;
 
         // Write the file signature.
-        let blob_with_metadata =
-            digest_blob_with_metadata(
-                VERSION,
-                self.scrypt_log2_n,
-                self.scrypt_r,
-                self.scrypt_p,
-                &iv,
-                &self.salt,
-                encrypted.as_ref(),
-            )?;
-        let signature = digest(
-            self.key.deref(),
-            blob_with_metadata.as_slice(),
+        let blob_with_metadata = digest_blob_with_metadata(
+            VERSION,
+            self.scrypt_log2_n,
+            self.scrypt_r,
+            self.scrypt_p,
+            &iv,
+            &self.salt,
+            encrypted.as_ref(),
         )?;
-        file.write_all(signature.deref())?;
+        let signature = digest(self.key.deref(), blob_with_metadata.as_slice())?;
+        buffer.write_all(signature.deref())?;
 
         // Write the encrypted password data.
-        file.write_all(&encrypted.as_ref())?;
+        buffer.write_all(&encrypted.as_ref())?;
 
-        file.sync_all()?;
-        Ok(())
+        Ok(output)
+    }
+
+    /// The size of every attachment of every password, see `MAX_TOTAL_ATTACHMENT_BYTES`.
+    pub fn attachments_len(&self) -> usize {
+        self.schema
+            .passwords
+            .iter()
+            .flat_map(|p| p.attachments.iter())
+            .map(|(_, content)| content.len())
+            .sum()
     }
 
     pub fn get_all_passwords(&self) -> Vec<&Password> {
@@ -412,8 +934,12 @@ impl PasswordStore {
         passwords
     }
 
-    /// Adds a password to the file.
+    /// Adds a password to the file. Every command, import and JSON-RPC call goes through here,
+    /// so this is where blank app names, empty passwords and duplicate app names are refused.
     pub fn add_password(&mut self, password: Password) -> Result<(), PasswordError> {
+        if password.name.trim().is_empty() {
+            return Err(PasswordError::EmptyAppNameError);
+        }
         if password.password.deref().len() == 0 {
             return Err(PasswordError::EmptyPasswordError);
         }
@@ -421,6 +947,7 @@ impl PasswordStore {
             return Err(PasswordError::AppExistsError);
         }
         self.schema.passwords.push(password);
+        self.changed = true;
         Ok(())
     }
 
@@ -432,6 +959,7 @@ impl PasswordStore {
         let mut i = 0;
         while i < self.schema.passwords.len() {
             if self.schema.passwords[i].name == p.name {
+                self.changed = true;
                 return Ok(self.schema.passwords.remove(i));
             }
             i += 1;
@@ -439,6 +967,177 @@ impl PasswordStore {
         unreachable!();
     }
 
+    /// Deletes a password, but keeps it in the trash so it can be restored.
+    pub fn trash_password(&mut self, name: &str) -> Result<Password, PasswordError> {
+        let password = self.delete_password(name)?;
+        self.schema.trash.push(TrashedPassword {
+            password: password.clone(),
+            deleted_at: ffi::time(),
+        });
+        Ok(password)
+    }
+
+    /// Moves a password out of the trash. If several were deleted with the same name, the
+    /// most recently deleted one is restored.
+    pub fn restore_password(&mut self, name: &str) -> Result<Password, PasswordError> {
+        let name = name.to_lowercase();
+        let index = self
+            .schema
+            .trash
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.password.name.to_lowercase() == name)
+            .max_by_key(|(_, t)| t.deleted_at)
+            .map(|(i, _)| i)
+            .ok_or(PasswordError::NoSuchAppError)?;
+
+        let password = self.schema.trash[index].password.clone();
+        self.add_password(password.clone())?;
+        self.schema.trash.remove(index);
+        Ok(password)
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            passwords: self.schema.passwords.clone(),
+            trash: self.schema.trash.clone(),
+        }
+    }
+
+    /// Adds what changed since `before` to the undo log, if anything did. Only the last
+    /// `UNDO_LEN` steps are kept.
+    pub fn record_undo(&mut self, command: &str, before: Snapshot, now: ffi::time_t) {
+        let is_in = |p: &Password, passwords: &Vec<Password>| passwords.iter().any(|o| o == p);
+        let removed: Vec<Password> = before
+            .passwords
+            .iter()
+            .filter(|p| !is_in(p, &self.schema.passwords))
+            .cloned()
+            .collect();
+        let added: Vec<String> = self
+            .schema
+            .passwords
+            .iter()
+            .filter(|p| !is_in(p, &before.passwords))
+            .map(|p| p.name.clone())
+            .collect();
+        let trashed: Vec<TrashedPassword> = self
+            .schema
+            .trash
+            .iter()
+            .filter(|t| !before.trash.contains(t))
+            .cloned()
+            .collect();
+        let untrashed: Vec<TrashedPassword> = before
+            .trash
+            .iter()
+            .filter(|t| !self.schema.trash.contains(t))
+            .cloned()
+            .collect();
+
+        if removed.is_empty() && added.is_empty() && trashed.is_empty() && untrashed.is_empty() {
+            return;
+        }
+        self.undo.push(UndoStep {
+            command: command.to_owned(),
+            done_at: now,
+            removed,
+            added,
+            trashed,
+            untrashed,
+        });
+        // The evicted passwords are SafeStrings, so they're scrubbed from memory when dropped.
+        while self.undo.len() > UNDO_LEN {
+            self.undo.remove(0);
+        }
+    }
+
+    /// The step `undo` would revert.
+    pub fn last_undo_step(&self) -> Option<&UndoStep> {
+        self.undo.last()
+    }
+
+    /// Reverts the last recorded step. On errors, like when a password was since added by a
+    /// command that isn't recorded, nothing changes.
+    pub fn undo(&mut self) -> Result<UndoStep, PasswordError> {
+        let mut schema = self.schema.clone();
+        let step = self
+            .undo
+            .last()
+            .cloned()
+            .ok_or(PasswordError::NothingToUndoError)?;
+
+        for name in step.added.iter() {
+            let index = schema
+                .passwords
+                .iter()
+                .position(|p| &p.name == name)
+                .ok_or(PasswordError::NoSuchAppError)?;
+            schema.passwords.remove(index);
+        }
+        for password in step.removed.iter() {
+            let name = password.name.to_lowercase();
+            if schema
+                .passwords
+                .iter()
+                .any(|p| p.name.to_lowercase() == name)
+            {
+                return Err(PasswordError::AppExistsError);
+            }
+            schema.passwords.push(password.clone());
+        }
+        for trashed in step.trashed.iter() {
+            if let Some(index) = schema.trash.iter().position(|t| t == trashed) {
+                schema.trash.remove(index);
+            }
+        }
+        schema.trash.extend(step.untrashed.iter().cloned());
+
+        self.schema = schema;
+        self.undo.pop();
+        self.changed = true;
+        Ok(step)
+    }
+
+    /// Opens the copy of the password file at `path` from before its last save, see
+    /// `replace_file`, with our master password. Without a copy, fails with
+    /// `NothingToUndoError`.
+    pub fn open_backup(&self, path: &Path) -> Result<PasswordStore, PasswordError> {
+        let backup = backup_path(path);
+        if !backup.exists() {
+            return Err(PasswordError::NothingToUndoError);
+        }
+        PasswordStore::open(&backup, self.master_password.clone())
+    }
+
+    /// Takes the passwords and the trash of `other`, like a backup from `open_backup`. The
+    /// master password and the scrypt parameters stay ours.
+    pub fn revert_to(&mut self, other: PasswordStore) {
+        self.schema = other.schema;
+        self.changed = true;
+    }
+
+    pub fn get_trashed_passwords(&self) -> Vec<&TrashedPassword> {
+        let mut trash: Vec<&TrashedPassword> = self.schema.trash.iter().collect();
+        trash.sort_by_key(|t| t.deleted_at);
+        trash
+    }
+
+    /// Permanently removes the passwords deleted more than `retention_days` ago. Returns how
+    /// many were removed.
+    pub fn purge_trash(&mut self, now: ffi::time_t, retention_days: u32) -> usize {
+        let retention_seconds = retention_days as i64 * 86400;
+        let trash_len = self.schema.trash.len();
+        self.schema
+            .trash
+            .retain(|t| (now as i64 - t.deleted_at as i64) < retention_seconds);
+        let purged = trash_len - self.schema.trash.len();
+        if purged > 0 {
+            self.changed = true;
+        }
+        purged
+    }
+
     pub fn search_passwords(&self, name: &str) -> Vec<&Password> {
         // Fuzzy search password app names.
         let keys = self
@@ -490,6 +1189,20 @@ impl PasswordStore {
         passwords
     }
 
+    /// Like `search_passwords`, but when a username is given, only keeps the passwords with
+    /// that exact username (case-insensitive). Useful for apps with several accounts.
+    pub fn search_passwords_for_account(
+        &self,
+        name: &str,
+        username: Option<&str>,
+    ) -> Vec<&Password> {
+        let mut passwords = self.search_passwords(name);
+        if let Some(username) = username {
+            passwords.retain(|p| p.username.to_lowercase() == username.to_lowercase());
+        }
+        passwords
+    }
+
     pub fn get_password(&self, name: &str) -> Option<Password> {
         'passwords_loop: for p in &self.schema.passwords {
             // Since the app name must be the same, we need the same length.
@@ -516,13 +1229,42 @@ impl PasswordStore {
         self.get_password(name).is_some()
     }
 
+    /// A SHA-256 hash of the passwords, in hex. The password file changes on every save,
+    /// since the salt and IV are new each time, but this only changes with the passwords.
+    pub fn content_fingerprint(&self) -> String {
+        // This should never fail. The structs are all encodable.
+        let json =
+            SafeString::from_string(serde_json::to_string(&self.get_all_passwords()).unwrap());
+        Sha256::digest(json.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
     pub fn change_password(
         &mut self,
         app_name: &str,
         closure: &dyn Fn(Password) -> Password,
     ) -> Result<Password, PasswordError> {
         let old_password = self.delete_password(app_name)?;
-        let new_password = closure(old_password.clone());
+        let mut new_password = closure(old_password.clone());
+
+        // Keep the previous value around, in case some device still uses it.
+        if new_password.password != old_password.password
+            || new_password.username != old_password.username
+        {
+            new_password.history.insert(
+                0,
+                PreviousPassword {
+                    username: old_password.username.clone(),
+                    password: old_password.password.clone(),
+                    changed_at: ffi::time(),
+                },
+            );
+            // The evicted values are SafeStrings, so they're scrubbed from memory when dropped.
+            new_password.history.truncate(HISTORY_LEN);
+        }
+
         match self.add_password(new_password.clone()) {
             Ok(_) => Ok(new_password),
             Err(err) => {
@@ -533,36 +1275,116 @@ impl PasswordStore {
         }
     }
 
-    pub fn change_master_password(&mut self, master_password: &str) {
-        self.key = generate_encryption_key(
+    /// Gives the password for `name` a new app name. Changing only the case of the name is
+    /// fine, any other name that is already taken fails with `AppExistsError`.
+    pub fn rename_password(
+        &mut self,
+        name: &str,
+        new_name: &str,
+    ) -> Result<Password, PasswordError> {
+        if self.has_password(new_name) && name.to_lowercase() != new_name.to_lowercase() {
+            return Err(PasswordError::AppExistsError);
+        }
+        self.change_password(name, &|old_password: Password| Password {
+            name: new_name.to_owned(),
+            updated_at: ffi::time(),
+            ..old_password
+        })
+    }
+
+    /// Re-encrypts the store with a new master password.
+    ///
+    /// Before anything is written to disk, the store is encrypted in memory and decrypted
+    /// again with the new master password. If that fails, the old master password is kept.
+    pub fn change_master_password(&mut self, master_password: &str) -> Result<(), PasswordError> {
+        self.replace_master_password(master_password).map(|_| ())
+    }
+
+    /// Does the work of `change_master_password`, and hands back the old key once it has been
+    /// zeroed, so that tests can check that it was.
+    fn replace_master_password(&mut self, master_password: &str) -> Result<SafeVec, PasswordError> {
+        let new_key = generate_encryption_key(
             master_password,
             self.salt,
             self.scrypt_log2_n,
             self.scrypt_r,
             self.scrypt_p,
-        );
+        )?;
+        let mut old_key = std::mem::replace(&mut self.key, new_key);
+
+        if let Err(err) = self.verify_master_password(master_password) {
+            let mut new_key = std::mem::replace(&mut self.key, old_key);
+            scrub_key(&mut new_key);
+            return Err(err);
+        }
+
+        // The store is now encrypted with the new key only, so the old one can go right away.
+        scrub_key(&mut old_key);
+        self.master_password = SafeString::from_string(master_password.to_owned());
+        self.verify_on_sync = true;
+        self.changed = true;
+        Ok(old_key)
     }
 
-    pub fn change_scrypt_params(&mut self, scrypt_log2_n: u8, scrypt_r: u32, scrypt_p: u32) {
-        self.scrypt_log2_n = scrypt_log2_n;
-        self.scrypt_r = scrypt_r;
-        self.scrypt_p = scrypt_p;
+    /// Checks that the store, once written, can be read back with this master password.
+    fn verify_master_password(&self, master_password: &str) -> Result<(), PasswordError> {
+        self.verify_bytes(master_password, self.to_bytes()?)
+    }
 
-        self.key = generate_encryption_key(
+    /// Checks that the bytes of a password file decrypt, with this master password, into the
+    /// passwords of the store.
+    fn verify_bytes(&self, master_password: &str, bytes: SafeVec) -> Result<(), PasswordError> {
+        let reopened =
+            PasswordStore::from_input(SafeString::from_string(master_password.into()), bytes)?;
+
+        if reopened.schema.passwords != self.schema.passwords {
+            return Err(PasswordError::CorruptionError);
+        }
+
+        Ok(())
+    }
+
+    /// The scrypt parameters, as (log2n, r, p).
+    pub fn scrypt_params(&self) -> (u8, u32, u32) {
+        (self.scrypt_log2_n, self.scrypt_r, self.scrypt_p)
+    }
+
+    /// Returns how long deriving the new key took, which is about how long unlocking takes.
+    /// Parameters that scrypt refuses leave the store as it was.
+    pub fn change_scrypt_params(
+        &mut self,
+        scrypt_log2_n: u8,
+        scrypt_r: u32,
+        scrypt_p: u32,
+    ) -> Result<Duration, PasswordError> {
+        let start = Instant::now();
+        let new_key = generate_encryption_key(
             self.master_password.deref(),
             self.salt,
-            self.scrypt_log2_n,
-            self.scrypt_r,
-            self.scrypt_p,
-        );
+            scrypt_log2_n,
+            scrypt_r,
+            scrypt_p,
+        )?;
+        let elapsed = start.elapsed();
+
+        self.scrypt_log2_n = scrypt_log2_n;
+        self.scrypt_r = scrypt_r;
+        self.scrypt_p = scrypt_p;
+        let mut old_key = std::mem::replace(&mut self.key, new_key);
+        scrub_key(&mut old_key);
+        self.changed = true;
+        Ok(elapsed)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::password::v2::{digest, generate_encryption_key, generate_random_iv, generate_random_salt, Password, PasswordStore, SCRYPT_PARAM_LOG2_N, SCRYPT_PARAM_P, SCRYPT_PARAM_R, verify_signature};
+    use crate::password::v2::{backup_path, digest, read_file_format, FileFormat, generate_encryption_key, generate_random_iv, generate_random_salt, replace_file, scrub_key, Password, PasswordStore, RotationStatus, HISTORY_LEN, SCRYPT_PARAM_LOG2_N, SCRYPT_PARAM_P, SCRYPT_PARAM_R, UNDO_LEN, verify_signature};
     use crate::password::PasswordError;
     use rtoolbox::safe_string::SafeString;
+    use rtoolbox::safe_vec::SafeVec;
+    use std::io::{Error as IoError, ErrorKind as IoErrorKind, Write};
+    use std::ops::Deref;
 
     #[test]
     fn test_generate_random_iv_has_right_length() {
@@ -584,6 +1406,7 @@ mod test {
                 SCRYPT_PARAM_R,
                 SCRYPT_PARAM_P
             )
+            .unwrap()
             .len(),
             32
         );
@@ -598,7 +1421,7 @@ mod test {
             SCRYPT_PARAM_LOG2_N,
             SCRYPT_PARAM_R,
             SCRYPT_PARAM_P
-        );
+        ).unwrap();
 
 
         let blob = b"my bicycle is beautiful";
@@ -606,6 +1429,34 @@ mod test {
         assert_eq!(true, verify_signature(&signature, blob, &key));
     }
 
+    #[test]
+    fn test_invalid_scrypt_params_are_an_error() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        let bytes = store.to_bytes().unwrap();
+
+        // log2n is the byte right after the version
+        for log2_n in [0, 64].iter() {
+            let mut damaged = bytes.deref().to_vec();
+            damaged[4] = *log2_n;
+            match PasswordStore::from_input(
+                SafeString::from_string("****".to_owned()),
+                SafeVec::new(damaged),
+            ) {
+                Err(PasswordError::InvalidScryptParamsError) => {}
+                _ => panic!("log2n {} should be refused", log2_n),
+            }
+        }
+
+        match store.change_scrypt_params(16, 1, 1) {
+            Err(PasswordError::InvalidScryptParamsError) => {}
+            _ => panic!("log2n 16 with r 1 should be refused"),
+        }
+        assert_eq!(
+            store.scrypt_params(),
+            (SCRYPT_PARAM_LOG2_N, SCRYPT_PARAM_R, SCRYPT_PARAM_P)
+        );
+    }
+
     #[test]
     fn test_create_password_store() {
         let store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
@@ -645,6 +1496,20 @@ mod test {
         assert!(store
             .add_password(Password::new("name", "username", ""))
             .is_err());
+
+        // blank app name => not allowed, also when renaming
+        match store.add_password(Password::new(" ", "username", "password")) {
+            Err(PasswordError::EmptyAppNameError) => {}
+            _ => panic!(),
+        }
+        store
+            .add_password(Password::new("name", "username", "password"))
+            .unwrap();
+        match store.rename_password("name", "") {
+            Err(PasswordError::EmptyAppNameError) => {}
+            _ => panic!(),
+        }
+        assert!(store.has_password("name"));
     }
 
     #[test]
@@ -654,25 +1519,22 @@ mod test {
         assert!(store
             .add_password(Password::new("name", "username", "password"))
             .is_ok());
-        assert_eq!(
-            store
-                .change_password("name", &|p| {
-                    // change app name and password, keep username
-                    Password::new("newname", p.username, "newpassword")
-                })
-                .unwrap(),
-            Password::new("newname", "username", "newpassword")
-        );
+        let changed = store
+            .change_password("name", &|p| {
+                // change app name and password, keep username
+                Password::new("newname", p.username, "newpassword")
+            })
+            .unwrap();
+        assert_eq!(changed.name, "newname");
+        assert_eq!(changed.username, "username");
+        assert_eq!(changed.password, "newpassword".into());
         assert_eq!(store.get_all_passwords().len(), 1);
         assert_eq!(store.get_all_passwords()[0].name, "newname");
         assert_eq!(store.get_all_passwords()[0].username, "username");
         assert_eq!(store.get_all_passwords()[0].password, "newpassword".into());
 
         // case insensitive works too
-        assert_eq!(
-            store.change_password("newname", &|p| p).unwrap(),
-            Password::new("newname", "username", "newpassword")
-        );
+        assert_eq!(store.change_password("newname", &|p| p).unwrap(), changed);
         assert_eq!(store.get_all_passwords().len(), 1);
         assert_eq!(store.get_all_passwords()[0].name, "newname");
         assert_eq!(store.get_all_passwords()[0].username, "username");
@@ -749,4 +1611,579 @@ mod test {
             .is_ok());
         assert!(store.has_password("name"));
     }
+
+    #[test]
+    fn test_search_passwords_for_account() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        store
+            .add_password(Password::new("Gmail work", "me@work.com", "password"))
+            .unwrap();
+        store
+            .add_password(Password::new("Gmail", "me@home.com", "password"))
+            .unwrap();
+
+        assert_eq!(store.search_passwords_for_account("gmail", None).len(), 2);
+        let passwords = store.search_passwords_for_account("gmail", Some("ME@work.com"));
+        assert_eq!(passwords.len(), 1);
+        assert_eq!(passwords[0].name, "Gmail work");
+        assert!(store
+            .search_passwords_for_account("gmail", Some("me"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_content_fingerprint() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        let empty_fingerprint = store.content_fingerprint();
+        assert_eq!(empty_fingerprint.len(), 64);
+
+        let password = Password::new("name", "username", "password");
+        store.add_password(password.clone()).unwrap();
+        let fingerprint = store.content_fingerprint();
+        assert_ne!(fingerprint, empty_fingerprint);
+
+        // Same passwords, different master password and salt
+        let mut other = PasswordStore::new(SafeString::from_string("xxxx".to_owned())).unwrap();
+        other.add_password(password).unwrap();
+        assert_eq!(other.content_fingerprint(), fingerprint);
+    }
+
+    #[test]
+    fn test_deserialize_password_without_optional_fields() {
+        let p: Password = serde_json::from_str(
+            "{\"name\":\"name\",\"username\":\"username\",\"password\":\"password\",\
+             \"created_at\":1605554169,\"updated_at\":1605554169}",
+        )
+        .unwrap();
+        assert_eq!(p.name, "name");
+        assert_eq!(p.password, "password".into());
+        assert_eq!(p.otp_secret, None);
+        assert!(p.notes.is_empty());
+        assert_eq!(p.expires_at, None);
+        assert!(p.tags.is_empty());
+        assert!(p.history.is_empty());
+        assert!(p.attachments.is_empty());
+    }
+
+    #[test]
+    fn test_serialize_attachments_as_base64() {
+        let mut p = Password::new("name", "username", "password");
+        p.attachments = vec![("key.txt".to_owned(), SafeVec::new(b"foobar".to_vec()))];
+
+        let json = serde_json::to_string(&p).unwrap();
+        assert!(json.contains("\"attachments\":[[\"key.txt\",\"Zm9vYmFy\"]]"));
+
+        let deserialized: Password = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, p);
+        assert_eq!(
+            deserialized.get_attachment("KEY.TXT").unwrap().deref(),
+            b"foobar"
+        );
+        assert!(deserialized.get_attachment("other.txt").is_none());
+    }
+
+    #[test]
+    fn test_is_expired() {
+        let mut p = Password::new("name", "username", "password");
+        assert!(!p.is_expired(u32::MAX));
+        p.expires_at = Some(1000);
+        assert!(!p.is_expired(999));
+        assert!(p.is_expired(1000));
+        assert!(p.is_expired(1001));
+    }
+
+    #[test]
+    fn test_rename_tag() {
+        let mut p = Password::new("name", "username", "password");
+        p.tags = vec!["home".to_owned(), "Work".to_owned(), "email".to_owned()];
+        assert!(!p.rename_tag("job", "work"));
+
+        assert!(p.rename_tag("work", "job"));
+        assert_eq!(p.tags, vec!["home", "job", "email"]);
+
+        // No duplicates when the new tag is already there
+        assert!(p.rename_tag("home", "email"));
+        assert_eq!(p.tags, vec!["job", "email"]);
+
+        // Only the casing changes
+        assert!(p.rename_tag("JOB", "Job"));
+        assert_eq!(p.tags, vec!["Job", "email"]);
+    }
+
+    #[test]
+    fn test_rotation_status() {
+        let mut p = Password::new("name", "username", "password");
+        p.updated_at = 1000;
+        assert_eq!(p.rotation_status(u32::MAX), None);
+
+        p.expires_after_days = Some(90);
+        let due_at = 1000 + 90 * 86400;
+        assert_eq!(p.rotation_due_at(), Some(due_at));
+        assert_eq!(p.rotation_status(1000), Some(RotationStatus::UpToDate));
+        assert_eq!(
+            p.rotation_status(due_at - 86400),
+            Some(RotationStatus::DueSoon)
+        );
+        assert_eq!(p.rotation_status(due_at), Some(RotationStatus::Overdue));
+
+        p.expires_after_days = Some(u32::MAX);
+        assert_eq!(p.rotation_due_at(), Some(u32::MAX));
+    }
+
+    #[test]
+    fn test_change_master_password() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+
+        assert!(store.change_master_password("xxxx").is_ok());
+
+        let reopened = PasswordStore::from_input(
+            SafeString::from_string("xxxx".to_owned()),
+            store.to_bytes().unwrap(),
+        );
+        assert_eq!(
+            reopened.unwrap().get_password("name").unwrap(),
+            store.get_password("name").unwrap()
+        );
+        assert!(PasswordStore::from_input(
+            SafeString::from_string("****".to_owned()),
+            store.to_bytes().unwrap()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_password_without_timestamps() {
+        let password: Password =
+            serde_json::from_str(r#"{"name":"name","username":"username","password":"xxxx"}"#)
+                .unwrap();
+        assert_eq!(password.created_at, 0);
+        assert_eq!(password.updated_at, 0);
+    }
+
+    #[test]
+    fn test_scrub_key() {
+        let mut key = SafeVec::new(vec![0xab; 32]);
+        scrub_key(&mut key);
+        assert_eq!(key.len(), 32);
+        assert!(key.iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn test_change_master_password_scrubs_old_key() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        let old_key = store.replace_master_password("xxxx").unwrap();
+        assert!(!old_key.is_empty());
+        assert!(old_key.iter().all(|byte| *byte == 0));
+        assert!(store.key.iter().any(|byte| *byte != 0));
+    }
+
+    #[test]
+    fn test_change_password_keeps_history() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store
+            .add_password(Password::new("name", "username", "password0"))
+            .is_ok());
+
+        // Changes that don't touch the password or username are not recorded
+        let changed = store
+            .change_password("name", &|p| Password {
+                name: "newname".to_owned(),
+                ..p
+            })
+            .unwrap();
+        assert!(changed.history.is_empty());
+
+        for i in 1..=HISTORY_LEN + 2 {
+            store
+                .change_password("newname", &|p| Password {
+                    password: SafeString::from_string(format!("password{}", i)),
+                    ..p
+                })
+                .unwrap();
+        }
+
+        let history = store.get_password("newname").unwrap().history;
+        assert_eq!(history.len(), HISTORY_LEN);
+        // Most recent first, the oldest ones were evicted
+        assert_eq!(history[0].password, "password6".into());
+        assert_eq!(history[HISTORY_LEN - 1].password, "password2".into());
+        assert_eq!(history[0].username, "username");
+    }
+
+    #[test]
+    fn test_trash() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+
+        assert_eq!(store.trash_password("NAME").unwrap().name, "name");
+        assert!(store.get_password("name").is_none());
+        assert_eq!(store.get_trashed_passwords().len(), 1);
+
+        assert_eq!(store.restore_password("Name").unwrap().name, "name");
+        assert!(store.get_password("name").is_some());
+        assert!(store.get_trashed_passwords().is_empty());
+        assert!(store.restore_password("name").is_err());
+
+        // Can't restore over an existing password
+        store.trash_password("name").unwrap();
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+        assert!(store.restore_password("name").is_err());
+        assert_eq!(store.get_trashed_passwords().len(), 1);
+    }
+
+    #[test]
+    fn test_purge_trash() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+        store.trash_password("name").unwrap();
+        let deleted_at = store.get_trashed_passwords()[0].deleted_at;
+
+        assert_eq!(store.purge_trash(deleted_at + 29 * 86400, 30), 0);
+        assert_eq!(store.get_trashed_passwords().len(), 1);
+        assert_eq!(store.purge_trash(deleted_at + 30 * 86400, 30), 1);
+        assert!(store.get_trashed_passwords().is_empty());
+    }
+
+    #[test]
+    fn test_replace_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rooster.file");
+        std::fs::write(&path, b"old").unwrap();
+
+        replace_file(
+            &path,
+            b"new",
+            |file, bytes| file.write_all(bytes),
+            |_| Ok(()),
+        )
+        .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        assert_eq!(std::fs::read(backup_path(&path)).unwrap(), b"old");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        // The first save of a new file has nothing to back up.
+        let new_path = dir.path().join("new.file");
+        replace_file(
+            &new_path,
+            b"new",
+            |file, bytes| file.write_all(bytes),
+            |_| Ok(()),
+        )
+        .unwrap();
+        assert!(!backup_path(&new_path).exists());
+    }
+
+    #[test]
+    fn test_rename_password() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        store
+            .add_password(Password::new("Gmail", "me", "xxxx"))
+            .unwrap();
+        store
+            .add_password(Password::new("Youtube", "me", "yyyy"))
+            .unwrap();
+
+        assert_eq!(
+            store.rename_password("gmail", "GMAIL").unwrap().name,
+            "GMAIL"
+        );
+        match store.rename_password("GMAIL", "youtube") {
+            Err(PasswordError::AppExistsError) => {}
+            _ => panic!("Expected an app exists error"),
+        }
+        match store.rename_password("nope", "Nope") {
+            Err(PasswordError::NoSuchAppError) => {}
+            _ => panic!("Expected a no such app error"),
+        }
+        assert_eq!(names(&store), vec!["GMAIL", "Youtube"]);
+    }
+
+    #[test]
+    fn test_read_file_format() {
+        let store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert_eq!(
+            read_file_format(store.to_bytes().unwrap().deref()).unwrap(),
+            FileFormat::V2 {
+                scrypt_log2_n: SCRYPT_PARAM_LOG2_N,
+                scrypt_r: SCRYPT_PARAM_R,
+                scrypt_p: SCRYPT_PARAM_P,
+            }
+        );
+
+        assert_eq!(read_file_format(&[0, 0, 0, 1, 42]).unwrap(), FileFormat::V1);
+        match read_file_format(&[0, 0, 0, 3]) {
+            Err(PasswordError::OutdatedRoosterBinaryError) => {}
+            _ => panic!("Expected an outdated binary error"),
+        }
+        // Cut in the middle of the scrypt parameters
+        assert!(read_file_format(&[0, 0, 0, 2, 12, 0]).is_err());
+    }
+
+    #[test]
+    fn test_recover_from_backup_after_a_truncated_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rooster.file");
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        store
+            .add_password(Password::new("first", "username", "password"))
+            .unwrap();
+        store.sync(&path).unwrap();
+        store
+            .add_password(Password::new("second", "username", "password"))
+            .unwrap();
+        store.sync(&path).unwrap();
+
+        // Something else writes the password file in place and gets interrupted.
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(PasswordStore::from_input(
+            SafeString::from_string("****".to_owned()),
+            SafeVec::new(std::fs::read(&path).unwrap())
+        )
+        .is_err());
+
+        // The backup has the version from before the last save.
+        std::fs::rename(backup_path(&path), &path).unwrap();
+        let recovered = PasswordStore::from_input(
+            SafeString::from_string("****".to_owned()),
+            SafeVec::new(std::fs::read(&path).unwrap()),
+        )
+        .unwrap();
+        assert!(recovered.has_password("first"));
+        assert!(!recovered.has_password("second"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_replace_file_on_a_full_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rooster.file");
+        std::fs::write(&path, b"old").unwrap();
+
+        // Half of the bytes make it to the disk, then it's full.
+        let result = replace_file(
+            &path,
+            b"new bytes",
+            |file, bytes| {
+                file.write_all(&bytes[..4])?;
+                Err(IoError::from_raw_os_error(libc::ENOSPC))
+            },
+            |_| Ok(()),
+        );
+        match result {
+            Err(PasswordError::DiskFullError) => {}
+            _ => panic!("Expected a disk full error"),
+        }
+        assert_eq!(std::fs::read(&path).unwrap(), b"old");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_change_master_password_survives_a_crash_before_the_rename() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rooster.file");
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        store
+            .add_password(Password::new("name", "username", "password"))
+            .unwrap();
+        store.sync(&path).unwrap();
+        let old_bytes = std::fs::read(&path).unwrap();
+
+        // The new file is complete, but the process dies before renaming it.
+        store.change_master_password("xxxx").unwrap();
+        let bytes = store.to_bytes().unwrap();
+        let result = replace_file(
+            &path,
+            bytes.deref(),
+            |file, bytes| file.write_all(bytes),
+            |_| Err(PasswordError::Io(IoError::new(IoErrorKind::Other, "crash"))),
+        );
+        assert!(result.is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), old_bytes);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert!(PasswordStore::from_input(
+            SafeString::from_string("****".to_owned()),
+            SafeVec::new(old_bytes)
+        )
+        .is_ok());
+
+        // Saving again checks the new file, then puts it in place.
+        assert!(store.verify_on_sync);
+        store.sync(&path).unwrap();
+        let reopened = PasswordStore::from_input(
+            SafeString::from_string("xxxx".to_owned()),
+            SafeVec::new(std::fs::read(&path).unwrap()),
+        )
+        .unwrap();
+        assert!(reopened.has_password("name"));
+    }
+
+    fn names(store: &PasswordStore) -> Vec<String> {
+        store
+            .get_all_passwords()
+            .iter()
+            .map(|p| p.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store.last_undo_step().is_none());
+
+        let before = store.snapshot();
+        store
+            .add_password(Password::new("Gmail", "me", "xxxx"))
+            .unwrap();
+        store
+            .add_password(Password::new("Youtube", "me", "xxxx"))
+            .unwrap();
+        store.record_undo("import", before, 1);
+
+        let before = store.snapshot();
+        store
+            .change_password("gmail", &|old| Password {
+                password: SafeString::from_string("yyyy".to_owned()),
+                ..old
+            })
+            .unwrap();
+        store.trash_password("youtube").unwrap();
+        store.record_undo("regenerate", before, 2);
+
+        // Nothing changed, nothing to record
+        let before = store.snapshot();
+        store.record_undo("get", before, 3);
+        assert_eq!(store.last_undo_step().unwrap().command, "regenerate");
+        assert_eq!(
+            store.last_undo_step().unwrap().affected_names(),
+            vec!["Gmail", "Youtube"]
+        );
+
+        assert_eq!(store.undo().unwrap().command, "regenerate");
+        assert_eq!(names(&store), vec!["Gmail", "Youtube"]);
+        assert_eq!(
+            store.get_password("gmail").unwrap().password,
+            SafeString::from_string("xxxx".to_owned())
+        );
+        assert!(store.get_trashed_passwords().is_empty());
+
+        assert_eq!(store.undo().unwrap().command, "import");
+        assert!(names(&store).is_empty());
+        assert!(store.last_undo_step().is_none());
+    }
+
+    #[test]
+    fn test_undo_after_unrecorded_changes() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        store
+            .add_password(Password::new("Gmail", "me", "xxxx"))
+            .unwrap();
+        let before = store.snapshot();
+        store.delete_password("gmail").unwrap();
+        store.record_undo("delete", before, 1);
+
+        // Gmail came back some other way, so it can't be restored
+        store
+            .add_password(Password::new("Gmail", "other", "yyyy"))
+            .unwrap();
+        match store.undo() {
+            Err(PasswordError::AppExistsError) => {}
+            _ => panic!("Expected AppExistsError"),
+        }
+        assert_eq!(store.get_password("gmail").unwrap().username, "other");
+        assert!(store.last_undo_step().is_some());
+    }
+
+    #[test]
+    fn test_undo_keeps_the_last_steps_only() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        for i in 0..UNDO_LEN + 2 {
+            let before = store.snapshot();
+            store
+                .add_password(Password::new(format!("App {}", i), "me", "xxxx"))
+                .unwrap();
+            store.record_undo("add", before, i as crate::ffi::time_t);
+        }
+        for _ in 0..UNDO_LEN {
+            store.undo().unwrap();
+        }
+        match store.undo() {
+            Err(PasswordError::NothingToUndoError) => {}
+            _ => panic!("the oldest steps should be gone"),
+        }
+        assert_eq!(names(&store), vec!["App 0", "App 1"]);
+    }
+
+    #[test]
+    fn test_has_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rooster.file");
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store.has_changes());
+        store.sync(&path).unwrap();
+        assert!(!store.has_changes());
+
+        let mut store =
+            PasswordStore::open(&path, SafeString::from_string("****".to_owned())).unwrap();
+        assert!(!store.has_changes());
+        assert_eq!(store.purge_trash(0, 30), 0);
+        assert!(store.get_password("gmail").is_none());
+        assert!(!store.has_changes());
+        store
+            .add_password(Password::new("Gmail", "me", "xxxx"))
+            .unwrap();
+        assert!(store.has_changes());
+    }
+
+    #[test]
+    fn test_revert_to_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rooster.file");
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        match store.open_backup(&path) {
+            Err(PasswordError::NothingToUndoError) => {}
+            _ => panic!("there is no backup yet"),
+        }
+
+        store
+            .add_password(Password::new("Gmail", "me", "xxxx"))
+            .unwrap();
+        store.sync(&path).unwrap();
+        store.trash_password("gmail").unwrap();
+        store.sync(&path).unwrap();
+
+        let backup = store.open_backup(&path).unwrap();
+        store.revert_to(backup);
+        assert!(store.has_changes());
+        assert!(store.get_password("gmail").is_some());
+        assert!(store.get_trashed_passwords().is_empty());
+    }
+
+    #[test]
+    fn test_undo_log_is_not_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rooster.file");
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        store
+            .add_password(Password::new("Gmail", "me", "xxxx"))
+            .unwrap();
+        let before = store.snapshot();
+        store.trash_password("gmail").unwrap();
+        store.record_undo("delete", before, 1);
+        assert!(store.last_undo_step().is_some());
+        store.sync(&path).unwrap();
+
+        let store = PasswordStore::open(&path, SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store.last_undo_step().is_none());
+        assert_eq!(store.get_trashed_passwords().len(), 1);
+    }
 }
@@ -0,0 +1,580 @@
+//! A small QR code encoder (ISO/IEC 18004), to show secrets to a phone camera.
+//!
+//! Only what passwords and `otpauth://` URIs need: byte mode, error correction level M and
+//! versions 1 to 10, which hold up to 213 bytes. The structure follows Project Nayuki's
+//! "QR Code generator library".
+
+use rtoolbox::safe_string::SafeString;
+use rtoolbox::safe_vec::SafeVec;
+use std::ops::Deref;
+
+const MAX_VERSION: usize = 10;
+
+/// Error correction codewords per block for level M, by version.
+const ECC_CODEWORDS_PER_BLOCK: [usize; MAX_VERSION + 1] =
+    [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26];
+
+/// Error correction blocks for level M, by version.
+const NUM_ERROR_CORRECTION_BLOCKS: [usize; MAX_VERSION + 1] = [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5];
+
+/// Level M in the format information.
+const ECC_LEVEL_M_BITS: u32 = 0;
+
+/// Light modules around the code. The standard asks for 4, scanners are happy with 2.
+const QUIET_ZONE: i32 = 2;
+
+/// Black on white, whatever the colors of the terminal.
+const COLORS_ON: &'static str = "\x1b[30;47m";
+const COLORS_OFF: &'static str = "\x1b[0m";
+
+/// The most bytes `render_qr` can encode.
+pub const MAX_DATA_LEN: usize = 213;
+
+struct QrCode {
+    version: usize,
+    size: i32,
+    /// One byte per module, 1 for dark. The modules spell out the data, so this is scrubbed.
+    modules: SafeVec,
+    is_function: Vec<bool>,
+}
+
+impl QrCode {
+    fn new(version: usize) -> QrCode {
+        let size = version as i32 * 4 + 17;
+        let len = (size * size) as usize;
+        QrCode {
+            version,
+            size,
+            modules: SafeVec::new(vec![0; len]),
+            is_function: vec![false; len],
+        }
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        (y * self.size + x) as usize
+    }
+
+    fn is_dark(&self, x: i32, y: i32) -> bool {
+        0 <= x && x < self.size && 0 <= y && y < self.size && self.modules[self.index(x, y)] == 1
+    }
+
+    fn set_function(&mut self, x: i32, y: i32, dark: bool) {
+        let index = self.index(x, y);
+        self.modules.inner_mut()[index] = dark as u8;
+        self.is_function[index] = true;
+    }
+
+    fn draw_function_patterns(&mut self) {
+        for i in 0..self.size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+
+        self.draw_finder_pattern(3, 3);
+        self.draw_finder_pattern(self.size - 4, 3);
+        self.draw_finder_pattern(3, self.size - 4);
+
+        let positions = alignment_pattern_positions(self.version);
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                // Skip the three corners with finder patterns
+                if (i, j) != (0, 0) && (i, j) != (0, last) && (i, j) != (last, 0) {
+                    self.draw_alignment_pattern(x, y);
+                }
+            }
+        }
+
+        // Reserve the format areas, the real bits come with the mask.
+        self.draw_format_bits(0);
+        self.draw_version();
+    }
+
+    /// A finder pattern centered on (x, y), with its light separator.
+    fn draw_finder_pattern(&mut self, x: i32, y: i32) {
+        for dy in -4..=4 {
+            for dx in -4..=4 {
+                let (xx, yy) = (x + dx, y + dy);
+                if 0 <= xx && xx < self.size && 0 <= yy && yy < self.size {
+                    let distance = dx.abs().max(dy.abs());
+                    self.set_function(xx, yy, distance != 2 && distance != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment_pattern(&mut self, x: i32, y: i32) {
+        for dy in -2..=2 {
+            for dx in -2..=2 {
+                self.set_function(x + dx, y + dy, dx.abs().max(dy.abs()) != 1);
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, mask: u8) {
+        let bits = format_bits(mask);
+        let bit = |i: u32| (bits >> i) & 1 != 0;
+
+        // Around the top left finder pattern
+        for i in 0..6 {
+            self.set_function(8, i as i32, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i as i32, 8, bit(i));
+        }
+
+        // Split between the other two finder patterns
+        for i in 0..8 {
+            self.set_function(self.size - 1 - i as i32, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, self.size - 15 + i as i32, bit(i));
+        }
+        // Always dark
+        self.set_function(8, self.size - 8, true);
+    }
+
+    fn draw_version(&mut self) {
+        if self.version < 7 {
+            return;
+        }
+        let bits = version_bits(self.version);
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let a = self.size - 11 + i % 3;
+            let b = i / 3;
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// Places the codewords in the zigzag order, two columns at a time from the right.
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let mut i = 0;
+        let mut right = self.size - 1;
+        while right >= 1 {
+            // The vertical timing pattern is skipped entirely
+            if right == 6 {
+                right = 5;
+            }
+            for vertical in 0..self.size {
+                for j in 0..2 {
+                    let x = right - j;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward {
+                        self.size - 1 - vertical
+                    } else {
+                        vertical
+                    };
+                    let index = self.index(x, y);
+                    if !self.is_function[index] && i < codewords.len() * 8 {
+                        self.modules.inner_mut()[index] = (codewords[i / 8] >> (7 - i % 8)) & 1;
+                        i += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    /// Applying the same mask twice undoes it.
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let index = self.index(x, y);
+                if invert && !self.is_function[index] {
+                    self.modules.inner_mut()[index] ^= 1;
+                }
+            }
+        }
+    }
+
+    /// How hard the code is to scan, with the four penalty rules of the standard. This only
+    /// picks the mask, any mask gives a valid code.
+    fn penalty_score(&self) -> usize {
+        let mut penalty = 0;
+        let finder_like: [bool; 11] = [
+            true, false, true, true, true, false, true, false, false, false, false,
+        ];
+
+        for transposed in [false, true] {
+            let module = |a: i32, b: i32| {
+                if transposed {
+                    self.is_dark(b, a)
+                } else {
+                    self.is_dark(a, b)
+                }
+            };
+            for b in 0..self.size {
+                // Runs of 5 or more modules of the same color
+                let mut run = 1;
+                for a in 1..self.size {
+                    if module(a, b) == module(a - 1, b) {
+                        run += 1;
+                        if run == 5 {
+                            penalty += 3;
+                        } else if run > 5 {
+                            penalty += 1;
+                        }
+                    } else {
+                        run = 1;
+                    }
+                }
+
+                // Patterns that look like finders, light modules on either side
+                for a in 0..=self.size - 11 {
+                    let forward = (0..11).all(|k| module(a + k, b) == finder_like[k as usize]);
+                    let backward =
+                        (0..11).all(|k| module(a + k, b) == finder_like[10 - k as usize]);
+                    if forward || backward {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+
+        // 2x2 blocks of the same color
+        for y in 0..self.size - 1 {
+            for x in 0..self.size - 1 {
+                let color = self.is_dark(x, y);
+                if color == self.is_dark(x + 1, y)
+                    && color == self.is_dark(x, y + 1)
+                    && color == self.is_dark(x + 1, y + 1)
+                {
+                    penalty += 3;
+                }
+            }
+        }
+
+        // Balance of dark and light modules
+        let total = self.modules.len();
+        let dark = self.modules.iter().filter(|&&m| m == 1).count();
+        let deviation = (dark * 20).max(total * 10) - (dark * 20).min(total * 10);
+        penalty += ((deviation + total - 1) / total).saturating_sub(1) * 10;
+
+        penalty
+    }
+}
+
+/// The 15 bits of format information for level M and the mask, BCH protected.
+fn format_bits(mask: u8) -> u32 {
+    let data = ECC_LEVEL_M_BITS << 3 | mask as u32;
+    let mut remainder = data;
+    for _ in 0..10 {
+        remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+    }
+    (data << 10 | remainder) ^ 0x5412
+}
+
+/// The 18 bits of version information, BCH protected. Only drawn from version 7.
+fn version_bits(version: usize) -> u32 {
+    let mut remainder = version as u32;
+    for _ in 0..12 {
+        remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1F25);
+    }
+    (version as u32) << 12 | remainder
+}
+
+/// Where alignment patterns are centered, on both axes.
+fn alignment_pattern_positions(version: usize) -> Vec<i32> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let size = version as i32 * 4 + 17;
+    let count = version as i32 / 7 + 2;
+    let step = (version as i32 * 4 + count * 2 + 1) / (count * 2 - 2) * 2;
+    let mut positions: Vec<i32> = (0..count - 1).map(|i| size - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// How many modules are left for codewords once the function patterns are drawn.
+fn num_raw_data_modules(version: usize) -> usize {
+    let mut result = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let count = version / 7 + 2;
+        result -= (25 * count - 10) * count - 55;
+        if version >= 7 {
+            result -= 36;
+        }
+    }
+    result
+}
+
+fn num_data_codewords(version: usize) -> usize {
+    num_raw_data_modules(version) / 8
+        - ECC_CODEWORDS_PER_BLOCK[version] * NUM_ERROR_CORRECTION_BLOCKS[version]
+}
+
+/// Bits of the character count, in byte mode.
+fn char_count_bits(version: usize) -> usize {
+    if version <= 9 {
+        8
+    } else {
+        16
+    }
+}
+
+/// The smallest version that holds this many bytes.
+fn smallest_version(len: usize) -> Option<usize> {
+    (1..=MAX_VERSION)
+        .find(|&version| 4 + char_count_bits(version) + len * 8 <= num_data_codewords(version) * 8)
+}
+
+/// Multiplication in GF(2^8), modulo x^8 + x^4 + x^3 + x^2 + 1.
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u8 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x1D);
+        z ^= ((y >> i) & 1) * x;
+    }
+    z
+}
+
+/// The Reed-Solomon generator polynomial of this degree, without its leading 1.
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0; degree];
+    result[degree - 1] = 1;
+    let mut root: u8 = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_multiply(d, factor);
+        }
+    }
+    result
+}
+
+/// Appends the lowest `count` bits of the value, one byte per bit.
+fn push_bits(bits: &mut SafeVec, value: usize, count: usize) {
+    for i in (0..count).rev() {
+        bits.inner_mut().push(((value >> i) & 1) as u8);
+    }
+}
+
+/// Mode, length, data, then padding up to the capacity of the version.
+fn data_codewords(data: &[u8], version: usize) -> SafeVec {
+    let capacity = num_data_codewords(version) * 8;
+    let mut bits = SafeVec::new(Vec::with_capacity(capacity));
+
+    // Byte mode
+    push_bits(&mut bits, 0b0100, 4);
+    push_bits(&mut bits, data.len(), char_count_bits(version));
+    for &byte in data {
+        push_bits(&mut bits, byte as usize, 8);
+    }
+    let terminator = (capacity - bits.len()).min(4);
+    push_bits(&mut bits, 0, terminator);
+    let to_byte = (8 - bits.len() % 8) % 8;
+    push_bits(&mut bits, 0, to_byte);
+
+    let mut codewords = SafeVec::new(Vec::with_capacity(capacity / 8));
+    for byte in bits.chunks(8) {
+        codewords
+            .inner_mut()
+            .push(byte.iter().fold(0, |acc, &bit| acc << 1 | bit));
+    }
+    for pad in [0xEC, 0x11].iter().cycle() {
+        if codewords.len() == capacity / 8 {
+            break;
+        }
+        codewords.inner_mut().push(*pad);
+    }
+    codewords
+}
+
+/// Splits the data in blocks, appends their error correction, and interleaves it all.
+fn add_ecc_and_interleave(data: &[u8], version: usize) -> SafeVec {
+    let num_blocks = NUM_ERROR_CORRECTION_BLOCKS[version];
+    let block_ecc_len = ECC_CODEWORDS_PER_BLOCK[version];
+    let raw_codewords = num_raw_data_modules(version) / 8;
+    let num_short_blocks = num_blocks - raw_codewords % num_blocks;
+    let short_block_len = raw_codewords / num_blocks;
+
+    let divisor = reed_solomon_divisor(block_ecc_len);
+    let mut blocks: Vec<SafeVec> = Vec::with_capacity(num_blocks);
+    let mut start = 0;
+    for i in 0..num_blocks {
+        let data_len = short_block_len - block_ecc_len + if i < num_short_blocks { 0 } else { 1 };
+        let mut block = SafeVec::new(data[start..start + data_len].to_vec());
+        start += data_len;
+        let ecc = reed_solomon_remainder(block.deref(), &divisor);
+        if i < num_short_blocks {
+            // Keeps all blocks the same length, skipped when interleaving
+            block.inner_mut().push(0);
+        }
+        block.inner_mut().extend_from_slice(&ecc);
+        blocks.push(block);
+    }
+
+    let mut result = SafeVec::new(Vec::with_capacity(raw_codewords));
+    for i in 0..short_block_len + 1 {
+        for (j, block) in blocks.iter().enumerate() {
+            if i != short_block_len - block_ecc_len || j >= num_short_blocks {
+                result.inner_mut().push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+fn encode(data: &[u8]) -> Option<QrCode> {
+    let version = smallest_version(data.len())?;
+    let codewords = add_ecc_and_interleave(data_codewords(data, version).deref(), version);
+
+    let mut qr = QrCode::new(version);
+    qr.draw_function_patterns();
+    qr.draw_codewords(codewords.deref());
+
+    let mut best_mask = 0;
+    let mut best_penalty = usize::max_value();
+    for mask in 0..8 {
+        qr.apply_mask(mask);
+        qr.draw_format_bits(mask);
+        let penalty = qr.penalty_score();
+        if penalty < best_penalty {
+            best_mask = mask;
+            best_penalty = penalty;
+        }
+        qr.apply_mask(mask);
+    }
+    qr.apply_mask(best_mask);
+    qr.draw_format_bits(best_mask);
+    Some(qr)
+}
+
+/// Renders the data as a QR code for the terminal, two rows of modules per line thanks to
+/// half blocks. Returns `None` when there are more than `MAX_DATA_LEN` bytes.
+///
+/// Anyone who can see the screen, or a camera pointed at it, can read the code just as well
+/// as your phone. The result is a `SafeString`, so it is scrubbed once dropped, but the
+/// terminal may keep it in its scrollback.
+pub fn render_qr(data: &str) -> Option<SafeString> {
+    let qr = encode(data.as_bytes())?;
+
+    let mut rendered = String::with_capacity(
+        ((qr.size + 2 * QUIET_ZONE) as usize * 3 + 16) * (qr.size + 2 * QUIET_ZONE) as usize / 2,
+    );
+    let mut y = -QUIET_ZONE;
+    while y < qr.size + QUIET_ZONE {
+        rendered.push_str(COLORS_ON);
+        for x in -QUIET_ZONE..qr.size + QUIET_ZONE {
+            rendered.push(match (qr.is_dark(x, y), qr.is_dark(x, y + 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        rendered.push_str(COLORS_OFF);
+        rendered.push('\n');
+        y += 2;
+    }
+    Some(SafeString::from_string(rendered))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        alignment_pattern_positions, encode, format_bits, num_data_codewords, reed_solomon_divisor,
+        reed_solomon_remainder, render_qr, smallest_version, version_bits, MAX_DATA_LEN,
+    };
+    use std::ops::Deref;
+
+    #[test]
+    fn test_capacity() {
+        // Byte mode capacities at level M, from the standard
+        let capacities = [14, 26, 42, 62, 84, 106, 122, 152, 180, 213];
+        for (i, &capacity) in capacities.iter().enumerate() {
+            assert_eq!(smallest_version(capacity), Some(i + 1));
+            assert_eq!(smallest_version(capacity + 1).unwrap_or(11), i + 2);
+        }
+        assert_eq!(num_data_codewords(1), 16);
+        assert_eq!(capacities[9], MAX_DATA_LEN);
+    }
+
+    #[test]
+    fn test_reed_solomon() {
+        // "HELLO WORLD" at version 1-M, from Thonky's QR code tutorial
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        assert_eq!(
+            reed_solomon_remainder(&data, &reed_solomon_divisor(10)),
+            vec![196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+    }
+
+    #[test]
+    fn test_format_and_version_bits() {
+        assert_eq!(format_bits(0), 0b101010000010010);
+        assert_eq!(format_bits(5), 0b100000011001110);
+        assert_eq!(format_bits(7), 0b100101010100000);
+        assert_eq!(version_bits(7), 0b000111110010010100);
+        assert_eq!(version_bits(10), 0b001010010011010011);
+    }
+
+    #[test]
+    fn test_alignment_pattern_positions() {
+        assert_eq!(alignment_pattern_positions(1), Vec::<i32>::new());
+        assert_eq!(alignment_pattern_positions(2), vec![6, 18]);
+        assert_eq!(alignment_pattern_positions(7), vec![6, 22, 38]);
+        assert_eq!(alignment_pattern_positions(10), vec![6, 28, 50]);
+    }
+
+    #[test]
+    fn test_encode() {
+        let qr = encode(b"nLV3#cK8p!uQz7sE").unwrap();
+        assert_eq!(qr.size, 25);
+        // Finder pattern corners, the dark module and the timing patterns
+        assert!(qr.is_dark(0, 0) && qr.is_dark(24, 0) && qr.is_dark(0, 24));
+        assert!(!qr.is_dark(7, 7));
+        assert!(qr.is_dark(8, 17));
+        assert!((8..17).all(|i| qr.is_dark(i, 6) == (i % 2 == 0)));
+
+        assert!(encode(&[b'x'; MAX_DATA_LEN]).is_some());
+        assert!(encode(&[b'x'; MAX_DATA_LEN + 1]).is_none());
+    }
+
+    #[test]
+    fn test_render_qr() {
+        let rendered = render_qr("xxxx").unwrap();
+        // Version 1 is 21 modules, plus the quiet zone
+        let lines: Vec<&str> = rendered.deref().lines().collect();
+        assert_eq!(lines.len(), 13);
+        assert!(lines
+            .iter()
+            .all(|line| line.chars().filter(|c| " ▀▄█".contains(*c)).count() == 25));
+
+        assert!(render_qr(&"x".repeat(MAX_DATA_LEN + 1)).is_none());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::password::v2::{Password, PasswordStore};
+use clap::{Arg, ArgMatches};
+use fancy_regex::Regex;
+
+/// The ids of the arguments from `selector_args`, for `conflicts_with_all` and the like.
+pub const SELECTOR_IDS: [&'static str; 4] = ["contains", "regex", "tag", "exact"];
+
+/// One way of picking passwords by their app name or tags. Text comparisons ignore case.
+pub enum Selector {
+    /// The app name contains this text.
+    Contains(String),
+    /// The app name matches this regular expression. Add `(?i)` to ignore case.
+    Regex(Regex),
+    /// The username matches this regular expression.
+    UsernameRegex(Regex),
+    /// The password has this tag.
+    Tag(String),
+    /// The app name is exactly this.
+    Exact(String),
+}
+
+impl Selector {
+    pub fn matches(&self, password: &Password) -> bool {
+        match self {
+            Selector::Contains(text) => password
+                .name
+                .to_lowercase()
+                .contains(text.to_lowercase().as_str()),
+            Selector::Regex(regex) => regex.is_match(&password.name).unwrap_or(false),
+            Selector::UsernameRegex(regex) => regex.is_match(&password.username).unwrap_or(false),
+            Selector::Tag(tag) => password.has_tag(tag),
+            Selector::Exact(name) => password.name.to_lowercase() == name.to_lowercase(),
+        }
+    }
+}
+
+/// Compiles a regular expression from the command line, with an error message for the user.
+pub fn parse_regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|err| {
+        format!(
+            "Woops, \"{}\" is not a valid regular expression (reason: {}).",
+            pattern, err
+        )
+    })
+}
+
+/// The selector for `--regex` in `search` and `list`, where `--field` picks whether the app
+/// name or the username must match.
+pub fn regex_selector(pattern: &str, field: &str) -> Result<Selector, String> {
+    let regex = parse_regex(pattern)?;
+    Ok(match field {
+        "username" => Selector::UsernameRegex(regex),
+        _ => Selector::Regex(regex),
+    })
+}
+
+/// Passwords are selected when they match every selector, so no selector means all of them.
+pub struct Filter {
+    selectors: Vec<Selector>,
+}
+
+impl Filter {
+    pub fn new(selectors: Vec<Selector>) -> Filter {
+        Filter { selectors }
+    }
+
+    /// Reads the arguments from `selector_args`. Fails on invalid regular expressions.
+    pub fn from_matches(matches: &ArgMatches) -> Result<Filter, String> {
+        let mut selectors = Vec::new();
+        if let Some(text) = matches.get_one::<String>("contains") {
+            selectors.push(Selector::Contains(text.clone()));
+        }
+        if let Some(pattern) = matches.get_one::<String>("regex") {
+            selectors.push(Selector::Regex(parse_regex(pattern)?));
+        }
+        if let Some(tag) = matches.get_one::<String>("tag") {
+            selectors.push(Selector::Tag(tag.clone()));
+        }
+        if let Some(name) = matches.get_one::<String>("exact") {
+            selectors.push(Selector::Exact(name.clone()));
+        }
+        Ok(Filter::new(selectors))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.selectors.is_empty()
+    }
+
+    pub fn matches(&self, password: &Password) -> bool {
+        self.selectors.iter().all(|s| s.matches(password))
+    }
+}
+
+/// The same selection arguments for every command that works on several passwords at once.
+pub fn selector_args() -> [Arg; 4] {
+    [
+        Arg::new("contains")
+            .long("contains")
+            .help("Only the apps whose name contains this text"),
+        Arg::new("regex")
+            .long("regex")
+            .help("Only the apps whose name matches this regular expression"),
+        Arg::new("tag")
+            .long("tag")
+            .help("Only the passwords with this tag"),
+        Arg::new("exact")
+            .long("exact")
+            .help("Only the app with exactly this name"),
+    ]
+}
+
+/// The passwords selected by the filter, sorted by app name.
+pub fn select_entries<'a>(store: &'a PasswordStore, filter: &Filter) -> Vec<&'a Password> {
+    store
+        .get_all_passwords()
+        .into_iter()
+        .filter(|p| filter.matches(p))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{regex_selector, select_entries, Filter, Selector};
+    use crate::password::v2::{Password, PasswordStore};
+    use fancy_regex::Regex;
+    use rtoolbox::safe_string::SafeString;
+
+    fn names(store: &PasswordStore, selectors: Vec<Selector>) -> Vec<String> {
+        select_entries(store, &Filter::new(selectors))
+            .iter()
+            .map(|p| p.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_select_entries() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        let mut gmail = Password::new("Gmail", "me", "xxxx");
+        gmail.tags = vec!["Work".to_owned()];
+        store.add_password(gmail).unwrap();
+        store
+            .add_password(Password::new("Gmail personal", "me", "xxxx"))
+            .unwrap();
+        store
+            .add_password(Password::new("Youtube", "me", "xxxx"))
+            .unwrap();
+
+        assert_eq!(names(&store, vec![]).len(), 3);
+        assert_eq!(
+            names(&store, vec![Selector::Contains("MAIL".to_owned())]),
+            vec!["Gmail", "Gmail personal"]
+        );
+        assert_eq!(
+            names(&store, vec![Selector::Exact("gmail".to_owned())]),
+            vec!["Gmail"]
+        );
+        assert_eq!(
+            names(&store, vec![Selector::Tag("work".to_owned())]),
+            vec!["Gmail"]
+        );
+        assert_eq!(
+            names(&store, vec![Selector::Regex(Regex::new("^G.*l$").unwrap())]),
+            vec!["Gmail", "Gmail personal"]
+        );
+
+        assert_eq!(
+            names(&store, vec![regex_selector("^me$", "username").unwrap()]),
+            vec!["Gmail", "Gmail personal", "Youtube"]
+        );
+        assert_eq!(
+            names(&store, vec![regex_selector("^me$", "app").unwrap()]),
+            Vec::<String>::new()
+        );
+        assert!(regex_selector("(unclosed", "app").is_err());
+
+        // Every selector must match
+        assert_eq!(
+            names(
+                &store,
+                vec![
+                    Selector::Contains("gmail".to_owned()),
+                    Selector::Tag("home".to_owned())
+                ]
+            ),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use rtoolbox::safe_string::SafeString;
+use std::fmt;
+use std::ops::Deref;
+
+/// Passwords with fewer bits of entropy than this are considered weak.
+pub const WEAK_ENTROPY_BITS: f64 = 50.0;
+
+/// Passwords with at least this many bits of entropy are considered strong.
+pub const STRONG_ENTROPY_BITS: f64 = 80.0;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Rating {
+    Weak,
+    Fair,
+    Strong,
+}
+
+impl fmt::Display for Rating {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rating::Weak => write!(f, "weak"),
+            Rating::Fair => write!(f, "fair"),
+            Rating::Strong => write!(f, "strong"),
+        }
+    }
+}
+
+/// Estimates the entropy of a password, in bits.
+///
+/// The estimate assumes each character was picked at random among the character classes
+/// present in the password (lowercase, uppercase, digits, punctuation, other). This is
+/// accurate for generated passwords and optimistic for human-chosen ones.
+pub fn estimate_entropy_bits(password: &SafeString) -> f64 {
+    let mut has_lowercase = false;
+    let mut has_uppercase = false;
+    let mut has_digit = false;
+    let mut has_punctuation = false;
+    let mut has_other = false;
+    let mut length = 0;
+
+    for c in password.deref().chars() {
+        if c.is_ascii_lowercase() {
+            has_lowercase = true;
+        } else if c.is_ascii_uppercase() {
+            has_uppercase = true;
+        } else if c.is_ascii_digit() {
+            has_digit = true;
+        } else if c.is_ascii_punctuation() || c == ' ' {
+            has_punctuation = true;
+        } else {
+            has_other = true;
+        }
+        length += 1;
+    }
+
+    let mut pool_size = 0;
+    if has_lowercase {
+        pool_size += 26;
+    }
+    if has_uppercase {
+        pool_size += 26;
+    }
+    if has_digit {
+        pool_size += 10;
+    }
+    if has_punctuation {
+        pool_size += 33;
+    }
+    if has_other {
+        pool_size += 100;
+    }
+
+    if pool_size == 0 {
+        return 0.0;
+    }
+
+    length as f64 * (pool_size as f64).log2()
+}
+
+pub fn rate(entropy_bits: f64) -> Rating {
+    if entropy_bits < WEAK_ENTROPY_BITS {
+        Rating::Weak
+    } else if entropy_bits < STRONG_ENTROPY_BITS {
+        Rating::Fair
+    } else {
+        Rating::Strong
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::strength::{estimate_entropy_bits, rate, Rating};
+    use rtoolbox::safe_string::SafeString;
+
+    fn entropy(s: &str) -> f64 {
+        estimate_entropy_bits(&SafeString::from_string(s.to_owned()))
+    }
+
+    #[test]
+    fn test_empty_password_has_no_entropy() {
+        assert_eq!(entropy(""), 0.0);
+    }
+
+    #[test]
+    fn test_entropy_grows_with_length_and_diversity() {
+        assert!(entropy("abcdefgh") < entropy("abcdefghij"));
+        assert!(entropy("abcdefgh") < entropy("abcdEFgh"));
+        assert!(entropy("abcdEFgh") < entropy("abcdEF1h"));
+        assert!(entropy("abcdEF1h") < entropy("abcdEF1!"));
+        // 8 lowercase letters: 8 * log2(26)
+        assert!((entropy("abcdefgh") - 37.6).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_rating() {
+        assert_eq!(rate(entropy("password")), Rating::Weak);
+        assert_eq!(rate(entropy("correcthorse")), Rating::Fair);
+        assert_eq!(
+            rate(entropy("Tr0ub4dor&3-correct-horse-battery")),
+            Rating::Strong
+        );
+    }
+}
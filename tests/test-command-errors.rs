@@ -0,0 +1,74 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use rooster::{run_with_args, CommandError};
+
+#[test]
+fn test_command_errors() {
+    let rooster_file = tempfile();
+
+    // No password file yet
+    assert_eq!(
+        Err(CommandError::NotFound),
+        run_with_args(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        Ok(()),
+        run_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        Ok(()),
+        run_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        Err(CommandError::NotFound),
+        run_with_args(
+            &["rooster", "get", "-s", "Google"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        Err(CommandError::Duplicate),
+        run_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        Err(CommandError::UserAbort),
+        run_with_args(
+            &["rooster", "delete", "youtube"],
+            &mut CursorInputOutput::new("", "xxxx\nn\n"),
+            &rooster_file
+        )
+    );
+
+    // The exit code is the same for every error
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "delete", "youtube"],
+            &mut CursorInputOutput::new("", "xxxx\nn\n"),
+            &rooster_file
+        )
+    );
+}
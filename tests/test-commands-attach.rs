@@ -0,0 +1,146 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_attach() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Bank", "me@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let input_file = tempfile();
+    let content: Vec<u8> = (0..=255).collect();
+    std::fs::write(&input_file, &content).unwrap();
+    let input_path = input_file.to_str().unwrap();
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "attach", "add", "bank", input_path, "--name", "key.bin"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // Names must be unique
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "attach", "add", "bank", input_path, "--name", "KEY.bin"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "attach", "list", "bank"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("key.bin (256 bytes)"));
+
+    let output_file = tempfile();
+    let output_path = output_file.to_str().unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "attach", "get", "bank", "key.bin", output_path],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(std::fs::read(&output_file).unwrap(), content);
+
+    // Never overwrite a file
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "attach", "get", "bank", "key.bin", output_path],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "attach", "remove", "bank", "key.bin"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "attach", "list", "bank"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    assert!(output_as_vecu8.is_empty());
+}
+
+#[test]
+fn test_command_attach_refuses_large_files() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Bank", "me@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let input_file = tempfile();
+    std::fs::write(&input_file, vec![0u8; 64 * 1024 + 1]).unwrap();
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "attach",
+                "add",
+                "bank",
+                input_file.to_str().unwrap()
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("too large"));
+}
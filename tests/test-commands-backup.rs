@@ -0,0 +1,57 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_backup() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let backup_dir = tempfile();
+    std::fs::create_dir(&backup_dir).unwrap();
+
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "backup", backup_dir.to_str().unwrap()],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    let backup_path = output_as_string.trim();
+    assert!(backup_path.contains("rooster-backup-"));
+    assert_eq!(
+        std::fs::read(backup_path).unwrap(),
+        std::fs::read(&rooster_file).unwrap()
+    );
+
+    // The backup is a working Rooster file
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &std::path::PathBuf::from(backup_path)
+        )
+    );
+
+    // The directory must exist
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "backup", tempfile().to_str().unwrap()],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+}
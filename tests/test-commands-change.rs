@@ -52,3 +52,49 @@ fn test_command_change() {
         )
     );
 }
+
+#[test]
+fn test_command_change_show_or_copy() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // With --show, the new password is printed
+    let mut io = CursorInputOutput::new("", "xxxx\nefgh\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "change", "-s", "youtube"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let stdout = String::from_utf8(io.stdout_cursor.into_inner()).unwrap();
+    assert!(stdout.contains("efgh"));
+
+    // Without --show, it is copied to the clipboard (or we say how to see it) but not printed
+    let mut io = CursorInputOutput::new("", "xxxx\nijkl\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "change", "youtube"], &mut io, &rooster_file)
+    );
+    let stdout = String::from_utf8(io.stdout_cursor.into_inner()).unwrap();
+    let stderr = String::from_utf8(io.stderr_cursor.into_inner()).unwrap();
+    assert!(!stdout.contains("ijkl"));
+    assert!(stderr.contains("saved"));
+}
@@ -0,0 +1,33 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_config_show() {
+    let rooster_file = tempfile();
+
+    // Works without a password file, and without asking for the master password
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "config", "show"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains(rooster_file.to_string_lossy().as_ref()));
+    assert!(output_as_string.contains("generate.length"));
+    assert!(output_as_string.contains("scrypt.max-log2n"));
+
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "--memory", "config", "show"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("flag: --memory"));
+}
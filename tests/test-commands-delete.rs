@@ -26,12 +26,50 @@ fn test_command_delete() {
     assert_eq!(
         0,
         main_with_args(
-            &["rooster", "delete", "youtube"],
+            &["rooster", "generate", "-s", "Yahoo", "yh@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // Ambiguous matches are not deleted
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "delete", "-y", "y"],
             &mut CursorInputOutput::new("", "xxxx\n"),
             &rooster_file
         )
     );
 
+    // Nothing is deleted without a confirmation
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "delete", "youtube"],
+            &mut CursorInputOutput::new("", "xxxx\nn\n"),
+            &rooster_file
+        )
+    );
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Youtube"));
+    assert!(output_as_string.contains("Yahoo"));
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "delete", "youtube"],
+            &mut CursorInputOutput::new("", "xxxx\ny\n"),
+            &rooster_file
+        )
+    );
+
     let mut io = CursorInputOutput::new("", "xxxx\n");
     assert_eq!(
         0,
@@ -41,4 +79,70 @@ fn test_command_delete() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(!output_as_string.contains("Youtube"));
     assert!(!output_as_string.contains("yt@example.com"));
+    assert!(output_as_string.contains("Yahoo"));
+}
+
+#[test]
+fn test_command_delete_selection() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    for (app, tag) in [("Gmail", "old"), ("Yahoo", "old"), ("Youtube", "video")].iter() {
+        assert_eq!(
+            0,
+            main_with_args(
+                &[
+                    "rooster",
+                    "add",
+                    "-s",
+                    *app,
+                    "me@example.com",
+                    "--tag",
+                    *tag
+                ],
+                &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+                &rooster_file
+            )
+        );
+    }
+
+    // Answering no keeps everything
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "delete", "--tag", "old"],
+            &mut CursorInputOutput::new("", "xxxx\nn\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\ny\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "delete", "--tag", "old"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("2 password(s) match"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(!output_as_string.contains("Gmail"));
+    assert!(!output_as_string.contains("Yahoo"));
+    assert!(output_as_string.contains("Youtube"));
 }
@@ -27,7 +27,11 @@ fn test_command_export_json() {
     let mut io = CursorInputOutput::new("", "xxxx\n");
     assert_eq!(
         0,
-        main_with_args(&["rooster", "export", "json"], &mut io, &rooster_file)
+        main_with_args(
+            &["rooster", "export", "json", "--plaintext"],
+            &mut io,
+            &rooster_file
+        )
     );
     let output_as_vecu8 = io.stdout_cursor.into_inner();
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
@@ -81,7 +85,11 @@ fn test_command_export_csv() {
     let mut io = CursorInputOutput::new("", "xxxx\n");
     assert_eq!(
         0,
-        main_with_args(&["rooster", "export", "csv"], &mut io, &rooster_file)
+        main_with_args(
+            &["rooster", "export", "csv", "--plaintext"],
+            &mut io,
+            &rooster_file
+        )
     );
     let output_as_vecu8 = io.stdout_cursor.into_inner();
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
@@ -112,9 +120,344 @@ fn test_command_export_1password() {
     let mut io = CursorInputOutput::new("", "xxxx\n");
     assert_eq!(
         0,
-        main_with_args(&["rooster", "export", "1password"], &mut io, &rooster_file)
+        main_with_args(
+            &["rooster", "export", "1password", "--plaintext"],
+            &mut io,
+            &rooster_file
+        )
     );
     let output_as_vecu8 = io.stdout_cursor.into_inner();
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert_eq!(output_as_string, "Youtube,yt@example.com,abcd\n");
 }
+
+#[test]
+fn test_command_export_encrypted() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let export_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "export",
+                "encrypted",
+                export_file.as_path().to_str().unwrap()
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let exported = std::fs::read(&export_file).unwrap();
+    assert!(!String::from_utf8_lossy(&exported).contains("abcd"));
+
+    // Import it in another file, with another master password
+    let other_rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nyyyy\n"),
+            &other_rooster_file
+        )
+    );
+
+    let import_args = [
+        "rooster",
+        "import",
+        "encrypted",
+        export_file.as_path().to_str().unwrap(),
+    ];
+    assert_eq!(
+        1,
+        main_with_args(
+            &import_args,
+            &mut CursorInputOutput::new("", "yyyy\nyyyy\n"),
+            &other_rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &import_args,
+            &mut CursorInputOutput::new("", "yyyy\nxxxx\n"),
+            &other_rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "yyyy\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "-s", "youtube"],
+            &mut io,
+            &other_rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+}
+
+#[test]
+fn test_command_export_markdown() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let export_file = tempfile();
+    let export_path = export_file.to_str().unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "export", "markdown", export_path],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let markdown = std::fs::read_to_string(&export_file).unwrap();
+    assert!(markdown.contains("| Youtube | yt@example.com | abcd |"));
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "export", "markdown", "--no-secrets", export_path],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let markdown = std::fs::read_to_string(&export_file).unwrap();
+    assert!(markdown.contains("| Youtube | yt@example.com |"));
+    assert!(!markdown.contains("abcd"));
+}
+
+#[test]
+fn test_command_export_encrypted_by_default() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "export", "json"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(!output_as_string.contains("abcd"));
+    assert!(!output_as_string.contains("Youtube"));
+
+    let export_file = tempfile();
+    std::fs::write(&export_file, output_as_vecu8.as_slice()).unwrap();
+
+    // The export can be imported in another password file
+    let other_rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nyyyy\n"),
+            &other_rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "encrypted",
+                export_file.to_str().unwrap()
+            ],
+            &mut CursorInputOutput::new("", "yyyy\nxxxx\n"),
+            &other_rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "yyyy\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "-s", "youtube"],
+            &mut io,
+            &other_rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+}
+
+#[test]
+fn test_command_export_selection() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "add",
+                "-s",
+                "Gmail",
+                "me@work.com",
+                "--tag",
+                "work"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nefgh\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "export", "json", "--plaintext", "--tag", "work"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    let output_as_json = serde_json::from_str::<Value>(output_as_string.as_ref()).unwrap();
+    let passwords = output_as_json["passwords"].as_array().unwrap();
+    assert_eq!(passwords.len(), 1);
+    assert_eq!(passwords[0]["name"], "Gmail");
+
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "export", "csv", "--plaintext", "--exact", "nope"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
+
+#[test]
+fn test_command_export_to_output_file() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // An existing file that anyone can read
+    let export_file = tempfile();
+    let export_path = export_file.to_str().unwrap();
+    std::fs::write(&export_file, "old export").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&export_file, std::fs::Permissions::from_mode(0o644)).unwrap();
+    }
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "export",
+                "csv",
+                "--plaintext",
+                "--output",
+                export_path
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(!output_as_string.contains("abcd"));
+    let csv = std::fs::read_to_string(&export_file).unwrap();
+    assert_eq!(csv, "Youtube,yt@example.com,abcd\n");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&export_file)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,70 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+fn fingerprint(args: &[&str], rooster_file: &std::path::PathBuf) -> String {
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(0, main_with_args(args, &mut io, rooster_file));
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned()
+}
+
+#[test]
+fn test_command_fingerprint() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let backup_file = tempfile();
+    std::fs::copy(&rooster_file, &backup_file).unwrap();
+    let backup_path = backup_file.to_str().unwrap();
+
+    // Saving re-encrypts the file, but the fingerprint only depends on the passwords
+    let live = fingerprint(&["rooster", "fingerprint"], &rooster_file);
+    assert_eq!(live.trim().len(), 64);
+    assert_eq!(
+        fingerprint(&["rooster", "fingerprint"], &rooster_file),
+        live
+    );
+    assert_eq!(
+        fingerprint(
+            &["rooster", "fingerprint", "--file", backup_path],
+            &rooster_file
+        ),
+        live
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Google", "me@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nefgh\n"),
+            &rooster_file
+        )
+    );
+    assert_ne!(
+        fingerprint(&["rooster", "fingerprint"], &rooster_file),
+        live
+    );
+    assert_eq!(
+        fingerprint(
+            &["rooster", "fingerprint", "--file", backup_path],
+            &rooster_file
+        ),
+        live
+    );
+}
@@ -42,3 +42,130 @@ fn test_command_generate() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("yt@example.com"));
 }
+
+#[test]
+fn test_command_generate_match_regex() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "generate",
+                "-s",
+                "-a",
+                "-l",
+                "8",
+                "--match-regex",
+                "^[a-z]*$",
+                "Youtube",
+                "yt@example.com"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string
+        .split_whitespace()
+        .any(|word| word.len() == 8 && word.chars().all(|c| c.is_ascii_lowercase())));
+
+    // Impossible patterns fail instead of looping forever
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "generate",
+                "--match-regex",
+                "^$",
+                "Google",
+                "google@example.com"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // Invalid patterns are rejected
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "generate",
+                "--match-regex",
+                "(",
+                "Google",
+                "google@example.com"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
+
+#[test]
+fn test_command_generate_charset() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // The charset can't be empty
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "generate",
+                "-s",
+                "--charset",
+                "",
+                "Youtube",
+                "yt"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "generate",
+                "-s",
+                "--charset",
+                "ab",
+                "Youtube",
+                "yt"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    let password = output_as_string.trim();
+    assert_eq!(password.len(), 32);
+    assert!(password.chars().all(|c| c == 'a' || c == 'b'));
+}
@@ -0,0 +1,70 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+fn list(args: &[&str], rooster_file: &std::path::PathBuf) -> String {
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(0, main_with_args(args, &mut io, rooster_file));
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned()
+}
+
+#[test]
+fn test_command_icon() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    for app in ["Bank", "Youtube"].iter() {
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "add", "-s", *app, "me@example.com"],
+                &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+                &rooster_file
+            )
+        );
+    }
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "icon", "bank", "🏦"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let output = list(&["rooster", "list"], &rooster_file);
+    assert!(output.contains("🏦 Bank"));
+    assert!(output.contains("   Youtube"));
+
+    let output = list(&["rooster", "list", "--no-icons"], &rooster_file);
+    assert!(!output.contains("🏦"));
+    assert!(output.starts_with("Bank"));
+
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "icon", "bank", "a b"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "icon", "bank", "--clear"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let output = list(&["rooster", "list"], &rooster_file);
+    assert!(!output.contains("🏦"));
+    assert!(output.starts_with("Bank"));
+}
@@ -44,6 +44,8 @@ fn test_command_import_json() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("abcd"));
     assert!(output_as_string.contains("yt@example.com"));
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("Youtube"));
 
     let import_file_csv = tempfile();
@@ -75,6 +77,8 @@ fn test_command_import_json() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("abcd"));
     assert!(output_as_string.contains("yt@example.com"));
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("Youtube"));
 
     let import_file_1password = tempfile();
@@ -106,6 +110,8 @@ fn test_command_import_json() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("abcd"));
     assert!(output_as_string.contains("yt@example.com"));
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("Youtube"));
 }
 
@@ -150,6 +156,8 @@ fn test_command_import_csv() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("abcd"));
     assert!(output_as_string.contains("yt@example.com"));
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("Youtube"));
 }
 
@@ -194,5 +202,383 @@ fn test_command_import_1password() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("abcd"));
     assert!(output_as_string.contains("yt@example.com"));
+    assert!(output_as_string.contains("Note: Note"));
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("Youtube"));
 }
+
+#[test]
+fn test_command_import_1password_with_short_rows() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let import_file_1password = tempfile();
+    File::create(import_file_1password.clone())
+        .unwrap()
+        .write_all(
+            "Note,abcd,Youtube,Login,youtube.com,yt@example.com\nNote,efgh,Google,Login\n"
+                .as_bytes(),
+        )
+        .unwrap();
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "1password",
+                import_file_1password.as_path().to_str().unwrap()
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Google, invalid format, skipping"));
+    assert!(output_as_string.contains("Imported: 1"));
+    assert!(output_as_string.contains("Errors: 1"));
+
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "get", "-s", "google"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
+
+#[test]
+fn test_command_import_on_conflict_update() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let import_file_csv = tempfile();
+    File::create(import_file_csv.clone())
+        .unwrap()
+        .write_all("Youtube,new@example.com,efgh\nGoogle,google@example.com,ijkl".as_bytes())
+        .unwrap();
+
+    // By default, existing apps are skipped
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "csv",
+                import_file_csv.as_path().to_str().unwrap()
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+    assert!(output_as_string.contains("yt@example.com"));
+
+    // With --on-conflict update, existing apps are overwritten
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "csv",
+                import_file_csv.as_path().to_str().unwrap(),
+                "--on-conflict",
+                "update"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Updated: 1"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("efgh"));
+    assert!(output_as_string.contains("new@example.com"));
+}
+
+#[test]
+fn test_command_import_bitwarden() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let import_file = tempfile();
+    File::create(import_file.clone()).unwrap().write_all(
+        "{\"encrypted\":false,\"items\":[{\"type\":1,\"name\":\"Youtube\",\"login\":{\"username\":\"yt@example.com\",\"password\":\"abcd\"}},{\"type\":2,\"name\":\"A note\",\"secureNote\":{\"type\":0}},{\"type\":3,\"name\":\"A card\"}]}".as_bytes()
+    ).unwrap();
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "bitwarden",
+                import_file.as_path().to_str().unwrap()
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Skipped 2 item(s)"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+    assert!(output_as_string.contains("yt@example.com"));
+}
+
+#[test]
+fn test_command_import_keepass() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let import_file = tempfile();
+    File::create(import_file.clone())
+        .unwrap()
+        .write_all(
+            "\"Group\",\"Title\",\"Username\",\"Password\",\"URL\",\"Notes\"\n\
+             \"Root\",\"Youtube\",\"new@example.com\",\"efgh\",\"\",\"\"\n\
+             \"Root\",\"Bank, personal\",\"me\",\"a,b\",\"\",\"line 1\nline 2\"\n"
+                .as_bytes(),
+        )
+        .unwrap();
+
+    // Youtube is already there, and we agree to overwrite it
+    let mut io = CursorInputOutput::new("", "xxxx\ny\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "keepass",
+                import_file.as_path().to_str().unwrap()
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Imported: 1"));
+    assert!(output_as_string.contains("Updated: 1"));
+    assert!(output_as_string.contains("Skipped: 0"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("efgh"));
+    assert!(output_as_string.contains("new@example.com"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "-s", "Bank, personal"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("a,b"));
+    assert!(output_as_string.contains("line 1\nline 2"));
+
+    // Saying no keeps the existing password
+    let mut io = CursorInputOutput::new("", "xxxx\nn\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "keepass",
+                import_file.as_path().to_str().unwrap()
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Skipped: 2"));
+}
+
+#[test]
+fn test_command_import_csv_with_notes() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let import_file_csv = tempfile();
+    File::create(import_file_csv.clone())
+        .unwrap()
+        .write_all(
+            "Youtube,yt@example.com,abcd\nGoogle,me@example.com,efgh,recovery code 1234".as_bytes(),
+        )
+        .unwrap();
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "csv",
+                import_file_csv.as_path().to_str().unwrap()
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Imported: 2"));
+    assert!(output_as_string.contains("With notes: 1"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "google"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Note: recovery code 1234"));
+}
+
+#[test]
+fn test_command_import_csv_with_short_rows() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let import_file_csv = tempfile();
+    File::create(import_file_csv.clone())
+        .unwrap()
+        .write_all("Youtube,yt@example.com,abcd\nGoogle,me@example.com\n".as_bytes())
+        .unwrap();
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "csv",
+                import_file_csv.as_path().to_str().unwrap()
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Google, invalid format, skipping"));
+    assert!(output_as_string.contains("Imported: 1"));
+    assert!(output_as_string.contains("Errors: 1"));
+
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "get", "-s", "google"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
@@ -0,0 +1,220 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::fs::File;
+use std::io::Write;
+
+#[test]
+fn test_command_init_wizard() {
+    let rooster_file = tempfile();
+    let import_file = tempfile();
+    File::create(import_file.clone())
+        .unwrap()
+        .write_all("Youtube,yt@example.com,abcd".as_bytes())
+        .unwrap();
+
+    // Location, scrypt setting, master password twice, then import from CSV
+    let input = format!(
+        "\n2\nxxxx\nxxxx\ncsv\n{}\n",
+        import_file.as_path().to_str().unwrap()
+    );
+    let mut io = CursorInputOutput::new("", input.as_str());
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "init"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains(rooster_file.to_str().unwrap()));
+    assert!(output_as_string.contains("Imported: 1"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+}
+
+#[test]
+fn test_command_init_confirmation_mismatch() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "init"],
+            &mut CursorInputOutput::new("", "\n\nxxxx\nyyyy\n\n"),
+            &rooster_file
+        )
+    );
+
+    // Asked again after a typo
+    let mut io = CursorInputOutput::new("", "\n\nxxxx\nyyyy\nxxxx\nxxxx\n\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "init"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("did not match. Let's try again."));
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
+
+#[test]
+fn test_command_init_non_interactive() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests", "--non-interactive"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
+
+#[test]
+fn test_command_init_password_stdin() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests", "--password-stdin"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list", "--password-stdin"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&rooster_file)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // Still not empty
+    let rooster_file = tempfile();
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "init", "--password-stdin"],
+            &mut CursorInputOutput::new("", "\n"),
+            &rooster_file
+        )
+    );
+    assert!(!rooster_file.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_command_init_creates_a_private_file() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    let mode = std::fs::metadata(&rooster_file)
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    // Looser permissions are reported, and fixed by the next save
+    std::fs::set_permissions(&rooster_file, std::fs::Permissions::from_mode(0o644)).unwrap();
+    let mut io = CursorInputOutput::new("", "xxxx\nabcd\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("other users of this computer can read"));
+    let mode = std::fs::metadata(&rooster_file)
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[test]
+fn test_command_init_empty_master_password() {
+    // Asked again
+    let rooster_file = tempfile();
+    let mut io = CursorInputOutput::new("", "\n\n   \nxxxx\nxxxx\n\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "init"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("cannot be empty"));
+    assert!(output_as_string.contains("only 4 characters long"));
+
+    // Scripts fail right away
+    let rooster_file = tempfile();
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "init", "--non-interactive"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert!(!rooster_file.exists());
+
+    // Unless asked not to
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--non-interactive", "--allow-empty"],
+            &mut CursorInputOutput::new("", "\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "\n"),
+            &rooster_file
+        )
+    );
+}
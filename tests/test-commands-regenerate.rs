@@ -68,3 +68,123 @@ fn test_command_regenerate() {
 
     assert_ne!(output_1_as_string, output_2_as_string);
 }
+
+#[test]
+fn test_command_regenerate_selection() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    for app in ["Gmail", "Gmail work", "Youtube"].iter() {
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "add", "-s", *app, "me@example.com"],
+                &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+                &rooster_file
+            )
+        );
+    }
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "regenerate", "--regex", "^Gmail", "--yes", "-s"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Gmail: "));
+    assert!(output_as_string.contains("Gmail work: "));
+    assert!(!output_as_string.contains("Youtube"));
+    assert!(!output_as_string.contains("abcd"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+}
+
+#[test]
+fn test_command_regenerate_selection_as_csv() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    for app in ["Gmail", "Youtube"].iter() {
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "add", "-s", *app, "me@example.com"],
+                &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+                &rooster_file
+            )
+        );
+    }
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "regenerate",
+                "--regex",
+                ".",
+                "--yes",
+                "--output",
+                "csv"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    let lines: Vec<&str> = output_as_string.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "app,username,password");
+    assert!(lines[1].starts_with("Gmail,me@example.com,"));
+    assert!(lines[2].starts_with("Youtube,me@example.com,"));
+    assert!(!output_as_string.contains("abcd"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "regenerate",
+                "--exact",
+                "gmail",
+                "--yes",
+                "--output",
+                "csv",
+                "--no-header"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert_eq!(output_as_string.lines().count(), 1);
+    assert!(output_as_string.starts_with("Gmail,me@example.com,"));
+}
@@ -23,6 +23,25 @@ fn test_command_rename() {
         )
     );
 
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "generate", "-s", "Music", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // The new name must not be taken
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "rename", "youtube", "music"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
     assert_eq!(
         0,
         main_with_args(
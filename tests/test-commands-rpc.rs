@@ -0,0 +1,49 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_rpc() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new(
+        "",
+        "xxxx\n\
+         {\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"add\",\"params\":{\"app\":\"Youtube\",\"username\":\"yt@example.com\",\"password\":\"abcd\"}}\n\
+         {\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"list\"}\n\
+         {\"jsonrpc\":\"2.0\",\"id\":3,\"method\":\"get\",\"params\":{\"app\":\"youtube\"}}\n\
+         {\"jsonrpc\":\"2.0\",\"id\":4,\"method\":\"nope\"}\n",
+    );
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "rpc"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    let lines: Vec<&str> = output_as_string.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":null}");
+    // Listing never gives out passwords
+    assert!(lines[1].contains("Youtube"));
+    assert!(!lines[1].contains("abcd"));
+    assert!(lines[2].contains("\"password\":\"abcd\""));
+    assert!(lines[3].contains("\"code\":-32601"));
+
+    // The password added over RPC was saved at the end of the session
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+}
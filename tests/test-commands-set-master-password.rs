@@ -39,4 +39,116 @@ fn test_command_set_master_password() {
             &rooster_file
         )
     );
+    // Also known as change-master-password
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "change-master-password"],
+            &mut CursorInputOutput::new("", "abcd\nefgh\nefgh\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "efgh\n"),
+            &rooster_file
+        )
+    );
+}
+
+#[test]
+fn test_command_set_master_password_empty() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "set-master-password"],
+            &mut CursorInputOutput::new("", "xxxx\n\n \n\t\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "set-master-password"],
+            &mut CursorInputOutput::new("", "xxxx\n\nabcdefgh\nabcdefgh\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "set-master-password", "--allow-empty"],
+            &mut CursorInputOutput::new("", "abcdefgh\n\n\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "\n"),
+            &rooster_file
+        )
+    );
+}
+
+#[test]
+fn test_command_set_master_password_confirmation_mismatch() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // Three typos in a row
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "set-master-password"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\nabce\nabcd\nabce\nabcd\nabce\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // One typo
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "set-master-password"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\nabce\nabcd\nabcd\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "abcd\n"),
+            &rooster_file
+        )
+    );
 }
@@ -0,0 +1,59 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_verify_password() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\nabcd\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "verify-password", "youtube"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    assert!(!String::from_utf8_lossy(output_as_vecu8.as_slice()).contains("abcd"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\nabce\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "verify-password", "Youtube"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(!output_as_string.contains("abcd"));
+
+    // No fuzzy matching
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "verify-password", "yout"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+}
@@ -42,3 +42,87 @@ fn test_corruption() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("Your Rooster file is corrupted"));
 }
+
+#[test]
+fn test_corruption_recovery_from_backup() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // A write that stopped halfway through
+    let bytes = std::fs::read(&rooster_file).unwrap();
+    std::fs::write(&rooster_file, &bytes[..bytes.len() / 2]).unwrap();
+
+    let mut io = CursorInputOutput::new("", "xxxx\nxxxx\nxxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("from before the last save"));
+
+    // The backup was made right before adding Youtube
+    let mut backup_file = rooster_file.clone().into_os_string();
+    backup_file.push(".bak");
+    std::fs::rename(&backup_file, &rooster_file).unwrap();
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(!output_as_string.contains("Youtube"));
+}
+
+#[test]
+fn test_reading_leaves_the_backup_alone() {
+    let rooster_file = tempfile();
+    let mut backup_file = rooster_file.clone().into_os_string();
+    backup_file.push(".bak");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    let backup = std::fs::read(&backup_file).unwrap();
+    let current = std::fs::read(&rooster_file).unwrap();
+
+    // Neither file is written by commands that only read
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "-s", "youtube"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(std::fs::read(&backup_file).unwrap(), backup);
+    assert_eq!(std::fs::read(&rooster_file).unwrap(), current);
+}
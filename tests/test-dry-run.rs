@@ -0,0 +1,161 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_dry_run_is_never_saved() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // The new password is neither shown nor saved
+    let mut io = CursorInputOutput::new("", "xxxx\nefgh\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "--dry-run",
+                "add",
+                "-s",
+                "Google",
+                "me@example.com"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(!output_as_string.contains("efgh"));
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("dry run"));
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "get", "-s", "google"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "delete", "youtube", "--yes", "--dry-run"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+}
+
+#[test]
+fn test_dry_run_writes_no_file_and_copies_nothing() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // Nothing is copied to the clipboard
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "--dry-run", "get", "youtube"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("I would have copied your password for Youtube"));
+
+    // No backup is written
+    let backup_dir = tempfile();
+    std::fs::create_dir(&backup_dir).unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "--dry-run",
+                "backup",
+                backup_dir.to_str().unwrap()
+            ],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+    assert_eq!(std::fs::read_dir(&backup_dir).unwrap().count(), 0);
+
+    // No export is written
+    let export_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "--dry-run",
+                "export",
+                "csv",
+                "--plaintext",
+                "--output",
+                export_file.to_str().unwrap()
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert!(!export_file.exists());
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "--dry-run", "rename", "youtube", "Vimeo"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("I would have renamed Youtube to Vimeo"));
+}
@@ -0,0 +1,145 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_file_option_overrides_the_password_file() {
+    let rooster_file = tempfile();
+    let other_file = tempfile();
+    let other_path = other_file.to_str().unwrap();
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests", "--file", other_path],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert!(other_file.exists());
+    assert!(!rooster_file.exists());
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "add",
+                "-s",
+                "Youtube",
+                "yt@example.com",
+                "--file",
+                other_path
+            ],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "--file", other_path, "get", "-s", "youtube"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    assert!(String::from_utf8_lossy(output_as_vecu8.as_slice()).contains("abcd"));
+
+    // Without --file, the usual password file is used, and it doesn't exist
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains(rooster_file.to_str().unwrap()));
+}
+
+#[test]
+fn test_file_option_with_attach_add() {
+    let rooster_file = tempfile();
+    let other_file = tempfile();
+    let other_path = other_file.to_str().unwrap();
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests", "--file", other_path],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "add",
+                "-s",
+                "Bank",
+                "me@example.com",
+                "--file",
+                other_path
+            ],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // `attach add` has a positional file argument of its own
+    let input_file = tempfile();
+    std::fs::write(&input_file, b"secret key").unwrap();
+    let input_path = input_file.to_str().unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "attach", "add", "bank", input_path, "--file", other_path],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "--file",
+                other_path,
+                "attach",
+                "add",
+                "bank",
+                input_path,
+                "--name",
+                "other.bin"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert!(!rooster_file.exists());
+
+    let output_file = tempfile();
+    let output_path = output_file.to_str().unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "attach",
+                "get",
+                "bank",
+                "other.bin",
+                output_path,
+                "--file",
+                other_path
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(std::fs::read(&output_file).unwrap(), b"secret key");
+}
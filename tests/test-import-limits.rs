@@ -0,0 +1,85 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::fs::File;
+use std::io::Write;
+
+#[test]
+fn test_import_limits() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let import_file = tempfile();
+    File::create(import_file.clone())
+        .unwrap()
+        .write_all("Youtube,yt@example.com,abcd\nFacebook,fb@example.com,efgh\n".as_bytes())
+        .unwrap();
+    let import_args = [
+        "rooster",
+        "import",
+        "csv",
+        import_file.as_path().to_str().unwrap(),
+    ];
+
+    // Above the hard limit, nothing is imported
+    std::env::set_var("ROOSTER_IMPORT_HARD_LIMIT", "1");
+    assert_eq!(
+        1,
+        main_with_args(
+            &import_args,
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // The file is not even read to the end
+    let broken_file = tempfile();
+    let mut content = b"Youtube,yt@example.com,abcd\nFacebook,fb@example.com,efgh\n".to_vec();
+    content.extend_from_slice(b"\xff\xfe,not,utf8\n");
+    File::create(broken_file.clone())
+        .unwrap()
+        .write_all(&content)
+        .unwrap();
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "csv",
+                broken_file.as_path().to_str().unwrap(),
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("more than the 1 entries"));
+
+    // Above the soft limit, we need a confirmation
+    std::env::set_var("ROOSTER_IMPORT_HARD_LIMIT", "10");
+    std::env::set_var("ROOSTER_IMPORT_SOFT_LIMIT", "1");
+    assert_eq!(
+        1,
+        main_with_args(
+            &import_args,
+            &mut CursorInputOutput::new("", "xxxx\nn\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\ny\n");
+    assert_eq!(0, main_with_args(&import_args, &mut io, &rooster_file));
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Imported: 2"));
+}
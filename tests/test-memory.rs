@@ -0,0 +1,50 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_memory_store_is_never_saved() {
+    let rooster_file = tempfile();
+
+    // No master password is asked for, only the app's password
+    let mut io = CursorInputOutput::new("", "abcd\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "--memory",
+                "add",
+                "-s",
+                "Youtube",
+                "yt@example.com"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+    assert!(!rooster_file.exists());
+
+    // Each run starts from an empty store
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "list", "--memory"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("No passwords on record yet"));
+
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "--memory", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert!(!rooster_file.exists());
+}
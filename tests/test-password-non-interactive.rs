@@ -0,0 +1,64 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_master_password_non_interactive() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // The master password comes first on stdin, then the app's password
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "--password-stdin",
+                "add",
+                "-s",
+                "Youtube",
+                "yt@example.com"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // A wrong password fails right away instead of asking again
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "get", "-s", "youtube", "--password-stdin"],
+            &mut CursorInputOutput::new("", "yyyy\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    std::env::set_var("ROOSTER_MASTER_PASSWORD", "xxxx");
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+
+    std::env::set_var("ROOSTER_MASTER_PASSWORD", "yyyy");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "get", "-s", "youtube"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    std::env::remove_var("ROOSTER_MASTER_PASSWORD");
+}